@@ -4,9 +4,9 @@ use learnopengl::gl_function;
 use learnopengl::program::Program;
 use learnopengl::shader::Shader;
 use learnopengl::vertex_array::VertexArray;
+use learnopengl::window::Window;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::video::GLProfile;
 use std::ptr;
 
 const VERTEX_SHADER: &'static str = include_str!("shaders/01vertex.glsl");
@@ -17,25 +17,7 @@ const VERTICES: [f32; 12] = [
 const INDICES: [u32; 6] = [0, 1, 3, 1, 2, 3];
 
 pub fn main() -> Result<(), String> {
-    let sdl_context = sdl2::init()?;
-    let video_subsystem = sdl_context.video()?;
-    let attrs = video_subsystem.gl_attr();
-
-    attrs.set_context_major_version(3);
-    attrs.set_context_minor_version(3);
-    attrs.set_context_profile(GLProfile::Core);
-    #[cfg(target_os = "macos")]
-    attrs.set_context_flags().forward_compatible().set();
-
-    let window = video_subsystem
-        .window("rust-sdl2 demo: Video", 800, 600)
-        .position_centered()
-        .opengl()
-        .build()
-        .map_err(|e| e.to_string())?;
-    let _opengl = window.gl_create_context().unwrap();
-    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
-    let mut event_pump = sdl_context.event_pump()?;
+    let mut window = Window::new("rust-sdl2 demo: Video", 800, 600)?;
 
     let program = Program::new(vec![
         Shader::new(gl::VERTEX_SHADER, VERTEX_SHADER).unwrap(),
@@ -55,7 +37,7 @@ pub fn main() -> Result<(), String> {
 
     gl_function!(ClearColor(0.3, 0.3, 0.5, 1.0));
     'gameloop: loop {
-        for event in event_pump.poll_iter() {
+        for event in window.events() {
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
@@ -77,7 +59,7 @@ pub fn main() -> Result<(), String> {
         ));
         VertexArray::unbind();
 
-        window.gl_swap_window();
+        window.swap_buffers();
     }
 
     Ok(())
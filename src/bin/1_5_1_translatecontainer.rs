@@ -5,10 +5,10 @@ use learnopengl::program::Program;
 use learnopengl::shader::Shader;
 use learnopengl::texture::{Texture, TextureType};
 use learnopengl::vertex_array::VertexArray;
+use learnopengl::window::Window;
 use nalgebra::{Matrix4, Rotation, Translation3, Vector3};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::video::GLProfile;
 use std::ptr;
 
 const VERTEX_SHADER: &'static str = include_str!("shaders/04.1-transformtexturevertex.glsl");
@@ -20,25 +20,7 @@ const VERTICES: [f32; 20] = [
 const INDICES: [u32; 6] = [0, 1, 3, 1, 2, 3];
 
 pub fn main() -> Result<(), String> {
-    let sdl_context = sdl2::init()?;
-    let video_subsystem = sdl_context.video()?;
-    let attrs = video_subsystem.gl_attr();
-
-    attrs.set_context_major_version(3);
-    attrs.set_context_minor_version(3);
-    attrs.set_context_profile(GLProfile::Core);
-    #[cfg(target_os = "macos")]
-    attrs.set_context_flags().forward_compatible().set();
-
-    let window = video_subsystem
-        .window("rust-sdl2 demo: Video", 800, 600)
-        .position_centered()
-        .opengl()
-        .build()
-        .map_err(|e| e.to_string())?;
-    let _opengl = window.gl_create_context().unwrap();
-    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
-    let mut event_pump = sdl_context.event_pump()?;
+    let mut window = Window::new("rust-sdl2 demo: Video", 800, 600)?;
 
     let program = Program::new(vec![
         Shader::new(gl::VERTEX_SHADER, VERTEX_SHADER).unwrap(),
@@ -83,7 +65,7 @@ pub fn main() -> Result<(), String> {
 
     gl_function!(ClearColor(0.3, 0.3, 0.5, 1.0));
     'gameloop: loop {
-        for event in event_pump.poll_iter() {
+        for event in window.events() {
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
@@ -94,9 +76,8 @@ pub fn main() -> Result<(), String> {
             }
         }
 
-        let mut timer = sdl_context.timer().unwrap();
         let mut transform = Matrix4::<f32>::identity();
-        let rotation = Rotation::from_axis_angle(&Vector3::z_axis(), timer.ticks() as f32);
+        let rotation = Rotation::from_axis_angle(&Vector3::z_axis(), window.ticks() as f32);
         let translate = Translation3::new(0.5, -0.5, 0.0);
         transform *= translate.to_homogeneous() * rotation.to_homogeneous();
         gl_function!(Clear(gl::COLOR_BUFFER_BIT));
@@ -112,8 +93,8 @@ pub fn main() -> Result<(), String> {
             ptr::null()
         ));
 
-        window.gl_swap_window();
-        timer.delay(100);
+        window.swap_buffers();
+        window.delay(100);
     }
 
     Ok(())
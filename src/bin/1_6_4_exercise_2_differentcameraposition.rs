@@ -5,10 +5,10 @@ use learnopengl::program::Program;
 use learnopengl::shader::Shader;
 use learnopengl::texture::{Texture, TextureType};
 use learnopengl::vertex_array::VertexArray;
+use learnopengl::window::Window;
 use nalgebra::{Perspective3, Rotation, Rotation3, Translation3, Vector3};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::video::GLProfile;
 
 const VERTEX_SHADER: &'static str = include_str!("shaders/05.1-coordtexturevertex.glsl");
 const FRAGMENT_SHADER: &'static str = include_str!("shaders/04.1-transformtexturefragment.glsl");
@@ -33,25 +33,7 @@ const VERTICES: [f32; 180] = [
 ];
 
 pub fn main() -> Result<(), String> {
-    let sdl_context = sdl2::init()?;
-    let video_subsystem = sdl_context.video()?;
-    let attrs = video_subsystem.gl_attr();
-
-    attrs.set_context_major_version(3);
-    attrs.set_context_minor_version(3);
-    attrs.set_context_profile(GLProfile::Core);
-    #[cfg(target_os = "macos")]
-    attrs.set_context_flags().forward_compatible().set();
-
-    let window = video_subsystem
-        .window("rust-sdl2 demo: Video", 800, 600)
-        .position_centered()
-        .opengl()
-        .build()
-        .map_err(|e| e.to_string())?;
-    let _opengl = window.gl_create_context().unwrap();
-    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
-    let mut event_pump = sdl_context.event_pump()?;
+    let mut window = Window::new("rust-sdl2 demo: Video", 800, 600)?;
 
     let program = Program::new(vec![
         Shader::new(gl::VERTEX_SHADER, VERTEX_SHADER).unwrap(),
@@ -100,7 +82,7 @@ pub fn main() -> Result<(), String> {
     gl_function!(Enable(gl::DEPTH_TEST));
     gl_function!(ClearColor(0.3, 0.3, 0.5, 1.0));
     'gameloop: loop {
-        for event in event_pump.poll_iter() {
+        for event in window.events() {
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
@@ -111,12 +93,11 @@ pub fn main() -> Result<(), String> {
             }
         }
 
-        let mut sdl_timer = sdl_context.timer().unwrap();
         let rotation_vector = Vector3::new(0.5f32, 1f32, 0f32);
         let it_model = model.to_homogeneous()
             * Rotation3::new(
                 rotation_vector
-                    * (sdl_timer.ticks() as f32 * 50f32.to_radians() / rotation_vector.magnitude()),
+                    * (window.ticks() as f32 * 50f32.to_radians() / rotation_vector.magnitude()),
             )
             .to_homogeneous();
         gl_function!(Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
@@ -127,8 +108,8 @@ pub fn main() -> Result<(), String> {
         vertex_array.bind();
         gl_function!(DrawArrays(gl::TRIANGLES, 0, 36,));
 
-        window.gl_swap_window();
-        sdl_timer.delay(100);
+        window.swap_buffers();
+        window.delay(100);
     }
 
     Ok(())
@@ -96,5 +96,6 @@ pub fn main() -> Result<(), String> {
     }
     game.play_with_fps_camera(vec![
         Box::new(FlashLightSystem { camera: game.camera().clone() })
-    ])
+    ])?;
+    Ok(())
 }
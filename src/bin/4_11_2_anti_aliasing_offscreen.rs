@@ -5,6 +5,7 @@ use learnopengl::buffer::Buffer;
 use learnopengl::cube::cube_mesh;
 use learnopengl::ecs::components::{Skybox, TextureInfo, Transform};
 use learnopengl::ecs::systems::system::System;
+use learnopengl::error::Error;
 use learnopengl::frame_buffer::FrameBuffer;
 use learnopengl::game::Game;
 use learnopengl::gl_function;
@@ -33,8 +34,8 @@ struct FrameBufferSystem {
 
 impl FrameBufferSystem {
     pub fn new() -> Result<FrameBufferSystem, String> {
-        let frame_buffer = FrameBuffer::multisample(800, 600);
-        let intermediate_frame_buffer = FrameBuffer::intermediate(800, 600);
+        let frame_buffer = FrameBuffer::multisample(800, 600)?;
+        let intermediate_frame_buffer = FrameBuffer::intermediate(800, 600)?;
         let program = Program::new(vec![
             Shader::new(ShaderType::Vertex as _, include_str!("shaders/15.1-postprocessing_vertex.glsl"))?,
             Shader::new(ShaderType::Fragment as _, include_str!("shaders/15.1-postprocessing_fragment.glsl"))?
@@ -63,11 +64,11 @@ impl System for FrameBufferSystem {
         "Frame Buffer"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         gl_function!(ClearColor(0.1f32, 0.1f32, 0.1f32, 1f32));
         gl_function!(Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
         self.frame_buffer.bind();
@@ -77,11 +78,11 @@ impl System for FrameBufferSystem {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         self.frame_buffer.read_bind();
         self.intermediate_frame_buffer.draw_bind();
         gl_function!(BlitFramebuffer(0, 0, 800, 600, 0, 0, 800, 600, gl::COLOR_BUFFER_BIT, gl::NEAREST));
@@ -7,6 +7,7 @@ use learnopengl::cube::cube_mesh;
 use learnopengl::ecs::components::{Input, TextureInfo, Transform};
 use learnopengl::ecs::systems::input::InputType;
 use learnopengl::ecs::systems::system::System;
+use learnopengl::error::Error;
 use learnopengl::game::Game;
 use learnopengl::gl_function;
 use learnopengl::light::DirectionalLight;
@@ -24,11 +25,11 @@ impl System for ControlDepth {
         "control depth"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
         for (_e, (input, _)) in world.query_mut::<(&Input, &DepthControl)>() {
             for event in input.events.iter() {
                 if let Event::KeyDown {
@@ -45,11 +46,11 @@ impl System for ControlDepth {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 }
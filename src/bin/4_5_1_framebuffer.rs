@@ -1,93 +1,13 @@
-use hecs::World;
 use nalgebra::{Rotation3, UnitVector3, Vector3};
 use russimp::texture::TextureType;
-use learnopengl::buffer::Buffer;
+use sdl2::keyboard::Keycode;
 use learnopengl::cube::cube_mesh;
-use learnopengl::ecs::components::{TextureInfo, Transform};
-use learnopengl::ecs::systems::system::System;
-use learnopengl::frame_buffer::FrameBuffer;
+use learnopengl::ecs::components::{Input, PostEffectControl, TextureInfo, Transform};
+use learnopengl::ecs::systems::input::InputType;
+use learnopengl::ecs::systems::post_processing::{BLUR_KERNEL, EDGE_DETECTION_KERNEL, GREYSCALE_FRAGMENT_SHADER, INVERSION_FRAGMENT_SHADER, SHARPEN_KERNEL};
 use learnopengl::game::Game;
-use learnopengl::gl_function;
 use learnopengl::light::DirectionalLight;
 use learnopengl::plane::build_plane;
-use learnopengl::program::Program;
-use learnopengl::shader::Shader;
-use learnopengl::shader_loader::ShaderType;
-use learnopengl::vertex_array::VertexArray;
-
-const QUAD_VERTICES: [f32; 24] = [
-    -1f32, 1f32, 0f32, 1f32,
-    -1f32, -1f32, 0f32, 0f32,
-    1f32, -1f32, 1f32, 0f32,
-    -1f32, 1f32, 0f32, 1f32,
-    1f32, -1f32, 1f32, 0f32,
-    1f32, 1f32, 1f32, 1f32,
-];
-
-struct FrameBufferSystem {
-    frame_buffer: FrameBuffer,
-    program: Program,
-    vao: VertexArray,
-    _vbo: Buffer,
-}
-
-impl FrameBufferSystem {
-    pub fn new() -> Result<FrameBufferSystem, String> {
-        let frame_buffer = FrameBuffer::new(800, 600);
-        let program = Program::new(vec![
-            Shader::new(ShaderType::Vertex as _, include_str!("shaders/15.1-postprocessing_vertex.glsl"))?,
-            Shader::new(ShaderType::Fragment as _, include_str!("shaders/15.1-postprocessing_fragment.glsl"))?
-        ])?;
-        program.use_program();
-        program.set_uniform_i1("texture1", 0);
-        let vao = VertexArray::new();
-        let vbo = Buffer::new(gl::ARRAY_BUFFER);
-        vao.bind();
-        vbo.bind();
-        vbo.set_data(&QUAD_VERTICES, gl::STATIC_DRAW);
-        VertexArray::set_vertex_attrib_with_padding::<f32>(gl::FLOAT, 0, 4, 2, 0, false);
-        VertexArray::set_vertex_attrib_with_padding::<f32>(gl::FLOAT, 1, 4, 2, 2, false);
-        Ok(FrameBufferSystem {
-            frame_buffer,
-            program,
-            vao,
-            _vbo: vbo,
-        })
-    }
-}
-
-impl System for FrameBufferSystem {
-    fn name(&self) -> &str {
-        "Frame Buffer"
-    }
-
-    fn start(&self, _world: &mut World) -> Result<(), String> {
-        Ok(())
-    }
-
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
-        self.frame_buffer.bind();
-        gl_function!(Enable(gl::DEPTH_TEST));
-        gl_function!(ClearColor(0.1f32, 0.1f32, 0.1f32, 1f32));
-        Ok(())
-    }
-
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
-        Ok(())
-    }
-
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
-        FrameBuffer::unbind();
-        gl_function!(ClearColor(1f32, 1f32, 1f32, 1f32));
-        gl_function!(Clear(gl::COLOR_BUFFER_BIT));
-        self.program.use_program();
-        self.vao.bind();
-        gl_function!(Disable(gl::DEPTH_TEST));
-        self.frame_buffer.texture.bind(gl::TEXTURE0);
-        gl_function!(DrawArrays(gl::TRIANGLES, 0, 6));
-        Ok(())
-    }
-}
 
 pub fn main() -> Result<(), String> {
     let mut game = Game::new(
@@ -139,6 +59,25 @@ pub fn main() -> Result<(), String> {
         rotation: Rotation3::identity(),
     })?;
     game.spawn_mesh(&floor, Transform::identity())?;
-    game.play_with_fps_camera(vec![Box::new(FrameBufferSystem::new()?)])?;
+    let mut post_process_stack = game.post_process_stack()?;
+    post_process_stack.add_effect("inversion", INVERSION_FRAGMENT_SHADER)?;
+    post_process_stack.add_effect("greyscale", GREYSCALE_FRAGMENT_SHADER)?.set_enabled(false);
+    post_process_stack.add_kernel("sharpen", SHARPEN_KERNEL)?.set_enabled(false);
+    post_process_stack.add_kernel("blur", BLUR_KERNEL)?.set_enabled(false);
+    post_process_stack.add_kernel("edge detection", EDGE_DETECTION_KERNEL)?.set_enabled(false);
+    for (keycode, effect) in [
+        (Keycode::Num1, "inversion"),
+        (Keycode::Num2, "greyscale"),
+        (Keycode::Num3, "sharpen"),
+        (Keycode::Num4, "blur"),
+        (Keycode::Num5, "edge detection"),
+    ] {
+        game.spawn((Input::new(vec![InputType::Keyboard]), PostEffectControl {
+            keycode,
+            effect: effect.to_string(),
+        }));
+    }
+    game.set_post_process_stack(post_process_stack);
+    game.play_with_fps_camera(vec![])?;
     Ok(())
 }
\ No newline at end of file
@@ -6,6 +6,7 @@ use learnopengl::buffer::Buffer;
 use learnopengl::cube::cube_mesh;
 use learnopengl::ecs::components::{TextureInfo, Transform};
 use learnopengl::ecs::systems::system::System;
+use learnopengl::error::Error;
 use learnopengl::frame_buffer::FrameBuffer;
 use learnopengl::game::Game;
 use learnopengl::gl_function;
@@ -45,7 +46,7 @@ struct FrameBufferSystem {
 
 impl FrameBufferSystem {
     pub fn new() -> Result<FrameBufferSystem, String> {
-        let frame_buffer = FrameBuffer::new(800, 600);
+        let frame_buffer = FrameBuffer::new(800, 600)?;
         let program = Program::new(vec![
             Shader::new(ShaderType::Vertex as _, include_str!("shaders/15.2-postprocessing_mirror_vertex.glsl"))?,
             Shader::new(ShaderType::Fragment as _, include_str!("shaders/15.1-postprocessing_fragment.glsl"))?
@@ -82,22 +83,22 @@ impl System for FrameBufferSystem {
         "Frame Buffer"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         self.frame_buffer.bind();
         gl_function!(Enable(gl::DEPTH_TEST));
         gl_function!(ClearColor(0.1f32, 0.1f32, 0.1f32, 1f32));
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         FrameBuffer::unbind();
         gl_function!(ClearColor(1f32, 1f32, 1f32, 1f32));
         gl_function!(Clear(gl::COLOR_BUFFER_BIT));
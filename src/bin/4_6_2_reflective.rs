@@ -7,6 +7,7 @@ use learnopengl::camera::Camera;
 use learnopengl::cube::cube_mesh;
 use learnopengl::ecs::components::{SkipRendering, Shader as RenderingShader, Skybox, TextureInfo, Transform, Mesh, Border, Transparent};
 use learnopengl::ecs::systems::system::System;
+use learnopengl::error::Error;
 use learnopengl::game::Game;
 use learnopengl::gl_function;
 use learnopengl::light::DirectionalLight;
@@ -39,21 +40,21 @@ impl System for ReflectionSystem {
         "Reflection System"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         self.program.use_program();
         self.program.set_uniform_i1("skybox", 0);
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
         let skybox = world.query_mut::<&RenderingShader>().with::<Skybox>().into_iter().next()
             .map(|(_e, s) | s.textures.get(0).unwrap().clone());
         if let Some(texture) = skybox {
@@ -8,6 +8,7 @@ use learnopengl::camera::Camera;
 use learnopengl::cube::cube_mesh;
 use learnopengl::ecs::components::{Model, Transform};
 use learnopengl::ecs::systems::system::System;
+use learnopengl::error::Error;
 use learnopengl::game::Game;
 use learnopengl::gl_function;
 use learnopengl::light::{DirectionalLight, PointLight};
@@ -44,19 +45,19 @@ impl System for NormalSystem {
         "Exploding system"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, world: &mut World, delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, world: &mut World, delta_time: f32) -> Result<(), Error> {
         let projection = (*self.camera).borrow().projection();
         let view = (*self.camera).borrow().look_at_matrix();
         self.program.use_program();
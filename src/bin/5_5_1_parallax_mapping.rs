@@ -5,6 +5,7 @@ use russimp::texture::TextureType;
 use learnopengl::cube::cube_mesh;
 use learnopengl::ecs::components::{TextureInfo, Transform};
 use learnopengl::ecs::systems::system::System;
+use learnopengl::error::Error;
 use learnopengl::game::Game;
 use learnopengl::light::PointLight;
 use learnopengl::plane::build_plane;
@@ -18,15 +19,15 @@ impl System for RotationSystem {
         "Rotation System"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn update(&self, world: &mut World, delta_time: f32) -> Result<(), String> {
+    fn update(&self, world: &mut World, delta_time: f32) -> Result<(), Error> {
         let total_ticks = *self.total_ticks.borrow() + delta_time;
         self.total_ticks.replace(total_ticks);
         for (_e, transform) in world.query_mut::<&mut Transform>().without::<PointLight>() {
@@ -38,7 +39,7 @@ impl System for RotationSystem {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 }
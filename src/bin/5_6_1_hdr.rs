@@ -1,100 +1,71 @@
-use std::cell::RefCell;
+use std::cell::Cell;
+use std::rc::Rc;
 use hecs::World;
 use nalgebra::{Rotation3, Vector3};
 use russimp::texture::TextureType;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use learnopengl::buffer::Buffer;
 use learnopengl::cube::cube_mesh;
-use learnopengl::ecs::components::{Input, TextureInfo, Transform};
+use learnopengl::ecs::components::{Input, TextureInfo, Transform, UniformValue};
 use learnopengl::ecs::systems::input::InputType;
+use learnopengl::ecs::systems::post_processing::PostEffect;
+use learnopengl::ecs::systems::post_processing::tone_mapping::{AutoExposure, AutoExposureSettings, ToneMappingOperator};
 use learnopengl::ecs::systems::system::System;
-use learnopengl::frame_buffer::FrameBuffer;
+use learnopengl::error::Error;
 use learnopengl::game::Game;
-use learnopengl::gl_function;
 use learnopengl::light::PointLight;
-use learnopengl::program::Program;
-use learnopengl::shader::Shader;
-use learnopengl::shader_loader::ShaderType;
-use learnopengl::texture::TextureFormat;
-use learnopengl::vertex_array::VertexArray;
 
-const QUAD_VERTICES: [f32; 24] = [
-    -1f32, 1f32, 0f32, 1f32,
-    -1f32, -1f32, 0f32, 0f32,
-    1f32, -1f32, 1f32, 0f32,
-    -1f32, 1f32, 0f32, 1f32,
-    1f32, -1f32, 1f32, 0f32,
-    1f32, 1f32, 1f32, 1f32,
-];
-
-struct FrameBufferSystem {
-    exposure: RefCell<f32>,
-    frame_buffer: FrameBuffer,
-    program: Program,
-    vao: VertexArray,
-    _vbo: Buffer,
+struct ExposureSystem {
+    auto_exposure: Rc<AutoExposure>,
+    effect: Rc<PostEffect>,
+    operator: Cell<ToneMappingOperator>,
 }
 
 struct ExposureControl(Keycode, Keycode);
 
-impl FrameBufferSystem {
-    pub fn new() -> Result<FrameBufferSystem, String> {
-        let frame_buffer = FrameBuffer::new_with_format(800, 600, TextureFormat::FloatingPoint);
-        let program = Program::new(vec![
-            Shader::new(ShaderType::Vertex as _, include_str!("shaders/15.1-postprocessing_vertex.glsl"))?,
-            Shader::new(ShaderType::Fragment as _, include_str!("shaders/24.1-hdr_fragment.glsl"))?
-        ])?;
-        program.use_program();
-        program.set_uniform_i1("texture1", 0);
-        program.set_uniform_f1("exposure", 1f32);
-        let vao = VertexArray::new();
-        let vbo = Buffer::new(gl::ARRAY_BUFFER);
-        vao.bind();
-        vbo.bind();
-        vbo.set_data(&QUAD_VERTICES, gl::STATIC_DRAW);
-        VertexArray::set_vertex_attrib_with_padding::<f32>(gl::FLOAT, 0, 4, 2, 0, false);
-        VertexArray::set_vertex_attrib_with_padding::<f32>(gl::FLOAT, 1, 4, 2, 2, false);
-        Ok(FrameBufferSystem {
-            exposure: RefCell::new(1f32),
-            frame_buffer,
-            program,
-            vao,
-            _vbo: vbo,
-        })
-    }
-}
+struct ToneMappingControl(Keycode, Keycode);
 
-impl System for FrameBufferSystem {
+impl System for ExposureSystem {
     fn name(&self) -> &str {
-        "Frame Buffer"
+        "Exposure"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
-        self.frame_buffer.bind();
-        gl_function!(Enable(gl::DEPTH_TEST));
-        gl_function!(ClearColor(0.1f32, 0.1f32, 0.1f32, 1f32));
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         for (_e, (input, exposure_control)) in _world.query_mut::<(&Input, &ExposureControl)>() {
             for e in input.events.iter() {
-                let mut exposure = *self.exposure.borrow();
+                let exposure = match self.effect.parameter("exposure") {
+                    Some(UniformValue::Float(exposure)) => exposure,
+                    _ => 1f32,
+                };
                 match &e {
                     Event::KeyDown { keycode: Some(k), .. } if k == &exposure_control.0 => {
-                        exposure -= 1f32;
-                        self.program.set_uniform_f1("exposure", exposure);
-                        self.exposure.replace(exposure);
+                        self.effect.set_parameter("exposure", UniformValue::Float(exposure - 1f32));
                     }
                     Event::KeyDown { keycode: Some(k), .. } if k == &exposure_control.1 => {
-                        exposure += 1f32;
-                        self.program.set_uniform_f1("exposure", exposure);
-                        self.exposure.replace(exposure);
+                        self.effect.set_parameter("exposure", UniformValue::Float(exposure + 1f32));
+                    }
+                    _ => {},
+                }
+            }
+        }
+        for (_e, (input, tone_mapping_control)) in _world.query_mut::<(&Input, &ToneMappingControl)>() {
+            for e in input.events.iter() {
+                match &e {
+                    Event::KeyDown { keycode: Some(k), repeat: false, .. } if k == &tone_mapping_control.0 => {
+                        let operator = self.operator.get().next();
+                        self.effect.set_parameter("tone_mapping_operator", operator.uniform());
+                        self.operator.set(operator);
+                    }
+                    Event::KeyDown { keycode: Some(k), repeat: false, .. } if k == &tone_mapping_control.1 => {
+                        self.auto_exposure.set_enabled(!self.auto_exposure.is_enabled());
                     }
                     _ => {},
                 }
@@ -103,15 +74,7 @@ impl System for FrameBufferSystem {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
-        FrameBuffer::unbind();
-        gl_function!(ClearColor(1f32, 1f32, 1f32, 1f32));
-        gl_function!(Clear(gl::COLOR_BUFFER_BIT));
-        self.program.use_program();
-        self.vao.bind();
-        gl_function!(Disable(gl::DEPTH_TEST));
-        self.frame_buffer.texture.bind(gl::TEXTURE0);
-        gl_function!(DrawArrays(gl::TRIANGLES, 0, 6));
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 }
@@ -162,6 +125,16 @@ pub fn main() -> Result<(), String> {
         rotation: Rotation3::identity(),
     })?;
     game.spawn((Input::new(vec![InputType::Keyboard]), ExposureControl(Keycode::Q, Keycode::E)));
-    game.play_with_fps_camera(vec![Box::new(FrameBufferSystem::new()?)])?;
+    game.spawn((Input::new(vec![InputType::Keyboard]), ToneMappingControl(Keycode::T, Keycode::Y)));
+    let mut post_process_stack = game.post_process_stack()?;
+    let auto_exposure = Rc::new(AutoExposure::new(post_process_stack.shader_loader(), AutoExposureSettings::default())?);
+    let operator = ToneMappingOperator::Reinhard;
+    let effect = post_process_stack.add_tone_mapping("tone mapping", operator, Some(auto_exposure.clone()))?;
+    game.set_post_process_stack(post_process_stack);
+    game.play_with_fps_camera(vec![Box::new(ExposureSystem {
+        auto_exposure,
+        effect,
+        operator: Cell::new(operator),
+    })])?;
     Ok(())
 }
\ No newline at end of file
@@ -1,172 +1,52 @@
-#![feature(variant_count)]
-#![feature(iter_advance_by)]
-
-use std::cell::RefCell;
-use std::iter::{Cycle, Peekable};
+use std::cell::Cell;
 use std::rc::Rc;
-use std::slice::Iter;
 use hecs::World;
-use include_dir::{Dir, include_dir};
 use itertools::Itertools;
 use nalgebra::{Rotation3, Vector3};
 use rand::{Rng, thread_rng};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use learnopengl::buffer::Buffer;
-use learnopengl::camera::Camera;
 use learnopengl::cube::cube_mesh;
-use learnopengl::ecs::components::{Input, Mesh, Shader as MeshShader, SkipRendering, Transform};
+use learnopengl::ecs::components::{Input, SkipRendering, Transform};
 use learnopengl::ecs::systems::input::InputType;
+use learnopengl::ecs::systems::rendering::RenderingPath;
+use learnopengl::ecs::systems::rendering::deferred::GBufferView;
 use learnopengl::ecs::systems::system::System;
-use learnopengl::frame_buffer::FrameBuffer;
+use learnopengl::error::Error;
 use learnopengl::game::Game;
-use learnopengl::gl_function;
-use learnopengl::light::{Light, PointLight};
-use learnopengl::multiple_render_target::MultipleRenderTarget;
-use learnopengl::program::Program;
-use learnopengl::shader::Shader;
-use learnopengl::shader_loader::{ShaderLoader, ShaderType};
-use learnopengl::texture::TextureFormat;
-use learnopengl::vertex_array::VertexArray;
-use crate::RenderingTarget::*;
-
-const QUAD_VERTICES: [f32; 24] = [
-    -1f32, 1f32, 0f32, 1f32,
-    -1f32, -1f32, 0f32, 0f32,
-    1f32, -1f32, 1f32, 0f32,
-    -1f32, 1f32, 0f32, 1f32,
-    1f32, -1f32, 1f32, 0f32,
-    1f32, 1f32, 1f32, 1f32,
-];
-static SHADERS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/src/bin/shaders");
-
-#[derive(Clone, Copy, Debug)]
-enum RenderingTarget {
-    DeferredShading = 0,
-    Position = 1,
-    Normal = 2,
-    Albedo = 3,
-    Specular = 4,
-}
+use learnopengl::light::PointLight;
 
-impl RenderingTarget {
-    fn iterator() -> Cycle<Iter<'static, RenderingTarget>> {
-        static VALUES: [RenderingTarget; 5] = [DeferredShading, Position, Normal, Albedo, Specular];
-        VALUES.iter().cycle()
-    }
-}
-
-struct FrameBufferSystem {
-    camera: Rc<RefCell<Camera>>,
-    program: Program,
-    frame_buffer: MultipleRenderTarget,
-    light_program: Program,
-    quad_program: Program,
-    greyscale_quad_program: Program,
-    targets: RefCell<Peekable<Cycle<Iter<'static, RenderingTarget>>>>,
-    vao: VertexArray,
-    _vbo: Buffer,
+struct GBufferViewSystem {
+    view: Rc<Cell<GBufferView>>,
 }
 
-struct RenderingControl {
+struct GBufferViewControl {
     forward: Keycode,
     backward: Keycode,
 }
 
-impl FrameBufferSystem {
-    pub fn new(camera: Rc<RefCell<Camera>>) -> Result<FrameBufferSystem, String> {
-        let shader_loader = ShaderLoader::new(&SHADERS_DIR);
-        let frame_buffer = MultipleRenderTarget::new_with_formats(800, 600, &vec![
-            TextureFormat::FloatingPoint, TextureFormat::FloatingPoint, TextureFormat::UnsignedByteWithAlpha,
-        ]);
-        let quad_program = Program::new(vec![
-            Shader::new(ShaderType::Vertex as _, include_str!("shaders/15.1-postprocessing_vertex.glsl"))?,
-            Shader::new(ShaderType::Fragment as _, include_str!("shaders/26.1-quad_fragment.glsl"))?
-        ])?;
-        let greyscale_quad_program = Program::new(vec![
-            Shader::new(ShaderType::Vertex as _, include_str!("shaders/15.1-postprocessing_vertex.glsl"))?,
-            Shader::new(ShaderType::Fragment as _, include_str!("shaders/26.1-greyscale_quad_fragment.glsl"))?
-        ])?;
-        let program = Program::new(vec![
-            Shader::new(ShaderType::Vertex as _, include_str!("shaders/25.1-blur_vertex.glsl"))?,
-            shader_loader.load(ShaderType::Fragment, "26.1-lighting_pass_fragment.glsl")?,
-        ])?;
-        let light_program = Program::new(vec![
-            Shader::new(ShaderType::Vertex as _, include_str!("shaders/26.1-bloom_light_vertex.glsl"))?,
-            Shader::new(ShaderType::Fragment as _, include_str!("shaders/26.1-bloom_light_fragment.glsl"))?,
-        ])?;
-        quad_program.use_program();
-        quad_program.set_uniform_i1("texture1", 0);
-        greyscale_quad_program.use_program();
-        greyscale_quad_program.set_uniform_i1("texture1", 0);
-        program.use_program();
-        program.set_uniform_i1("gPosition", 0);
-        program.set_uniform_i1("gNormal", 1);
-        program.set_uniform_i1("gAlbedoSpec", 2);
-        light_program.use_program();
-        light_program.bind_uniform_block("Matrices", 0);
-        let vao = VertexArray::new();
-        let vbo = Buffer::new(gl::ARRAY_BUFFER);
-        vao.bind();
-        vbo.bind();
-        vbo.set_data(&QUAD_VERTICES, gl::STATIC_DRAW);
-        VertexArray::set_vertex_attrib_with_padding::<f32>(gl::FLOAT, 0, 4, 2, 0, false);
-        VertexArray::set_vertex_attrib_with_padding::<f32>(gl::FLOAT, 1, 4, 2, 2, false);
-        Ok(FrameBufferSystem {
-            camera,
-            light_program,
-            program,
-            frame_buffer,
-            greyscale_quad_program,
-            quad_program,
-            vao,
-            targets: RefCell::new(RenderingTarget::iterator().peekable()),
-            _vbo: vbo,
-        })
-    }
-}
-
-impl System for FrameBufferSystem {
+impl System for GBufferViewSystem {
     fn name(&self) -> &str {
-        "Frame Buffer"
+        "G-buffer View"
     }
 
-    fn start(&self, world: &mut World) -> Result<(), String> {
-        self.frame_buffer.bind();
-        self.frame_buffer.set_draw_buffers();
-        MultipleRenderTarget::unbind();
-        self.program.use_program();
-        for (i, (_e, light)) in world.query_mut::<&PointLight>().into_iter().enumerate() {
-            let light_max = light.diffuse.get(0).unwrap()
-                .max(*light.diffuse.get(1).unwrap())
-                .max(*light.diffuse.get(2).unwrap());
-            let radius = (-light.linear + (light.linear.powi(2)
-                - 4f32 * light.quadratic * (light.constant - 256f32/5f32 * light_max)).sqrt()) / (2f32 * light.quadratic);
-            let name = format!("point_lights[{}]", i);
-            light.set_light_in_program(&self.program, &name);
-            self.program.set_uniform_i1(&format!("{}.set", name), 1);
-            self.program.set_uniform_f1(&format!("radiuses[{}]", i), radius);
-        }
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
-        self.frame_buffer.bind();
-        gl_function!(ClearColor(0f32, 0f32, 0f32, 0f32));
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
-        for (_e, (input, rendering_control)) in _world.query_mut::<(&Input, &RenderingControl)>() {
+    fn update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        for (_e, (input, control)) in world.query_mut::<(&Input, &GBufferViewControl)>() {
             for e in input.events.iter() {
                 match &e {
-                    Event::KeyDown { keycode: Some(k), repeat, .. } if !*repeat && k == &rendering_control.backward => {
-                        self.targets.borrow_mut().
-                            advance_by(std::mem::variant_count::<RenderingTarget>() - 1)
-                            .map_err(|e| e.to_string())?;
+                    Event::KeyDown { keycode: Some(k), repeat: false, .. } if k == &control.backward => {
+                        self.view.set(self.view.get().previous());
                     }
-                    Event::KeyDown { keycode: Some(k), repeat, .. } if !*repeat && k == &rendering_control.forward => {
-                        self.targets.borrow_mut().next();
+                    Event::KeyDown { keycode: Some(k), repeat: false, .. } if k == &control.forward => {
+                        self.view.set(self.view.get().next());
                     }
                     _ => {},
                 }
@@ -175,61 +55,7 @@ impl System for FrameBufferSystem {
         Ok(())
     }
 
-    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), String> {
-        MultipleRenderTarget::unbind();
-        self.vao.bind();
-
-        gl_function!(ClearColor(0f32, 0f32, 0f32, 1f32));
-        gl_function!(Clear(gl::COLOR_BUFFER_BIT));
-        match self.targets.borrow_mut().peek().unwrap().clone() {
-            RenderingTarget::Position => {
-                gl_function!(Disable(gl::DEPTH_TEST));
-                self.quad_program.use_program();
-                self.frame_buffer.textures.get(0).unwrap().bind(gl::TEXTURE0);
-                gl_function!(DrawArrays(gl::TRIANGLES, 0, 6));
-            },
-            RenderingTarget::Normal => {
-                gl_function!(Disable(gl::DEPTH_TEST));
-                self.quad_program.use_program();
-                self.frame_buffer.textures.get(1).unwrap().bind(gl::TEXTURE0);
-                gl_function!(DrawArrays(gl::TRIANGLES, 0, 6));
-            },
-            RenderingTarget::Albedo => {
-                gl_function!(Disable(gl::DEPTH_TEST));
-                self.quad_program.use_program();
-                self.frame_buffer.textures.get(2).unwrap().bind(gl::TEXTURE0);
-                gl_function!(DrawArrays(gl::TRIANGLES, 0, 6));
-            },
-            RenderingTarget::Specular => {
-                gl_function!(Disable(gl::DEPTH_TEST));
-                self.greyscale_quad_program.use_program();
-                self.frame_buffer.textures.get(2).unwrap().bind(gl::TEXTURE0);
-                gl_function!(DrawArrays(gl::TRIANGLES, 0, 6));
-            },
-            RenderingTarget::DeferredShading => {
-                gl_function!(Enable(gl::DEPTH_TEST));
-                self.program.use_program();
-                self.program.set_uniform_v3("viewPos", self.camera.borrow().position());
-                for (i, texture) in self.frame_buffer.textures.iter().enumerate() {
-                    texture.bind(gl::TEXTURE0 + i as u32);
-                }
-                gl_function!(DrawArrays(gl::TRIANGLES, 0, 6));
-
-                gl_function!(BindFramebuffer(gl::READ_FRAMEBUFFER, self.frame_buffer.resource));
-                gl_function!(BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0));
-                gl_function!(BlitFramebuffer(0, 0, 800, 600, 0, 0, 800, 600, gl::DEPTH_BUFFER_BIT, gl::NEAREST));
-                FrameBuffer::unbind();
-                self.light_program.use_program();
-                for (_e, (light, mesh, shader)) in world.query_mut::<(&PointLight, &Mesh, &MeshShader)>() {
-                    self.light_program.set_uniform_matrix4("model", &light.model);
-                    self.light_program.set_uniform_v3("light.diffuse", light.diffuse);
-                    let n_vertices = mesh.vertices.len();
-                    shader.vertex_array.bind();
-                    gl_function!(DrawArrays(gl::TRIANGLES, 0, n_vertices as i32,));
-                    VertexArray::unbind();
-                }
-            }
-        };
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 }
@@ -242,11 +68,12 @@ pub fn main() -> Result<(), String> {
         60,
         Vector3::new(0f32, 0f32, 0f32),
         "17.1-uniform_buffer_objects_vertex.glsl",
-        "26.1-gbuffer_fragment.glsl",
+        "12.1-modelloading.glsl",
         "17.1-uniform_buffer_objects_vertex.glsl",
-        "25.1-bloom_light_fragment.glsl",
+        "09.1-lightfragment.glsl",
         16,
     )?;
+    game.set_rendering_path(RenderingPath::Deferred);
     let mut rnd = thread_rng();
     let mut light_cube = cube_mesh(vec![]);
     light_cube.vertices = light_cube.vertices.iter().map(|v| v * 0.25).collect_vec();
@@ -293,10 +120,11 @@ pub fn main() -> Result<(), String> {
             scale: Vector3::new(1f32, 1f32, 1f32),
         })?;
     }
-    game.spawn((Input::new(vec![InputType::Keyboard]), RenderingControl {
+    game.spawn((Input::new(vec![InputType::Keyboard]), GBufferViewControl {
         backward: Keycode::Q,
         forward: Keycode::E,
     }));
-    game.play_with_fps_camera(vec![Box::new(FrameBufferSystem::new(game.camera())?)])?;
+    let view = game.g_buffer_view()?;
+    game.play_with_fps_camera(vec![Box::new(GBufferViewSystem { view })])?;
     Ok(())
 }
@@ -3,8 +3,9 @@
 
 use itertools::Itertools;
 use nalgebra::{Rotation3, Vector3};
+use russimp::texture::TextureType;
 use learnopengl::cube::cube_mesh;
-use learnopengl::ecs::components::{ExtraUniform, Transform, UniformValue};
+use learnopengl::ecs::components::{ExtraUniform, Skybox, TextureInfo, Transform, UniformValue};
 use learnopengl::game::Game;
 use learnopengl::light::PointLight;
 use learnopengl::sphere::sphere_mesh;
@@ -69,6 +70,13 @@ pub fn main() -> Result<(), String> {
             ])?;
         }
     }
+    game.spawn_skybox(&Skybox {
+        texture_info: TextureInfo {
+            id: 0,
+            texture_type: TextureType::None,
+            path: format!("{}/../LOGL/resources/textures/hdr/newport_loft.hdr", env!("CARGO_MANIFEST_DIR")),
+        }
+    })?;
     game.play_with_fps_camera(vec![])?;
     Ok(())
 }
@@ -4,11 +4,8 @@
 #include "directional_light.glsl"
 #include "point_light.glsl"
 #include "spot_light.glsl"
+#include "lights.glsl"
 
-#define MAX_LIGHTS 4
-uniform DirectionalLight directional_lights[MAX_LIGHTS];
-uniform PointLight point_lights[MAX_LIGHTS];
-uniform SpotLight spot_lights[MAX_LIGHTS];
 uniform Material material;
 uniform vec3 viewPos;
 
@@ -28,15 +25,22 @@ void main()
     vec3 viewDir = normalize(viewPos - FragPos);
 
     vec3 result = vec3(0.0);
-    for (int i = 0; i < MAX_LIGHTS; i++) {
-        result += calculateDirectionalLight(directional_lights[i], material, norm, viewDir, TexCoords);
+    for (int i = 0; i < n_directional_lights; i++) {
+        result += calculateDirectionalLight(directional_lights[i], material, norm, viewDir, TexCoords, directionalLightShadow(i, FragPos));
     }
-    for (int i = 0; i < MAX_LIGHTS; i++) {
-        result += calculatePointLight(point_lights[i], material, norm, FragPos, viewDir, TexCoords);
+    uvec4 cluster = currentCluster();
+    LightList pointLights = pointLightList(cluster);
+    for (int j = 0; j < pointLights.count; j++) {
+        int i = lightIndex(pointLights, j);
+        result += calculatePointLight(point_lights[i], material, norm, FragPos, viewDir, TexCoords, pointLightShadow(i, FragPos));
     }
-    for (int i = 0; i < MAX_LIGHTS; i++) {
-        result += calculateSpotLight(spot_lights[i], material, norm, FragPos, viewDir, TexCoords);
+    LightList spotLights = spotLightList(cluster);
+    for (int j = 0; j < spotLights.count; j++) {
+        int i = lightIndex(spotLights, j);
+        result += calculateSpotLight(spot_lights[i], material, norm, FragPos, viewDir, TexCoords, spotLightShadow(i, FragPos));
     }
 
     FragColor = vec4(result, alpha);
+    FragColor = clusterDebugColor(FragColor, cluster);
+    FragColor = shadowCascadeDebugColor(FragColor, FragPos);
 }
@@ -4,8 +4,13 @@ out vec4 FragColor;
 in vec3 TexCoords;
 
 uniform samplerCube skybox;
+uniform bool hdr;
 
 void main()
 {
     FragColor = texture(skybox, TexCoords);
-}
\ No newline at end of file
+    if (hdr) {
+        vec3 color = FragColor.rgb / (FragColor.rgb + vec3(1.0));
+        FragColor = vec4(pow(color, vec3(1.0/2.2)), 1.0);
+    }
+}
@@ -1,35 +0,0 @@
-#version 410 core
-layout (location = 0) in vec3 aPos;
-layout (location = 1) in vec3 aNormal;
-layout (location = 2) in vec2 aTexCoords;
-layout (location = 3) in vec3 aTangent;
-layout (location = 4) in vec3 aBitangent;
-
-layout (std140) uniform Matrices {
-    mat4 view;
-    mat4 projection;
-};
-uniform mat4 model;
-
-out VS_OUT {
-    vec2 TexCoords;
-    vec3 TangentViewPos;
-    vec3 TangentFragPos;
-    mat3 TBN;
-} vs_out;
-
-uniform vec3 viewPos;
-
-void main() {
-    mat3 normalMatrix = transpose(inverse(mat3(model)));
-    vec3 T = normalize(normalMatrix * aTangent);
-    vec3 N = normalize(normalMatrix * aNormal);
-    T = normalize(T - dot(T, N) * N);
-    vec3 B = cross(N, T);
-    mat3 TBN = transpose(mat3(T, B, N));
-    vs_out.TexCoords = aTexCoords;
-    vs_out.TBN = TBN;
-    vs_out.TangentViewPos = TBN * viewPos;
-    vs_out.TangentFragPos = TBN * vec3(model * vec4(aPos, 1.0));
-    gl_Position = projection * view * model * vec4(aPos, 1.0);
-}
\ No newline at end of file
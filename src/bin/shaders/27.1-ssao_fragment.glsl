@@ -1,48 +0,0 @@
-#version 410
-out float FragColor;
-
-in vec2 TexCoords;
-
-uniform sampler2D gPosition;
-uniform sampler2D gNormal;
-uniform vec3 samples[64];
-uniform vec3 noise[16];
-uniform mat4 projection;
-const vec2 noiseScale = vec2(800.0/4.0, 600.0/4.0);
-const int kernelSize = 64;
-const float radius = 0.5;
-const float bias = 0.025;
-
-float rand(vec2 co){
-    return fract(sin(dot(co, vec2(12.9898, 78.233))) * 43758.5453);
-}
-
-void main() {
-    vec3 fragPos = texture(gPosition, TexCoords).xyz;
-    vec3 normal = normalize(texture(gNormal, TexCoords).rgb);
-    vec2 noiseCoords = noiseScale * TexCoords.xy;
-    vec3 randomVec = normalize(vec3(rand(TexCoords) * 2.0 - 1, rand(noiseCoords) * 2.0 - 1, 0.0));
-
-    vec3 tangent = normalize(randomVec - normal * dot(randomVec, normal));
-    vec3 bitangent = cross(normal, tangent);
-    mat3 TBN = mat3(tangent, bitangent, normal);
-    float occlusion = 0.0;
-    for(int i = 0; i < kernelSize; ++i)
-    {
-        vec3 samplePos = TBN * samples[i]; // from tangent to view-space
-        samplePos = fragPos + samplePos * radius;
-
-        vec4 offset = vec4(samplePos, 1.0);
-        offset = projection * offset; // from view to clip-space
-        offset.xyz /= offset.w; // perspective divide
-        offset.xyz = offset.xyz * 0.5 + 0.5; // transform to range 0.0 - 1.0
-
-        float sampleDepth = texture(gPosition, offset.xy).z; // get depth value of kernel sample
-
-        float rangeCheck = smoothstep(0.0, 1.0, radius / abs(fragPos.z - sampleDepth));
-        occlusion += (sampleDepth >= samplePos.z + bias ? 1.0 : 0.0) * rangeCheck;
-    }
-    occlusion = 1.0 - (occlusion / kernelSize);
-
-    FragColor = occlusion;
-}
\ No newline at end of file
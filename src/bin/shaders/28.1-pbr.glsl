@@ -8,9 +8,9 @@ in vec3 Normal;
 
 #include "material.glsl"
 #include "point_light.glsl"
+#include "lights.glsl"
+#include "image_based_lighting.glsl"
 
-const int MAX_LIGHTS = 4;
-uniform PointLight point_lights[MAX_LIGHTS];
 uniform vec3 viewPos;
 uniform vec3 albedo;
 uniform float metallic;
@@ -61,12 +61,15 @@ void main() {
     F0 = mix(F0, albedo, metallic);
 
     vec3 Lo = vec3(0.0);
-    for (int i = 0; i < 4; ++i) {
+    uvec4 cluster = currentCluster();
+    LightList pointLights = pointLightList(cluster);
+    for (int j = 0; j < pointLights.count; ++j) {
+        int i = lightIndex(pointLights, j);
         vec3 L = normalize(point_lights[i].position - WorldPos);
         vec3 H = normalize(V + L);
 
         float distance = length(point_lights[i].position - WorldPos);
-        float attenuation = 1.0 / (distance * distance);
+        float attenuation = rangeWindow(distance, point_lights[i].range) / (distance * distance);
         vec3 radiance = point_lights[i].specular * attenuation;
 
         float NDF = DistributionGGX(N, H, roughness);
@@ -85,11 +88,12 @@ void main() {
         Lo += (kD * albedo / PI + specular) * radiance * NdotL;
     }
 
-    vec3 ambient = vec3(0.03) * albedo * ao;
+    vec3 ambient = ambientLighting(N, V, F0, albedo, metallic, roughness) * ao * ambientOcclusion();
     vec3 color = ambient + Lo;
 
     color = color / (color + vec3(1.0));
     color = pow(color, vec3(1.0/2.2));
 
     FragColor = vec4(color, 1.0);
+    FragColor = clusterDebugColor(FragColor, cluster);
 }
\ No newline at end of file
@@ -0,0 +1,8 @@
+#pragma once
+uniform bool ambient_occlusion_enabled;
+uniform sampler2D ambient_occlusion_map;
+
+float ambientOcclusion() {
+    if (!ambient_occlusion_enabled) return 1.0;
+    return texture(ambient_occlusion_map, gl_FragCoord.xy / vec2(textureSize(ambient_occlusion_map, 0))).r;
+}
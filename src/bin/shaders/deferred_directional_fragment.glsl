@@ -0,0 +1,27 @@
+#version 410 core
+
+#include "deferred_lighting.glsl"
+
+in vec2 TexCoords;
+
+out vec4 FragColor;
+
+void main() {
+    GBufferSample g = sampleGBuffer(TexCoords);
+    if (!g.geometry) {
+        discard;
+    }
+    vec3 viewDir = normalize(viewPos - g.position);
+
+    vec3 result = vec3(0.0);
+    for (int i = 0; i < n_directional_lights; i++) {
+        result += calculateDirectionalLightWithoutMaterial(
+            directional_lights[i], g.shininess, g.diffuse, g.specular, g.normal, viewDir, directionalLightShadow(i, g.position)
+        );
+    }
+
+    vec4 clipPos = projection * view * vec4(g.position, 1.0);
+    gl_FragDepth = clipPos.z / clipPos.w * 0.5 + 0.5;
+    FragColor = vec4(result, 1.0);
+    FragColor = shadowCascadeDebugColor(FragColor, g.position);
+}
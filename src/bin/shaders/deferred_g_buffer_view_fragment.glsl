@@ -0,0 +1,12 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D g_buffer_texture;
+uniform bool alpha_channel;
+
+void main() {
+    vec4 value = texture(g_buffer_texture, TexCoords);
+    FragColor = vec4(alpha_channel ? vec3(value.a) : value.rgb, 1.0);
+}
@@ -0,0 +1,57 @@
+#version 410 core
+
+#include "mesh_features.glsl"
+#include "material.glsl"
+#include "parallax.glsl"
+
+layout (location = 0) out vec4 gPosition;
+layout (location = 1) out vec4 gNormal;
+#ifndef SSAO_PREPASS
+layout (location = 2) out vec4 gAlbedoSpec;
+#endif
+
+uniform Material material;
+uniform bool receive_shadows;
+
+#ifdef COMPUTE_TANGENTS
+in FragmentData {
+#else
+in VertexData {
+#endif
+    vec3 FragPos;
+    vec3 Normal;
+    vec2 TexCoords;
+#ifdef HAS_NORMAL_MAP
+    vec3 TangentViewPos;
+    vec3 TangentFragPos;
+    mat3 TBN;
+#endif
+} fs_in;
+
+void main() {
+    float alpha = texture(material.diffuse0, fs_in.TexCoords).a;
+    if (alpha < 0.1) {
+        discard;
+    }
+    vec2 texCoords = fs_in.TexCoords;
+#ifdef HAS_NORMAL_MAP
+#ifdef HAS_HEIGHT_MAP
+    vec3 viewDir = normalize(fs_in.TangentViewPos - fs_in.TangentFragPos);
+    texCoords = ParallaxMapping(texCoords, viewDir, material);
+    if (texCoords.x > 1.0 || texCoords.y > 1.0 || texCoords.x < 0.0 || texCoords.y < 0.0) {
+        discard;
+    }
+#endif
+    vec3 norm = texture(material.normal0, texCoords).rgb;
+    norm = normalize(transpose(fs_in.TBN) * normalize(norm * 2.0 - 1.0));
+#else
+    vec3 norm = normalize(fs_in.Normal);
+#endif
+
+    gPosition = vec4(fs_in.FragPos, max(material.shininess, 1.0));
+    gNormal = vec4(norm, receive_shadows ? 1.0 : 0.0);
+#ifndef SSAO_PREPASS
+    gAlbedoSpec.rgb = material.n_diffuse > 0 ? texture(material.diffuse0, texCoords).rgb : vec3(0.0);
+    gAlbedoSpec.a = MATERIAL_HAS_SPECULAR(material) ? texture(material.specular0, texCoords).r : 0.0;
+#endif
+}
@@ -0,0 +1,38 @@
+#version 410 core
+
+#include "deferred_lighting.glsl"
+
+uniform vec4 deferred_viewport;
+uniform int light_index;
+uniform bool spot_light;
+
+out vec4 FragColor;
+
+void main() {
+    vec2 texCoords = (gl_FragCoord.xy - deferred_viewport.xy) / deferred_viewport.zw;
+    GBufferSample g = sampleGBuffer(texCoords);
+    if (!g.geometry) {
+        discard;
+    }
+    vec3 viewDir = normalize(viewPos - g.position);
+
+    vec3 result;
+    if (spot_light) {
+        result = calculateSpotLightWithoutMaterial(
+            spot_lights[light_index], g.shininess, g.diffuse, g.specular, g.normal, g.position, viewDir,
+            spotLightShadow(light_index, g.position)
+        );
+    } else {
+        PointLight light = point_lights[light_index];
+        float distance = length(light.position - g.position);
+        float attenuation = lightAttenuation(light.constant, light.linear, light.quadratic, light.range, distance);
+        vec3 ambient = light.ambient * g.diffuse * ambientOcclusion() * attenuation;
+        light.ambient = vec3(0.0);
+        result = ambient + calculatePointLightWithPositionWithoutMaterial(
+            light, light.position, g.shininess, g.diffuse, g.specular, g.normal, g.position, viewDir, texCoords,
+            pointLightShadow(light_index, g.position)
+        );
+    }
+
+    FragColor = vec4(result, 1.0);
+}
@@ -1,7 +1,5 @@
 #version 410 core
 layout (location = 0) in vec3 aPos;
-layout (location = 1) in vec3 aNormal;
-layout (location = 2) in vec2 aTextCoords;
 
 layout (std140) uniform Matrices {
     mat4 view;
@@ -11,4 +9,4 @@ uniform mat4 model;
 
 void main() {
     gl_Position = projection * view * model * vec4(aPos, 1.0);
-}
\ No newline at end of file
+}
@@ -0,0 +1,31 @@
+#pragma once
+#define DEFERRED_SHADING
+#include "lights.glsl"
+
+layout (std140) uniform Matrices {
+    mat4 view;
+    mat4 projection;
+};
+uniform sampler2D gPosition;
+uniform sampler2D gNormal;
+uniform sampler2D gAlbedoSpec;
+uniform vec3 viewPos;
+
+struct GBufferSample {
+    bool geometry;
+    vec3 position;
+    vec3 normal;
+    vec3 diffuse;
+    vec3 specular;
+    float shininess;
+};
+
+GBufferSample sampleGBuffer(vec2 texCoords) {
+    vec4 position = texture(gPosition, texCoords);
+    vec4 normal = texture(gNormal, texCoords);
+    vec4 albedoSpec = texture(gAlbedoSpec, texCoords);
+    receive_shadows = normal.a > 0.5;
+    return GBufferSample(
+        position.a > 0.0, position.xyz, normalize(normal.xyz), albedoSpec.rgb, vec3(albedoSpec.a), position.a
+    );
+}
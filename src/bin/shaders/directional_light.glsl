@@ -1,13 +1,17 @@
+#pragma once
+#include "ambient_occlusion.glsl"
+
 struct DirectionalLight {
     vec3 direction;
     vec3 ambient;
     vec3 diffuse;
     vec3 specular;
     bool set;
+    int shadow_index;
 };
 
 vec3 calculateDirectionalLightWithLightDirection(
-    DirectionalLight light, vec3 direction, Material material, vec3 normal, vec3 viewDir, vec2 texCoords
+    DirectionalLight light, vec3 direction, Material material, vec3 normal, vec3 viewDir, vec2 texCoords, float shadow
 ) {
     if (!light.set) return vec3(0.0);
     vec3 lightDir = normalize(-direction);
@@ -20,16 +24,28 @@ vec3 calculateDirectionalLightWithLightDirection(
     vec3 ambient = vec3(0.0);
     vec3 diffuse = vec3(0.0);
     if (material.n_diffuse > 0) {
-        ambient += light.ambient * vec3(texture(material.diffuse0, texCoords));
+        ambient += light.ambient * vec3(texture(material.diffuse0, texCoords)) * ambientOcclusion();
         diffuse += light.diffuse * diff * vec3(texture(material.diffuse0, texCoords));
     }
 
     vec3 specular = vec3(0.0);
-    if (material.n_specular > 0) {
+    if (MATERIAL_HAS_SPECULAR(material)) {
         specular += light.specular * spec * vec3(texture(material.specular0, texCoords));
     }
 
-    return (ambient + diffuse + specular);
+    return (ambient + (1.0 - shadow) * (diffuse + specular));
+}
+
+vec3 calculateDirectionalLightWithLightDirection(
+    DirectionalLight light, vec3 direction, Material material, vec3 normal, vec3 viewDir, vec2 texCoords
+) {
+    return calculateDirectionalLightWithLightDirection(light, direction, material, normal, viewDir, texCoords, 0.0);
+}
+
+vec3 calculateDirectionalLight(
+    DirectionalLight light, Material material, vec3 normal, vec3 viewDir, vec2 texCoords, float shadow
+) {
+    return calculateDirectionalLightWithLightDirection(light, light.direction, material, normal, viewDir, texCoords, shadow);
 }
 
 vec3 calculateDirectionalLight(
@@ -37,3 +53,21 @@ vec3 calculateDirectionalLight(
 ) {
     return calculateDirectionalLightWithLightDirection(light, light.direction, material, normal, viewDir, texCoords);
 }
+
+vec3 calculateDirectionalLightWithoutMaterial(
+    DirectionalLight light, float shininess, vec3 diffuseColor, vec3 specularColor, vec3 normal, vec3 viewDir, float shadow
+) {
+    if (!light.set) return vec3(0.0);
+    vec3 lightDir = normalize(-light.direction);
+
+    float diff = max(dot(lightDir, normal), 0.0);
+
+    vec3 halfwayDir = normalize(lightDir + viewDir);
+    float spec = pow(max(dot(normal, halfwayDir), 0.0), shininess);
+
+    vec3 ambient = light.ambient * diffuseColor * ambientOcclusion();
+    vec3 diffuse = light.diffuse * diff * diffuseColor;
+    vec3 specular = light.specular * spec * specularColor;
+
+    return (ambient + (1.0 - shadow) * (diffuse + specular));
+}
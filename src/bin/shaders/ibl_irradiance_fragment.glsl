@@ -0,0 +1,28 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec3 LocalPos;
+
+uniform samplerCube environment_map;
+
+const float PI = 3.14159265359;
+const float SAMPLE_DELTA = 0.025;
+
+void main() {
+    vec3 N = normalize(LocalPos);
+    vec3 up = abs(N.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, N));
+    up = normalize(cross(N, right));
+
+    vec3 irradiance = vec3(0.0);
+    float samples = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += SAMPLE_DELTA) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += SAMPLE_DELTA) {
+            vec3 tangentSample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 sampleVec = tangentSample.x * right + tangentSample.y * up + tangentSample.z * N;
+            irradiance += texture(environment_map, sampleVec).rgb * cos(theta) * sin(theta);
+            samples++;
+        }
+    }
+    FragColor = vec4(PI * irradiance / samples, 1.0);
+}
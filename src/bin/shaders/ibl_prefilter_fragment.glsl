@@ -0,0 +1,47 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec3 LocalPos;
+
+#include "ibl_sampling.glsl"
+
+uniform samplerCube environment_map;
+uniform float environment_resolution;
+uniform float roughness;
+
+const uint SAMPLE_COUNT = 1024u;
+
+float distributionGGX(float NdotH, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+void main() {
+    vec3 N = normalize(LocalPos);
+    vec3 R = N;
+    vec3 V = R;
+
+    vec3 prefiltered = vec3(0.0);
+    float totalWeight = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; ++i) {
+        vec2 Xi = hammersley(i, SAMPLE_COUNT);
+        vec3 H = importanceSampleGGX(Xi, N, roughness);
+        vec3 L = normalize(2.0 * dot(V, H) * H - V);
+
+        float NdotL = max(dot(N, L), 0.0);
+        if (NdotL > 0.0) {
+            float NdotH = max(dot(N, H), 0.0);
+            float HdotV = max(dot(H, V), 0.0);
+            float pdf = distributionGGX(NdotH, roughness) * NdotH / (4.0 * HdotV) + 0.0001;
+            float saTexel = 4.0 * PI / (6.0 * environment_resolution * environment_resolution);
+            float saSample = 1.0 / (float(SAMPLE_COUNT) * pdf + 0.0001);
+            float mipLevel = roughness == 0.0 ? 0.0 : 0.5 * log2(saSample / saTexel);
+
+            prefiltered += textureLod(environment_map, L, mipLevel).rgb * NdotL;
+            totalWeight += NdotL;
+        }
+    }
+    FragColor = vec4(prefiltered / max(totalWeight, 0.0001), 1.0);
+}
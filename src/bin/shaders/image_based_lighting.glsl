@@ -0,0 +1,24 @@
+#pragma once
+uniform bool image_based_lighting;
+uniform samplerCube irradiance_map;
+uniform samplerCube prefilter_map;
+uniform sampler2D brdf_lut;
+uniform float prefilter_max_lod;
+
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 F0, float roughness) {
+    return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+vec3 ambientLighting(vec3 N, vec3 V, vec3 F0, vec3 albedo, float metallic, float roughness) {
+    if (!image_based_lighting) return vec3(0.03) * albedo;
+    float NdotV = max(dot(N, V), 0.0);
+    vec3 F = fresnelSchlickRoughness(NdotV, F0, roughness);
+    vec3 kD = (1.0 - F) * (1.0 - metallic);
+    vec3 diffuse = texture(irradiance_map, N).rgb * albedo;
+
+    vec3 R = reflect(-V, N);
+    vec3 prefiltered = textureLod(prefilter_map, R, roughness * prefilter_max_lod).rgb;
+    vec2 brdf = texture(brdf_lut, vec2(NdotV, roughness)).rg;
+    vec3 specular = prefiltered * (F * brdf.x + brdf.y);
+    return kD * diffuse + specular;
+}
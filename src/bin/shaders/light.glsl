@@ -1,3 +1,4 @@
+#pragma once
 struct Light {
     vec3 direction;
     vec3 position;
@@ -0,0 +1,12 @@
+#pragma once
+
+float rangeWindow(float distance, float range) {
+    if (range <= 0.0) return 1.0;
+    float ratio = distance / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window;
+}
+
+float lightAttenuation(float constant, float linear, float quadratic, float range, float distance) {
+    return rangeWindow(distance, range) / (constant + linear * distance + quadratic * (distance * distance));
+}
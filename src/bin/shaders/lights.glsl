@@ -0,0 +1,81 @@
+#pragma once
+#include "material.glsl"
+#include "directional_light.glsl"
+#include "point_light.glsl"
+#include "spot_light.glsl"
+
+#ifndef MAX_LIGHTS
+#define MAX_LIGHTS 64
+#endif
+#ifdef LIGHTS_STORAGE_BUFFER
+layout (std140) readonly buffer Lights {
+#else
+layout (std140) uniform Lights {
+#endif
+    DirectionalLight directional_lights[MAX_LIGHTS];
+    PointLight point_lights[MAX_LIGHTS];
+    SpotLight spot_lights[MAX_LIGHTS];
+};
+uniform int n_directional_lights;
+uniform int n_point_lights;
+uniform int n_spot_lights;
+
+#include "shadows.glsl"
+
+struct LightList {
+    int offset;
+    int count;
+};
+
+#ifdef CLUSTERED_LIGHTING
+uniform usamplerBuffer cluster_grid;
+uniform usamplerBuffer cluster_light_indices;
+uniform uvec3 cluster_dimensions;
+uniform vec2 cluster_depth_range;
+uniform vec4 cluster_viewport;
+uniform bool cluster_debug;
+
+uvec4 currentCluster() {
+    float near = cluster_depth_range.x;
+    float far = cluster_depth_range.y;
+    float ndcDepth = gl_FragCoord.z * 2.0 - 1.0;
+    float depth = 2.0 * near * far / (far + near - ndcDepth * (far - near));
+    float slice = log(max(depth, near) / near) / log(far / near) * float(cluster_dimensions.z);
+    vec2 tile = (gl_FragCoord.xy - cluster_viewport.xy) / cluster_viewport.zw * vec2(cluster_dimensions.xy);
+    uvec3 cluster = min(uvec3(uvec2(max(tile, vec2(0.0))), uint(slice)), cluster_dimensions - 1u);
+    int index = int(cluster.x + cluster.y * cluster_dimensions.x + cluster.z * cluster_dimensions.x * cluster_dimensions.y);
+    return texelFetch(cluster_grid, index);
+}
+
+int lightIndex(LightList list, int i) {
+    return int(texelFetch(cluster_light_indices, list.offset + i).r);
+}
+
+vec4 clusterDebugColor(vec4 color, uvec4 cluster) {
+    if (!cluster_debug) return color;
+    float heat = clamp(float(cluster.y + cluster.w) / 16.0, 0.0, 1.0);
+    vec3 heatColor = heat < 0.5 ? mix(vec3(0.0, 0.0, 1.0), vec3(0.0, 1.0, 0.0), heat * 2.0)
+        : mix(vec3(0.0, 1.0, 0.0), vec3(1.0, 0.0, 0.0), heat * 2.0 - 1.0);
+    return vec4(mix(color.rgb, heatColor, 0.75), color.a);
+}
+#else
+uvec4 currentCluster() {
+    return uvec4(0u, uint(n_point_lights), 0u, uint(n_spot_lights));
+}
+
+int lightIndex(LightList list, int i) {
+    return list.offset + i;
+}
+
+vec4 clusterDebugColor(vec4 color, uvec4 cluster) {
+    return color;
+}
+#endif
+
+LightList pointLightList(uvec4 cluster) {
+    return LightList(int(cluster.x), int(cluster.y));
+}
+
+LightList spotLightList(uvec4 cluster) {
+    return LightList(int(cluster.z), int(cluster.w));
+}
@@ -1,3 +1,4 @@
+#pragma once
 struct Material {
     sampler2D diffuse0;
     sampler2D specular0;
@@ -10,4 +11,12 @@ struct Material {
     int n_diffuse;
     int n_specular;
     int n_height;
-};
\ No newline at end of file
+};
+
+#if defined(HAS_SPECULAR)
+#define MATERIAL_HAS_SPECULAR(material) true
+#elif defined(PERMUTATION)
+#define MATERIAL_HAS_SPECULAR(material) false
+#else
+#define MATERIAL_HAS_SPECULAR(material) (material.n_specular > 0)
+#endif
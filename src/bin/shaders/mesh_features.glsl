@@ -0,0 +1,4 @@
+#pragma once
+#if defined(HAS_NORMAL_MAP) && !defined(HAS_TANGENTS)
+#define COMPUTE_TANGENTS
+#endif
@@ -0,0 +1,95 @@
+#version 410 core
+
+#include "mesh_features.glsl"
+#include "material.glsl"
+#include "directional_light.glsl"
+#include "point_light.glsl"
+#include "spot_light.glsl"
+#include "lights.glsl"
+#include "parallax.glsl"
+
+uniform Material material;
+uniform vec3 viewPos;
+
+#ifdef COMPUTE_TANGENTS
+in FragmentData {
+#else
+in VertexData {
+#endif
+    vec3 FragPos;
+    vec3 Normal;
+    vec2 TexCoords;
+#ifdef HAS_NORMAL_MAP
+    vec3 TangentViewPos;
+    vec3 TangentFragPos;
+    mat3 TBN;
+#endif
+} fs_in;
+
+out vec4 FragColor;
+
+void main() {
+    float alpha = texture(material.diffuse0, fs_in.TexCoords).a;
+    if (alpha < 0.1) {
+        discard;
+    }
+    vec2 texCoords = fs_in.TexCoords;
+    vec3 result = vec3(0.0);
+    uvec4 cluster = currentCluster();
+#ifdef HAS_NORMAL_MAP
+    vec3 viewDir = normalize(fs_in.TangentViewPos - fs_in.TangentFragPos);
+#ifdef HAS_HEIGHT_MAP
+    texCoords = ParallaxMapping(texCoords, viewDir, material);
+    if (texCoords.x > 1.0 || texCoords.y > 1.0 || texCoords.x < 0.0 || texCoords.y < 0.0) {
+        discard;
+    }
+#endif
+    vec3 norm = texture(material.normal0, texCoords).rgb;
+    norm = normalize(norm * 2.0 - 1.0);
+
+    for (int i = 0; i < n_directional_lights; i++) {
+        result += calculateDirectionalLightWithLightDirection(
+            directional_lights[i], normalize(fs_in.TBN * directional_lights[i].direction), material, norm, viewDir, texCoords,
+            directionalLightShadow(i, fs_in.FragPos)
+        );
+    }
+    LightList pointLights = pointLightList(cluster);
+    for (int j = 0; j < pointLights.count; j++) {
+        int i = lightIndex(pointLights, j);
+        result += calculatePointLightWithPosition(
+            point_lights[i], fs_in.TBN * point_lights[i].position, material, norm, fs_in.TangentFragPos, viewDir, texCoords,
+            pointLightShadow(i, fs_in.FragPos)
+        );
+    }
+    LightList spotLights = spotLightList(cluster);
+    for (int j = 0; j < spotLights.count; j++) {
+        int i = lightIndex(spotLights, j);
+        vec3 lightPos = fs_in.TBN * spot_lights[i].position;
+        result += calculateSpotLightWithPositionAndDirection(
+            spot_lights[i], lightPos, normalize(lightPos - fs_in.TangentFragPos), material, norm, fs_in.TangentFragPos, viewDir, texCoords,
+            spotLightShadow(i, fs_in.FragPos)
+        );
+    }
+#else
+    vec3 norm = normalize(fs_in.Normal);
+    vec3 viewDir = normalize(viewPos - fs_in.FragPos);
+
+    for (int i = 0; i < n_directional_lights; i++) {
+        result += calculateDirectionalLight(directional_lights[i], material, norm, viewDir, texCoords, directionalLightShadow(i, fs_in.FragPos));
+    }
+    LightList pointLights = pointLightList(cluster);
+    for (int j = 0; j < pointLights.count; j++) {
+        int i = lightIndex(pointLights, j);
+        result += calculatePointLight(point_lights[i], material, norm, fs_in.FragPos, viewDir, texCoords, pointLightShadow(i, fs_in.FragPos));
+    }
+    LightList spotLights = spotLightList(cluster);
+    for (int j = 0; j < spotLights.count; j++) {
+        int i = lightIndex(spotLights, j);
+        result += calculateSpotLight(spot_lights[i], material, norm, fs_in.FragPos, viewDir, texCoords, spotLightShadow(i, fs_in.FragPos));
+    }
+#endif
+
+    FragColor = vec4(result, alpha);
+    FragColor = clusterDebugColor(FragColor, cluster);
+    FragColor = shadowCascadeDebugColor(FragColor, fs_in.FragPos);
+}
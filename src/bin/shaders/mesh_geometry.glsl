@@ -2,19 +2,20 @@
 layout (triangles) in;
 layout (triangle_strip, max_vertices = 3) out;
 
-in VS_OUT {
+in VertexData {
     vec3 FragPos;
     vec3 Normal;
     vec2 TexCoords;
     mat3 NormalMatrix;
 } gs_in[];
 
-out GS_OUT {
+out FragmentData {
+    vec3 FragPos;
+    vec3 Normal;
     vec2 TexCoords;
     vec3 TangentViewPos;
     vec3 TangentFragPos;
     mat3 TBN;
-    vec3 Normal;
 } gs_out;
 
 uniform vec3 viewPos;
@@ -36,13 +37,14 @@ void main() {
         T = normalize(T - dot(T, N) * N);
         vec3 B = cross(N, T);
         mat3 TBN = transpose(mat3(T, B, N));
+        gs_out.FragPos = gs_in[i].FragPos;
+        gs_out.Normal = gs_in[i].Normal;
         gs_out.TexCoords = gs_in[i].TexCoords;
         gs_out.TBN = TBN;
         gs_out.TangentViewPos = TBN * viewPos;
         gs_out.TangentFragPos = TBN * gs_in[i].FragPos;
-        gs_out.Normal = gs_in[i].Normal;
         gl_Position = gl_in[i].gl_Position;
         EmitVertex();
     }
     EndPrimitive();
-}
\ No newline at end of file
+}
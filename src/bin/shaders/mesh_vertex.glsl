@@ -0,0 +1,67 @@
+#version 410 core
+
+#include "mesh_features.glsl"
+
+layout (location = 0) in vec3 aPos;
+#ifdef HAS_NORMALS
+layout (location = NORMAL_LOCATION) in vec3 aNormal;
+#endif
+#ifdef HAS_TEXTURE_COORDINATES
+layout (location = TEXTURE_COORDINATES_LOCATION) in vec2 aTexCoords;
+#endif
+#ifdef HAS_TANGENTS
+layout (location = TANGENT_LOCATION) in vec3 aTangent;
+#endif
+#ifdef INSTANCED
+layout (location = MODEL_LOCATION) in mat4 model;
+#else
+uniform mat4 model;
+#endif
+
+layout (std140) uniform Matrices {
+    mat4 view;
+    mat4 projection;
+};
+uniform vec3 viewPos;
+
+out VertexData {
+    vec3 FragPos;
+    vec3 Normal;
+    vec2 TexCoords;
+#if defined(COMPUTE_TANGENTS)
+    mat3 NormalMatrix;
+#elif defined(HAS_NORMAL_MAP)
+    vec3 TangentViewPos;
+    vec3 TangentFragPos;
+    mat3 TBN;
+#endif
+} vs_out;
+
+void main() {
+    vec4 worldPos = model * vec4(aPos, 1.0);
+    mat3 normalMatrix = transpose(inverse(mat3(model)));
+    gl_Position = projection * view * worldPos;
+    vs_out.FragPos = vec3(worldPos);
+#ifdef HAS_NORMALS
+    vs_out.Normal = normalMatrix * aNormal;
+#else
+    vs_out.Normal = vec3(0.0, 0.0, 1.0);
+#endif
+#ifdef HAS_TEXTURE_COORDINATES
+    vs_out.TexCoords = aTexCoords;
+#else
+    vs_out.TexCoords = vec2(0.0);
+#endif
+#if defined(COMPUTE_TANGENTS)
+    vs_out.NormalMatrix = normalMatrix;
+#elif defined(HAS_NORMAL_MAP)
+    vec3 T = normalize(normalMatrix * aTangent);
+    vec3 N = normalize(vs_out.Normal);
+    T = normalize(T - dot(T, N) * N);
+    vec3 B = cross(N, T);
+    mat3 TBN = transpose(mat3(T, B, N));
+    vs_out.TBN = TBN;
+    vs_out.TangentViewPos = TBN * viewPos;
+    vs_out.TangentFragPos = TBN * vs_out.FragPos;
+#endif
+}
@@ -1,3 +1,7 @@
+#pragma once
+#include "ambient_occlusion.glsl"
+#include "light_attenuation.glsl"
+
 struct PointLight {
     vec3 position;
 
@@ -10,6 +14,8 @@ struct PointLight {
     vec3 specular;
 
     bool set;
+    int shadow_index;
+    float range;
 };
 vec3 sampleOffsetDirections[20] = vec3[](
     vec3(1, 1, 1), vec3( 1, -1,  1), vec3(-1, -1,  1), vec3(-1, 1,  1),
@@ -19,42 +25,6 @@ vec3 sampleOffsetDirections[20] = vec3[](
     vec3(0, 1,  1), vec3( 0, -1,  1), vec3( 0, -1, -1), vec3( 0, 1, -1)
 );
 
-float shadowCalculation(vec3 fragPos, samplerCube depthMap, float far_plane, vec3 lightPos, vec3 viewPos) {
-    vec3 fragToLight = fragPos - lightPos;
-    float currentDepth = length(fragToLight);
-    float bias = 0.15;
-    float shadow = 0.0;
-    float samples = 20;
-    float viewDistance = length(viewPos - fragPos);
-    float diskRadius = (1 + (viewDistance / far_plane)) / far_plane;
-    for (int i = 0; i < samples; i += 1) {
-        float closestDepth = texture(depthMap, fragToLight + sampleOffsetDirections[i] * diskRadius).r;
-        closestDepth *= far_plane;
-        if (currentDepth - bias > closestDepth) shadow += 1.0;
-    }
-    shadow /= float(samples);
-    return shadow;
-}
-
-float shadowCalculationInLightSpace(vec4 fragPosLightSpace, sampler2D shadowMap, vec3 normal, vec3 lightDir) {
-    vec3 projCoords = fragPosLightSpace.xyz / fragPosLightSpace.w;
-    projCoords = projCoords * 0.5 + 0.5;
-    float closesDepth = texture(shadowMap, projCoords.xy).r;
-    float currentDepth = projCoords.z;
-    if (currentDepth > 1.0) return 0.0;
-    float bias = max(0.05 * (1.0 - dot(normal, lightDir)), 0.005);
-    float shadow = 0.0;
-    vec2 texelSize = 1.0 / textureSize(shadowMap, 0);
-    for (int x = -1; x <= 1; x += 1) {
-        for (int y = -1; y <= 1; y += 1) {
-            float pcfDepth = texture(shadowMap, projCoords.xy + vec2(x, y) * texelSize).r;
-            shadow += currentDepth - bias > pcfDepth ? 1.0 : 0.0;
-        }
-    }
-    shadow /= 9.0;
-    return shadow;
-}
-
 vec3 calculatePointLightWithPositionWithoutMaterialWithOcculsion(
     PointLight light, float occlusion, vec3 position, float shininess, vec3 diffuseColor, vec3 specularColor, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
 ) {
@@ -67,7 +37,7 @@ vec3 calculatePointLightWithPositionWithoutMaterialWithOcculsion(
     float spec = pow(max(dot(normal, halfwayDir), 0.0), shininess);
 
     float distance    = length(light.position - fragPos);
-    float attenuation = 1.0 / (light.constant + light.linear * distance + light.quadratic * (distance * distance));
+    float attenuation = lightAttenuation(light.constant, light.linear, light.quadratic, light.range, distance);
 
     vec3 ambient = light.ambient * diffuseColor * occlusion;
     vec3 diffuse = light.diffuse * diff * diffuseColor * attenuation;
@@ -78,7 +48,7 @@ vec3 calculatePointLightWithPositionWithoutMaterialWithOcculsion(
 }
 
 vec3 calculatePointLightWithPositionWithoutMaterial(
-    PointLight light, vec3 position, float shininess, vec3 diffuseColor, vec3 specularColor, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
+    PointLight light, vec3 position, float shininess, vec3 diffuseColor, vec3 specularColor, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords, float shadow
 ) {
     if (!light.set) return vec3(0.0);
     vec3 lightDir = normalize(position - fragPos);
@@ -89,96 +59,48 @@ vec3 calculatePointLightWithPositionWithoutMaterial(
     float spec = pow(max(dot(normal, halfwayDir), 0.0), shininess);
 
     float distance    = length(light.position - fragPos);
-    float attenuation = 1.0 / (light.constant + light.linear * distance + light.quadratic * (distance * distance));
+    float attenuation = lightAttenuation(light.constant, light.linear, light.quadratic, light.range, distance);
 
-    vec3 ambient = light.ambient * diffuseColor;
+    vec3 ambient = light.ambient * diffuseColor * ambientOcclusion();
     vec3 diffuse = light.diffuse * diff * diffuseColor * attenuation;
 
     vec3 specular = light.specular * spec * specularColor * attenuation;
 
-    return (ambient + diffuse + specular);
+    return (ambient + (1.0 - shadow) * (diffuse + specular));
+}
+
+vec3 calculatePointLightWithPositionWithoutMaterial(
+    PointLight light, vec3 position, float shininess, vec3 diffuseColor, vec3 specularColor, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
+) {
+    return calculatePointLightWithPositionWithoutMaterial(light, position, shininess, diffuseColor, specularColor, normal, fragPos, viewDir, texCoords, 0.0);
 }
 
 vec3 calculatePointLightWithPosition(
-    PointLight light, vec3 position, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
+    PointLight light, vec3 position, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords, float shadow
 ) {
     float shininess = material.shininess;
     vec3 diffuse = vec3(texture(material.diffuse0, texCoords));
     vec3 specular = vec3(0.0);
-    if (material.n_specular > 0) {
+    if (MATERIAL_HAS_SPECULAR(material)) {
         specular = vec3(texture(material.specular0, texCoords));
     }
-    return calculatePointLightWithPositionWithoutMaterial(light, position, shininess, diffuse, specular, normal, fragPos, viewDir, texCoords);
+    return calculatePointLightWithPositionWithoutMaterial(light, position, shininess, diffuse, specular, normal, fragPos, viewDir, texCoords, shadow);
 }
 
-vec3 calculatePointLight(
-    PointLight light, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
+vec3 calculatePointLightWithPosition(
+    PointLight light, vec3 position, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
 ) {
-    return calculatePointLightWithPosition(light, light.position, material, normal, fragPos, viewDir, texCoords);
+    return calculatePointLightWithPosition(light, position, material, normal, fragPos, viewDir, texCoords, 0.0);
 }
 
-vec3 calculatePointLightWithShadow(
-    PointLight light, sampler2D shadowMap, vec4 fragPosLightSpace, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
+vec3 calculatePointLight(
+    PointLight light, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords, float shadow
 ) {
-    if (!light.set) return vec3(0.0);
-    vec3 lightDir = normalize(light.position - fragPos);
-
-    float diff = max(dot(lightDir, normal), 0.0);
-
-    vec3 halfwayDir = normalize(lightDir + viewDir);
-    float spec = pow(max(dot(normal, halfwayDir), 0.0), material.shininess);
-
-    float distance    = length(light.position - fragPos);
-    float attenuation = 1.0 / (light.constant + light.linear * distance +
-    light.quadratic * (distance * distance));
-
-    vec3 ambient = vec3(0.0);
-    vec3 diffuse = vec3(0.0);
-    ambient += light.ambient * vec3(texture(material.diffuse0, texCoords));
-    diffuse += light.diffuse * diff * vec3(texture(material.diffuse0, texCoords));
-
-    ambient *= attenuation;
-    diffuse *= attenuation;
-
-    vec3 specular = vec3(0.0);
-    if (material.n_specular > 0) {
-        specular += light.specular * spec * vec3(texture(material.specular0, texCoords));
-        specular *= attenuation;
-    }
-
-    float shadow = shadowCalculationInLightSpace(fragPosLightSpace, shadowMap, normal, lightDir);
-    return (ambient + (1.0 - shadow) * (diffuse + specular));
+    return calculatePointLightWithPosition(light, light.position, material, normal, fragPos, viewDir, texCoords, shadow);
 }
 
-vec3 calculatePointLightWithShadowInWorldSpace(
-    PointLight light, samplerCube shadowMap, float far_plane, Material material, vec3 normal, vec3 fragPos, vec3 viewPos, vec3 viewDir, vec2 texCoords
+vec3 calculatePointLight(
+    PointLight light, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
 ) {
-    if (!light.set) return vec3(0.0);
-    vec3 lightDir = normalize(light.position - fragPos);
-
-    float diff = max(dot(lightDir, normal), 0.0);
-
-    vec3 halfwayDir = normalize(lightDir + viewDir);
-    float spec = pow(max(dot(normal, halfwayDir), 0.0), material.shininess);
-
-    float distance    = length(light.position - fragPos);
-    float attenuation = 1.0 / (light.constant + light.linear * distance +
-    light.quadratic * (distance * distance));
-
-    vec3 ambient = vec3(0.0);
-    vec3 diffuse = vec3(0.0);
-    ambient += light.ambient * vec3(texture(material.diffuse0, texCoords));
-    diffuse += light.diffuse * diff * vec3(texture(material.diffuse0, texCoords));
-
-    ambient *= attenuation;
-    diffuse *= attenuation;
-
-    vec3 specular = vec3(0.0);
-    if (material.n_specular > 0) {
-        specular += light.specular * spec * vec3(texture(material.specular0, texCoords));
-        specular *= attenuation;
-    }
-
-    float shadow = shadowCalculation(fragPos, shadowMap, far_plane, light.position, viewPos);
-    return (ambient + (1.0 - shadow) * (diffuse + specular));
+    return calculatePointLight(light, material, normal, fragPos, viewDir, texCoords, 0.0);
 }
@@ -0,0 +1,19 @@
+#version 410 core
+out vec4 FragColor;
+
+uniform sampler2D luminance;
+uniform sampler2D previous_luminance;
+uniform float luminance_level;
+uniform float delta_time;
+uniform float adaptation_speed;
+uniform bool reset;
+
+void main()
+{
+    float average = exp(textureLod(luminance, vec2(0.5), luminance_level).r);
+    float previous = texture(previous_luminance, vec2(0.5)).r;
+    float adapted = reset
+        ? average
+        : previous + (average - previous) * (1.0 - exp(-delta_time * adaptation_speed));
+    FragColor = vec4(adapted, 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,50 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D texture1;
+uniform vec2 source_texel_size;
+uniform bool prefilter;
+uniform float threshold;
+uniform float knee;
+
+vec3 applyThreshold(vec3 color) {
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.00001);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 0.00001);
+    return color * contribution;
+}
+
+void main()
+{
+    float x = source_texel_size.x;
+    float y = source_texel_size.y;
+
+    vec3 a = texture(texture1, vec2(TexCoords.x - 2.0 * x, TexCoords.y + 2.0 * y)).rgb;
+    vec3 b = texture(texture1, vec2(TexCoords.x, TexCoords.y + 2.0 * y)).rgb;
+    vec3 c = texture(texture1, vec2(TexCoords.x + 2.0 * x, TexCoords.y + 2.0 * y)).rgb;
+
+    vec3 d = texture(texture1, vec2(TexCoords.x - 2.0 * x, TexCoords.y)).rgb;
+    vec3 e = texture(texture1, vec2(TexCoords.x, TexCoords.y)).rgb;
+    vec3 f = texture(texture1, vec2(TexCoords.x + 2.0 * x, TexCoords.y)).rgb;
+
+    vec3 g = texture(texture1, vec2(TexCoords.x - 2.0 * x, TexCoords.y - 2.0 * y)).rgb;
+    vec3 h = texture(texture1, vec2(TexCoords.x, TexCoords.y - 2.0 * y)).rgb;
+    vec3 i = texture(texture1, vec2(TexCoords.x + 2.0 * x, TexCoords.y - 2.0 * y)).rgb;
+
+    vec3 j = texture(texture1, vec2(TexCoords.x - x, TexCoords.y + y)).rgb;
+    vec3 k = texture(texture1, vec2(TexCoords.x + x, TexCoords.y + y)).rgb;
+    vec3 l = texture(texture1, vec2(TexCoords.x - x, TexCoords.y - y)).rgb;
+    vec3 m = texture(texture1, vec2(TexCoords.x + x, TexCoords.y - y)).rgb;
+
+    vec3 downsample = e * 0.125;
+    downsample += (a + c + g + i) * 0.03125;
+    downsample += (b + d + f + h) * 0.0625;
+    downsample += (j + k + l + m) * 0.125;
+    if (prefilter) {
+        downsample = applyThreshold(downsample);
+    }
+    FragColor = vec4(max(downsample, vec3(0.0)), 1.0);
+}
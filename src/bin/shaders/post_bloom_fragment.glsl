@@ -0,0 +1,14 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D texture1;
+uniform sampler2D bloom;
+uniform float bloom_intensity;
+
+void main()
+{
+    vec3 color = texture(texture1, TexCoords).rgb;
+    FragColor = vec4(color + texture(bloom, TexCoords).rgb * bloom_intensity, 1.0);
+}
@@ -0,0 +1,31 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D texture1;
+uniform float radius;
+
+void main()
+{
+    float x = radius;
+    float y = radius;
+
+    vec3 a = texture(texture1, vec2(TexCoords.x - x, TexCoords.y + y)).rgb;
+    vec3 b = texture(texture1, vec2(TexCoords.x, TexCoords.y + y)).rgb;
+    vec3 c = texture(texture1, vec2(TexCoords.x + x, TexCoords.y + y)).rgb;
+
+    vec3 d = texture(texture1, vec2(TexCoords.x - x, TexCoords.y)).rgb;
+    vec3 e = texture(texture1, vec2(TexCoords.x, TexCoords.y)).rgb;
+    vec3 f = texture(texture1, vec2(TexCoords.x + x, TexCoords.y)).rgb;
+
+    vec3 g = texture(texture1, vec2(TexCoords.x - x, TexCoords.y - y)).rgb;
+    vec3 h = texture(texture1, vec2(TexCoords.x, TexCoords.y - y)).rgb;
+    vec3 i = texture(texture1, vec2(TexCoords.x + x, TexCoords.y - y)).rgb;
+
+    vec3 upsample = e * 4.0;
+    upsample += (b + d + f + h) * 2.0;
+    upsample += (a + c + g + i);
+    upsample *= 1.0 / 16.0;
+    FragColor = vec4(upsample, 1.0);
+}
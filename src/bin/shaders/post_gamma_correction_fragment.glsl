@@ -0,0 +1,13 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D texture1;
+uniform float gamma;
+
+void main()
+{
+    vec3 color = texture(texture1, TexCoords).rgb;
+    FragColor = vec4(pow(color, vec3(1.0 / gamma)), 1.0);
+}
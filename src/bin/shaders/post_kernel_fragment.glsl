@@ -0,0 +1,29 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D texture1;
+uniform float kernel[9];
+
+void main()
+{
+    vec2 offset = 1.0 / vec2(textureSize(texture1, 0));
+    vec2 offsets[9] = vec2[](
+        vec2(-offset.x,  offset.y),
+        vec2( 0.0f,      offset.y),
+        vec2( offset.x,  offset.y),
+        vec2(-offset.x,  0.0f),
+        vec2( 0.0f,      0.0f),
+        vec2( offset.x,  0.0f),
+        vec2(-offset.x, -offset.y),
+        vec2( 0.0f,     -offset.y),
+        vec2( offset.x, -offset.y)
+    );
+
+    vec3 color = vec3(0.0);
+    for (int i = 0; i < 9; i++) {
+        color += vec3(texture(texture1, TexCoords + offsets[i])) * kernel[i];
+    }
+    FragColor = vec4(color, 1.0);
+}
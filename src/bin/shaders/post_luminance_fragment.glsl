@@ -0,0 +1,13 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D texture1;
+
+void main()
+{
+    vec3 color = texture(texture1, TexCoords).rgb;
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    FragColor = vec4(log(max(luminance, 0.0001)), 0.0, 0.0, 1.0);
+}
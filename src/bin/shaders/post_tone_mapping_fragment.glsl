@@ -0,0 +1,98 @@
+#version 410 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+uniform sampler2D texture1;
+uniform sampler2D adapted_luminance;
+uniform int tone_mapping_operator;
+uniform float exposure;
+uniform float gamma;
+uniform bool auto_exposure;
+uniform float min_ev;
+uniform float max_ev;
+uniform float exposure_compensation;
+
+const int REINHARD = 0;
+const int ACES = 1;
+const int UNCHARTED2 = 2;
+const int AGX = 3;
+
+float exposureFromLuminance(float luminance) {
+    float ev100 = log2(max(luminance, 0.0001) * 100.0 / 12.5);
+    ev100 = clamp(ev100 - exposure_compensation, min_ev, max_ev);
+    return 1.0 / (1.2 * exp2(ev100));
+}
+
+vec3 reinhard(vec3 color) {
+    return color / (color + vec3(1.0));
+}
+
+vec3 aces(vec3 color) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+vec3 uncharted2Curve(vec3 x) {
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 uncharted2(vec3 color) {
+    const float exposureBias = 2.0;
+    const vec3 whitePoint = vec3(11.2);
+    return uncharted2Curve(color * exposureBias) / uncharted2Curve(whitePoint);
+}
+
+vec3 agxContrast(vec3 x) {
+    vec3 x2 = x * x;
+    vec3 x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+vec3 agx(vec3 color) {
+    const mat3 inset = mat3(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104
+    );
+    const mat3 outset = mat3(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116
+    );
+    const float minEv = -12.47393;
+    const float maxEv = 4.026069;
+    vec3 encoded = clamp(log2(max(inset * color, vec3(1e-10))), minEv, maxEv);
+    encoded = agxContrast((encoded - minEv) / (maxEv - minEv));
+    return pow(max(outset * encoded, vec3(0.0)), vec3(2.2));
+}
+
+void main()
+{
+    vec3 hdrColor = texture(texture1, TexCoords).rgb * exposure;
+    if (auto_exposure) {
+        hdrColor *= exposureFromLuminance(texture(adapted_luminance, vec2(0.5)).r);
+    }
+
+    vec3 mapped;
+    if (tone_mapping_operator == ACES) {
+        mapped = aces(hdrColor);
+    } else if (tone_mapping_operator == UNCHARTED2) {
+        mapped = uncharted2(hdrColor);
+    } else if (tone_mapping_operator == AGX) {
+        mapped = agx(hdrColor);
+    } else {
+        mapped = reinhard(hdrColor);
+    }
+    FragColor = vec4(pow(mapped, vec3(1.0 / gamma)), 1.0);
+}
@@ -0,0 +1,123 @@
+#pragma once
+#ifndef MAX_SHADOWS
+#define MAX_SHADOWS 16
+#endif
+#ifndef MAX_POINT_SHADOWS
+#define MAX_POINT_SHADOWS 8
+#endif
+struct Shadow {
+    mat4 light_space;
+    float bias;
+    int pcf_kernel;
+    float scale;
+    float split_depth;
+    int cascades;
+};
+struct PointShadow {
+    float far_plane;
+    float bias;
+    int pcf_kernel;
+};
+layout (std140) uniform Shadows {
+    mat4 shadow_view;
+    Shadow shadows[MAX_SHADOWS];
+    PointShadow point_shadows[MAX_POINT_SHADOWS];
+};
+uniform sampler2DArrayShadow shadow_maps;
+uniform samplerCubeArrayShadow point_shadow_maps;
+#ifdef DEFERRED_SHADING
+bool receive_shadows = false;
+#else
+uniform bool receive_shadows;
+#endif
+uniform bool shadow_cascades_debug;
+
+const float CASCADE_BLEND = 0.1;
+
+float shadowFactor(int index, vec3 fragPos) {
+    if (!receive_shadows || index < 0) return 0.0;
+    Shadow shadow = shadows[index];
+    vec4 lightSpacePos = shadow.light_space * vec4(fragPos, 1.0);
+    vec3 projCoords = lightSpacePos.xyz / lightSpacePos.w * 0.5 + 0.5;
+    if (projCoords.z > 1.0 || any(lessThan(projCoords.xy, vec2(0.0))) || any(greaterThan(projCoords.xy, vec2(1.0)))) {
+        return 0.0;
+    }
+    vec2 texelSize = 1.0 / vec2(textureSize(shadow_maps, 0).xy);
+    int radius = shadow.pcf_kernel / 2;
+    float lit = 0.0;
+    for (int x = -radius; x <= radius; x++) {
+        for (int y = -radius; y <= radius; y++) {
+            vec2 uv = projCoords.xy * shadow.scale + vec2(x, y) * texelSize;
+            lit += texture(shadow_maps, vec4(uv, float(index), projCoords.z - shadow.bias));
+        }
+    }
+    float samples = float((2 * radius + 1) * (2 * radius + 1));
+    return 1.0 - lit / samples;
+}
+
+float shadowViewDepth(vec3 fragPos) {
+    return -(shadow_view * vec4(fragPos, 1.0)).z;
+}
+
+int shadowCascade(int first, float depth) {
+    for (int c = 0; c < shadows[first].cascades; c++) {
+        if (depth < shadows[first + c].split_depth) return c;
+    }
+    return -1;
+}
+
+float directionalLightShadow(int i, vec3 fragPos) {
+    int first = directional_lights[i].shadow_index;
+    if (!receive_shadows || first < 0) return 0.0;
+    float depth = shadowViewDepth(fragPos);
+    int cascade = shadowCascade(first, depth);
+    if (cascade < 0) return 0.0;
+    float shadow = shadowFactor(first + cascade, fragPos);
+    float end = shadows[first + cascade].split_depth;
+    float start = cascade == 0 ? 0.0 : shadows[first + cascade - 1].split_depth;
+    float band = (end - start) * CASCADE_BLEND;
+    if (cascade + 1 < shadows[first].cascades && depth > end - band) {
+        float next = shadowFactor(first + cascade + 1, fragPos);
+        shadow = mix(shadow, next, (depth - (end - band)) / band);
+    }
+    return shadow;
+}
+
+vec4 shadowCascadeDebugColor(vec4 color, vec3 fragPos) {
+    if (!shadow_cascades_debug) return color;
+    const vec3 cascadeColors[4] = vec3[](
+        vec3(1.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0), vec3(1.0, 1.0, 0.0)
+    );
+    for (int i = 0; i < n_directional_lights; i++) {
+        int first = directional_lights[i].shadow_index;
+        if (first < 0) continue;
+        int cascade = shadowCascade(first, shadowViewDepth(fragPos));
+        if (cascade < 0) return color;
+        return vec4(mix(color.rgb, cascadeColors[cascade % 4], 0.5), color.a);
+    }
+    return color;
+}
+
+float pointLightShadow(int i, vec3 fragPos) {
+    int index = point_lights[i].shadow_index;
+    if (!receive_shadows || index < 0) return 0.0;
+    PointShadow shadow = point_shadows[index];
+    vec3 fragToLight = fragPos - point_lights[i].position;
+    float depth = length(fragToLight) / shadow.far_plane;
+    if (depth > 1.0) return 0.0;
+    float reference = depth - shadow.bias;
+    if (shadow.pcf_kernel <= 1) {
+        return 1.0 - texture(point_shadow_maps, vec4(fragToLight, float(index)), reference);
+    }
+    vec3 direction = normalize(fragToLight);
+    float diskRadius = float(shadow.pcf_kernel) / float(textureSize(point_shadow_maps, 0).x);
+    float lit = 0.0;
+    for (int s = 0; s < 20; s++) {
+        lit += texture(point_shadow_maps, vec4(direction + sampleOffsetDirections[s] * diskRadius, float(index)), reference);
+    }
+    return 1.0 - lit / 20.0;
+}
+
+float spotLightShadow(int i, vec3 fragPos) {
+    return shadowFactor(spot_lights[i].shadow_index, fragPos);
+}
@@ -1,3 +1,4 @@
+#pragma once
 struct DirectionalLight {
     vec3 direction;
     vec3 ambient;
@@ -1,3 +1,4 @@
+#pragma once
 struct Material {
     sampler2D diffuse;
     sampler2D specular;
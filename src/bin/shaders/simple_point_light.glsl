@@ -1,3 +1,4 @@
+#pragma once
 struct PointLight {
     vec3 position;
 
@@ -1,3 +1,7 @@
+#pragma once
+#include "ambient_occlusion.glsl"
+#include "light_attenuation.glsl"
+
 struct SpotLight {
     vec3 direction;
     vec3 position;
@@ -12,10 +16,12 @@ struct SpotLight {
     float linear;
     float quadratic;
     bool set;
+    int shadow_index;
+    float range;
 };
 
 vec3 calculateSpotLightWithPositionAndDirection(
-    SpotLight light, vec3 position, vec3 direction, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
+    SpotLight light, vec3 position, vec3 direction, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords, float shadow
 ) {
     if (!light.set) return vec3(0.0);
     vec3 lightDir = normalize(position - fragPos);
@@ -30,13 +36,12 @@ vec3 calculateSpotLightWithPositionAndDirection(
     float spec = pow(max(dot(normal, halfwayDir), 0.0), material.shininess);
 
     float distance    = length(position - fragPos);
-    float attenuation = 1.0 / (light.constant + light.linear * distance +
-    light.quadratic * (distance * distance));
+    float attenuation = lightAttenuation(light.constant, light.linear, light.quadratic, light.range, distance);
 
     vec3 ambient = vec3(0.0);
     vec3 diffuse = vec3(0.0);
     if (material.n_diffuse > 0) {
-        ambient += light.ambient * vec3(texture(material.diffuse0, texCoords));
+        ambient += light.ambient * vec3(texture(material.diffuse0, texCoords)) * ambientOcclusion();
         diffuse += light.diffuse * diff * vec3(texture(material.diffuse0, texCoords));
 
         ambient *= attenuation;
@@ -44,12 +49,24 @@ vec3 calculateSpotLightWithPositionAndDirection(
     }
 
     vec3 specular = vec3(0.0);
-    if (material.n_specular > 0) {
+    if (MATERIAL_HAS_SPECULAR(material)) {
         specular += light.specular * spec * vec3(texture(material.specular0, texCoords));
         specular *= attenuation * intensity;
     }
 
-    return ambient + diffuse + specular;
+    return ambient + (1.0 - shadow) * (diffuse + specular);
+}
+
+vec3 calculateSpotLightWithPositionAndDirection(
+    SpotLight light, vec3 position, vec3 direction, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords
+) {
+    return calculateSpotLightWithPositionAndDirection(light, position, direction, material, normal, fragPos, viewDir, texCoords, 0.0);
+}
+
+vec3 calculateSpotLight(
+    SpotLight light, Material material, vec3 normal, vec3 fragPos, vec3 viewDir, vec2 texCoords, float shadow
+) {
+    return calculateSpotLightWithPositionAndDirection(light, light.position, light.direction, material, normal, fragPos, viewDir, texCoords, shadow);
 }
 
 vec3 calculateSpotLight(
@@ -57,3 +74,28 @@ vec3 calculateSpotLight(
 ) {
     return calculateSpotLightWithPositionAndDirection(light, light.position, light.direction, material, normal, fragPos, viewDir, texCoords);
 }
+
+vec3 calculateSpotLightWithoutMaterial(
+    SpotLight light, float shininess, vec3 diffuseColor, vec3 specularColor, vec3 normal, vec3 fragPos, vec3 viewDir, float shadow
+) {
+    if (!light.set) return vec3(0.0);
+    vec3 lightDir = normalize(light.position - fragPos);
+    float theta = dot(lightDir, normalize(-light.direction));
+
+    float diff = max(dot(lightDir, normal), 0.0);
+
+    float epsilon = light.cutOff - light.outerCutOff;
+    float intensity = clamp((theta - light.outerCutOff) / epsilon, 0.0, 1.0);
+
+    vec3 halfwayDir = normalize(lightDir + viewDir);
+    float spec = pow(max(dot(normal, halfwayDir), 0.0), shininess);
+
+    float distance    = length(light.position - fragPos);
+    float attenuation = lightAttenuation(light.constant, light.linear, light.quadratic, light.range, distance);
+
+    vec3 ambient = light.ambient * diffuseColor * attenuation * ambientOcclusion();
+    vec3 diffuse = light.diffuse * diff * diffuseColor * attenuation * intensity;
+    vec3 specular = light.specular * spec * specularColor * attenuation * intensity;
+
+    return ambient + (1.0 - shadow) * (diffuse + specular);
+}
@@ -0,0 +1,37 @@
+#version 410 core
+out float FragColor;
+
+in vec2 TexCoords;
+
+layout (std140) uniform Matrices {
+    mat4 view;
+    mat4 projection;
+};
+
+uniform sampler2D gPosition;
+uniform sampler2D ssao_input;
+uniform float ssao_radius;
+
+float viewDepth(vec2 texCoords) {
+    vec4 position = texture(gPosition, texCoords);
+    return position.a <= 0.0 ? -1e6 : (view * vec4(position.xyz, 1.0)).z;
+}
+
+void main() {
+    vec2 texelSize = 1.0 / vec2(textureSize(ssao_input, 0));
+    vec2 depthTexelSize = 1.0 / vec2(textureSize(gPosition, 0));
+    float depth = viewDepth(TexCoords);
+    float falloff = max(ssao_radius * 0.25, 0.0001);
+    float result = 0.0;
+    float weights = 0.0;
+    for (int x = -2; x < 2; ++x) {
+        for (int y = -2; y < 2; ++y) {
+            vec2 offset = (vec2(x, y) + 0.5) * texelSize;
+            float sampleDepth = viewDepth(TexCoords + offset + 0.5 * depthTexelSize);
+            float weight = exp(-abs(sampleDepth - depth) / falloff);
+            result += texture(ssao_input, TexCoords + offset).r * weight;
+            weights += weight;
+        }
+    }
+    FragColor = weights > 0.0 ? result / weights : texture(ssao_input, TexCoords).r;
+}
@@ -0,0 +1,53 @@
+#version 410 core
+out float FragColor;
+
+in vec2 TexCoords;
+
+layout (std140) uniform Matrices {
+    mat4 view;
+    mat4 projection;
+};
+
+uniform sampler2D gPosition;
+uniform sampler2D gNormal;
+uniform sampler2D ssao_kernel;
+uniform sampler2D ssao_noise;
+uniform int ssao_samples;
+uniform float ssao_radius;
+uniform float ssao_bias;
+uniform float ssao_power;
+
+void main() {
+    vec4 position = texture(gPosition, TexCoords);
+    if (position.a <= 0.0) {
+        FragColor = 1.0;
+        return;
+    }
+    vec3 fragPos = (view * vec4(position.xyz, 1.0)).xyz;
+    vec3 normal = normalize(mat3(view) * texture(gNormal, TexCoords).xyz);
+    ivec2 noiseSize = textureSize(ssao_noise, 0);
+    vec3 randomVec = texelFetch(ssao_noise, ivec2(gl_FragCoord.xy) % noiseSize, 0).xyz;
+
+    vec3 tangent = normalize(randomVec - normal * dot(randomVec, normal));
+    vec3 bitangent = cross(normal, tangent);
+    mat3 TBN = mat3(tangent, bitangent, normal);
+    float occlusion = 0.0;
+    for (int i = 0; i < ssao_samples; ++i) {
+        vec3 samplePos = fragPos + TBN * texelFetch(ssao_kernel, ivec2(i, 0), 0).xyz * ssao_radius;
+
+        vec4 offset = projection * vec4(samplePos, 1.0);
+        offset.xyz /= offset.w;
+        offset.xyz = offset.xyz * 0.5 + 0.5;
+
+        vec4 samplePosition = texture(gPosition, offset.xy);
+        if (samplePosition.a <= 0.0) {
+            continue;
+        }
+        float sampleDepth = (view * vec4(samplePosition.xyz, 1.0)).z;
+        float rangeCheck = smoothstep(0.0, 1.0, ssao_radius / abs(fragPos.z - sampleDepth));
+        occlusion += (sampleDepth >= samplePos.z + ssao_bias ? 1.0 : 0.0) * rangeCheck;
+    }
+    occlusion = 1.0 - occlusion / float(max(ssao_samples, 1));
+
+    FragColor = pow(occlusion, ssao_power);
+}
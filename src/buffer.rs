@@ -4,7 +4,7 @@ use std::mem::{size_of, transmute};
 use std::ptr;
 
 #[derive(Debug)]
-pub struct Buffer(gl::types::GLuint, gl::types::GLenum);
+pub struct Buffer(pub(crate) gl::types::GLuint, pub(crate) gl::types::GLenum);
 
 impl Buffer {
     pub fn new(buffer_type: gl::types::GLenum) -> Buffer {
@@ -1,5 +1,9 @@
 use nalgebra::{Matrix3, Matrix4, Perspective3, Point3, Translation3, Unit, UnitVector3, Vector3};
 
+const ASPECT_RATIO: f32 = 800f32 / 600f32;
+const NEAR_PLANE: f32 = 0.1;
+const FAR_PLANE: f32 = 100f32;
+
 pub struct Camera {
     position: Vector3<f32>,
     front: Vector3<f32>,
@@ -44,8 +48,20 @@ impl Camera {
         self.fov = self.fov.clamp(1f32, 45f32);
     }
 
+    pub fn aspect_ratio(&self) -> f32 {
+        ASPECT_RATIO
+    }
+
+    pub fn near_plane(&self) -> f32 {
+        NEAR_PLANE
+    }
+
+    pub fn far_plane(&self) -> f32 {
+        FAR_PLANE
+    }
+
     pub fn projection(&self) -> Matrix4<f32> {
-        Perspective3::new(800f32 / 600f32, self.fov.to_radians(), 0.1, 100f32).to_homogeneous()
+        Perspective3::new(ASPECT_RATIO, self.fov.to_radians(), NEAR_PLANE, FAR_PLANE).to_homogeneous()
     }
 
     pub fn ground(&mut self) {
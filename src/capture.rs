@@ -0,0 +1,177 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use image::RgbaImage;
+use crate::error::Error;
+use crate::window::Window;
+
+const CAPTURE_VARIABLE: &'static str = "LEARNOPENGL_CAPTURE";
+const CAPTURE_SECONDS_VARIABLE: &'static str = "LEARNOPENGL_CAPTURE_SECONDS";
+const CAPTURE_FRAME_RATE_VARIABLE: &'static str = "LEARNOPENGL_CAPTURE_FRAME_RATE";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CaptureFormat {
+    PngSequence,
+    Y4m,
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptureOptions {
+    pub output: PathBuf,
+    pub format: CaptureFormat,
+    pub frame_rate: u32,
+    pub frames: usize,
+}
+
+impl CaptureOptions {
+    pub fn new<P: Into<PathBuf>>(output: P, format: CaptureFormat, frame_rate: u32, seconds: f32) -> Result<CaptureOptions, Error> {
+        if frame_rate == 0 {
+            return Err("Capture frame rate must be at least 1".to_string().into());
+        }
+        Ok(CaptureOptions {
+            output: output.into(),
+            format,
+            frame_rate,
+            frames: (seconds * frame_rate as f32).ceil() as usize,
+        })
+    }
+
+    pub fn from_env() -> Result<Option<CaptureOptions>, Error> {
+        let output = match std::env::var(CAPTURE_VARIABLE) {
+            Ok(output) => PathBuf::from(output),
+            Err(_) => return Ok(None),
+        };
+        let format = match output.extension().and_then(|e| e.to_str()) {
+            Some("y4m") => CaptureFormat::Y4m,
+            _ => CaptureFormat::PngSequence,
+        };
+        let seconds = std::env::var(CAPTURE_SECONDS_VARIABLE).ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5f32);
+        let frame_rate = std::env::var(CAPTURE_FRAME_RATE_VARIABLE).ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(60);
+        CaptureOptions::new(output, format, frame_rate, seconds)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", CAPTURE_FRAME_RATE_VARIABLE, e).into())
+    }
+
+    pub fn timestep(&self) -> f32 {
+        1_000_000f32 / self.frame_rate as f32
+    }
+}
+
+pub struct FrameCapture {
+    options: CaptureOptions,
+    stream: Option<BufWriter<File>>,
+    recorded_frames: usize,
+}
+
+impl FrameCapture {
+    pub fn new(options: CaptureOptions, width: usize, height: usize) -> Result<FrameCapture, Error> {
+        let stream = match options.format {
+            CaptureFormat::PngSequence => {
+                std::fs::create_dir_all(&options.output).map_err(|e| Error::io(&options.output, e))?;
+                None
+            }
+            CaptureFormat::Y4m => {
+                if let Some(parent) = options.output.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+                }
+                let mut stream = BufWriter::new(File::create(&options.output).map_err(|e| Error::io(&options.output, e))?);
+                write!(stream, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444\n", width, height, options.frame_rate)
+                    .map_err(|e| Error::io(&options.output, e))?;
+                Some(stream)
+            }
+        };
+        Ok(FrameCapture {
+            options,
+            stream,
+            recorded_frames: 0,
+        })
+    }
+
+    pub fn timestep(&self) -> f32 {
+        self.options.timestep()
+    }
+
+    pub fn recorded_frames(&self) -> usize {
+        self.recorded_frames
+    }
+
+    pub fn finished(&self) -> bool {
+        self.recorded_frames >= self.options.frames
+    }
+
+    pub fn record(&mut self, window: &Window) -> Result<(), Error> {
+        if self.finished() {
+            return Ok(());
+        }
+        let image = window.read_pixels()?;
+        match self.stream.as_mut() {
+            Some(stream) => write_y4m_frame(stream, &image)
+                .map_err(|e| Error::io(&self.options.output, e))?,
+            None => {
+                let path = self.options.output.join(format!("frame_{:05}.png", self.recorded_frames));
+                image.save(&path).map_err(|e| Error::image_encode(path, e))?
+            }
+        }
+        self.recorded_frames += 1;
+        if self.finished() {
+            if let Some(stream) = self.stream.as_mut() {
+                stream.flush().map_err(|e| Error::io(&self.options.output, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_y4m_frame<W: Write>(stream: &mut W, image: &RgbaImage) -> std::io::Result<()> {
+    let pixels = image.width() as usize * image.height() as usize;
+    let mut planes = vec![0u8; pixels * 3];
+    for (i, pixel) in image.pixels().enumerate() {
+        let r = pixel.0[0] as f32 / 255f32;
+        let g = pixel.0[1] as f32 / 255f32;
+        let b = pixel.0[2] as f32 / 255f32;
+        planes[i] = (16f32 + 65.481 * r + 128.553 * g + 24.966 * b).round() as u8;
+        planes[pixels + i] = (128f32 - 37.797 * r - 74.203 * g + 112f32 * b).round() as u8;
+        planes[2 * pixels + i] = (128f32 + 112f32 * r - 93.786 * g - 18.214 * b).round() as u8;
+    }
+    stream.write_all(b"FRAME\n")?;
+    stream.write_all(&planes)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+    use super::*;
+
+    #[test]
+    fn zero_frame_rate_is_rejected() {
+        assert!(CaptureOptions::new("capture.y4m", CaptureFormat::Y4m, 0, 5.0).is_err());
+    }
+
+    #[test]
+    fn frame_count_covers_the_duration() {
+        assert_eq!(CaptureOptions::new("capture.y4m", CaptureFormat::Y4m, 30, 2.0).unwrap().frames, 60);
+        assert_eq!(CaptureOptions::new("capture", CaptureFormat::PngSequence, 25, 0.5).unwrap().frames, 13);
+        assert_eq!(CaptureOptions::new("capture", CaptureFormat::PngSequence, 60, 0.0).unwrap().frames, 0);
+    }
+
+    #[test]
+    fn y4m_frames_are_planar_c444() {
+        let mut image = RgbaImage::new(2, 2);
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+        image.put_pixel(0, 1, Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 1, Rgba([0, 0, 255, 0]));
+        let mut frame = vec![];
+        write_y4m_frame(&mut frame, &image).unwrap();
+        assert_eq!(&frame[..6], b"FRAME\n");
+        assert_eq!(&frame[6..], &[
+            16, 235, 81, 41,
+            128, 128, 90, 240,
+            128, 128, 240, 110,
+        ]);
+    }
+}
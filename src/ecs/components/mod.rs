@@ -1,13 +1,19 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 use itertools::multizip;
-use nalgebra::{ArrayStorage, Matrix, Matrix4, Rotation3, Scale3, Translation3, U1, Vector2, Vector3};
+use nalgebra::{ArrayStorage, Matrix, Matrix2, Matrix3, Matrix4, Matrix4x3, Rotation3, Scale3, Translation3, U1, Vector2, Vector3, Vector4};
+use log::warn;
 use russimp::texture::TextureType;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
+use crate::error::Error;
 use crate::buffer::Buffer;
 use crate::ecs::systems::input::InputType;
 use crate::ecs::systems::rendering::RenderingSystem;
+use crate::pixels::ScreenshotFormat;
 use crate::program::Program;
+use crate::reflection::{gl_type_name, is_sampler};
+use crate::uniform::UniformType;
 use crate::texture::Texture;
 use crate::vertex_array::VertexArray;
 
@@ -33,16 +39,159 @@ pub enum UniformValue {
     Texture(u32),
     Matrix(Matrix4<f32>),
     Vector3(Vector3<f32>),
+    Bool(bool),
+    Int(i32),
+    UInt(u32),
+    Vector2(Vector2<f32>),
+    Vector4(Vector4<f32>),
+    IVector2(Vector2<i32>),
+    IVector3(Vector3<i32>),
+    IVector4(Vector4<i32>),
+    UVector2(Vector2<u32>),
+    UVector3(Vector3<u32>),
+    UVector4(Vector4<u32>),
+    Matrix2(Matrix2<f32>),
+    Matrix3(Matrix3<f32>),
+    Matrix3x4(Matrix4x3<f32>),
+    Array(Vec<UniformValue>),
 }
+
+impl UniformValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            UniformValue::Float(_) => "float",
+            UniformValue::Texture(_) => "sampler",
+            UniformValue::Matrix(_) => "mat4",
+            UniformValue::Vector3(_) => "vec3",
+            UniformValue::Bool(_) => "bool",
+            UniformValue::Int(_) => "int",
+            UniformValue::UInt(_) => "uint",
+            UniformValue::Vector2(_) => "vec2",
+            UniformValue::Vector4(_) => "vec4",
+            UniformValue::IVector2(_) => "ivec2",
+            UniformValue::IVector3(_) => "ivec3",
+            UniformValue::IVector4(_) => "ivec4",
+            UniformValue::UVector2(_) => "uvec2",
+            UniformValue::UVector3(_) => "uvec3",
+            UniformValue::UVector4(_) => "uvec4",
+            UniformValue::Matrix2(_) => "mat2",
+            UniformValue::Matrix3(_) => "mat3",
+            UniformValue::Matrix3x4(_) => "mat3x4",
+            UniformValue::Array(values) => values.first().map(|v| v.type_name()).unwrap_or("array"),
+        }
+    }
+
+    pub fn accepts(&self, gl_type: gl::types::GLenum) -> bool {
+        match self {
+            UniformValue::Float(_) => <f32 as UniformType>::accepts(gl_type),
+            UniformValue::Texture(_) => is_sampler(gl_type),
+            UniformValue::Matrix(_) => <Matrix4<f32> as UniformType>::accepts(gl_type),
+            UniformValue::Vector3(_) => <Vector3<f32> as UniformType>::accepts(gl_type),
+            UniformValue::Bool(_) => <bool as UniformType>::accepts(gl_type),
+            UniformValue::Int(_) => <i32 as UniformType>::accepts(gl_type),
+            UniformValue::UInt(_) => <u32 as UniformType>::accepts(gl_type),
+            UniformValue::Vector2(_) => <Vector2<f32> as UniformType>::accepts(gl_type),
+            UniformValue::Vector4(_) => <Vector4<f32> as UniformType>::accepts(gl_type),
+            UniformValue::IVector2(_) => <Vector2<i32> as UniformType>::accepts(gl_type),
+            UniformValue::IVector3(_) => <Vector3<i32> as UniformType>::accepts(gl_type),
+            UniformValue::IVector4(_) => <Vector4<i32> as UniformType>::accepts(gl_type),
+            UniformValue::UVector2(_) => <Vector2<u32> as UniformType>::accepts(gl_type),
+            UniformValue::UVector3(_) => <Vector3<u32> as UniformType>::accepts(gl_type),
+            UniformValue::UVector4(_) => <Vector4<u32> as UniformType>::accepts(gl_type),
+            UniformValue::Matrix2(_) => <Matrix2<f32> as UniformType>::accepts(gl_type),
+            UniformValue::Matrix3(_) => <Matrix3<f32> as UniformType>::accepts(gl_type),
+            UniformValue::Matrix3x4(_) => <Matrix4x3<f32> as UniformType>::accepts(gl_type),
+            UniformValue::Array(values) => values.iter().all(|v| v.accepts(gl_type)),
+        }
+    }
+
+    pub fn set(&self, program: &Program, name: &str) {
+        match self {
+            UniformValue::Float(v) => program.set_uniform(name, v),
+            UniformValue::Texture(v) => program.set_uniform(name, &(*v as i32)),
+            UniformValue::Matrix(v) => program.set_uniform(name, v),
+            UniformValue::Vector3(v) => program.set_uniform(name, v),
+            UniformValue::Bool(v) => program.set_uniform(name, v),
+            UniformValue::Int(v) => program.set_uniform(name, v),
+            UniformValue::UInt(v) => program.set_uniform(name, v),
+            UniformValue::Vector2(v) => program.set_uniform(name, v),
+            UniformValue::Vector4(v) => program.set_uniform(name, v),
+            UniformValue::IVector2(v) => program.set_uniform(name, v),
+            UniformValue::IVector3(v) => program.set_uniform(name, v),
+            UniformValue::IVector4(v) => program.set_uniform(name, v),
+            UniformValue::UVector2(v) => program.set_uniform(name, v),
+            UniformValue::UVector3(v) => program.set_uniform(name, v),
+            UniformValue::UVector4(v) => program.set_uniform(name, v),
+            UniformValue::Matrix2(v) => program.set_uniform(name, v),
+            UniformValue::Matrix3(v) => program.set_uniform(name, v),
+            UniformValue::Matrix3x4(v) => program.set_uniform(name, v),
+            UniformValue::Array(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    value.set(program, &format!("{}[{}]", name, i));
+                }
+            }
+        }
+    }
+}
+
+fn check_uniform(program: &Program, name: &str, value: &UniformValue) -> Result<(), Error> {
+    let reflection = program.reflection();
+    match reflection.uniform(name) {
+        Some(uniform) if !value.accepts(uniform.gl_type) => Err(Error::UniformType {
+            name: name.to_string(),
+            declared: gl_type_name(uniform.gl_type),
+            given: value.type_name(),
+        }),
+        Some(_) => Ok(()),
+        None => {
+            warn!("Uniform {} is not used by the program", name);
+            Ok(())
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ExtraUniform {
     pub name: &'static str,
     pub value: UniformValue,
 }
+
+impl ExtraUniform {
+    pub fn validate(&self, program: &Program) -> Result<(), Error> {
+        check_uniform(program, self.name, &self.value)
+    }
+}
 #[derive(Clone, Debug)]
 pub struct SkipRendering;
 #[derive(Clone, Debug)]
 pub struct Transparent;
+#[derive(Clone, Debug)]
+pub struct ShadowCaster;
+#[derive(Clone, Debug)]
+pub struct ShadowReceiver;
+
+#[derive(Clone, Debug)]
+pub struct CastsShadows {
+    pub resolution: u32,
+    pub bias: f32,
+    pub pcf_kernel: u32,
+    pub cascades: u32,
+    pub split_lambda: f32,
+    pub distance: f32,
+}
+
+impl Default for CastsShadows {
+    fn default() -> CastsShadows {
+        CastsShadows {
+            resolution: 1024,
+            bias: 0.005,
+            pcf_kernel: 3,
+            cascades: 4,
+            split_lambda: 0.5,
+            distance: 50.0,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Input {
@@ -69,6 +218,19 @@ pub struct QuitControl {
     pub quit_keycode: Keycode,
 }
 
+#[derive(Clone, Debug)]
+pub struct ScreenshotControl {
+    pub keycode: Keycode,
+    pub format: ScreenshotFormat,
+    pub directory: PathBuf,
+}
+
+#[derive(Clone, Debug)]
+pub struct PostEffectControl {
+    pub keycode: Keycode,
+    pub effect: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct TextureInfo {
     pub id: usize,
@@ -168,14 +330,14 @@ pub struct InstancedModel {
 }
 
 impl InstancedModel {
-    pub fn new(meshes: Vec<Mesh>, rendering: &mut RenderingSystem, models: Vec<Matrix4<f32>>) -> Result<InstancedModel, String> {
+    pub fn new(meshes: Vec<Mesh>, rendering: &mut RenderingSystem, models: Vec<Matrix4<f32>>) -> Result<InstancedModel, Error> {
         Ok(InstancedModel {
             models,
             model: meshes.into_iter().map(|m| {
                 let shader = rendering.shader_for_mesh(&m)?;
                 let shader = rendering.instanced_rendering.shader_for_mesh(&shader)?;
                 Ok((m, shader))
-            }).collect::<Result<Vec<_>, String>>()?,
+            }).collect::<Result<Vec<_>, Error>>()?,
         })
     }
 }
@@ -201,7 +363,8 @@ impl Mesh {
         }
     }
 
-    pub fn set_program(&self, program: &Program, textures: &[Arc<Texture>]) {
+    fn texture_uniforms(&self) -> (Vec<(String, i32)>, i32, i32, i32) {
+        let mut uniforms = vec![];
         let mut diffuse_index = 0;
         let mut specular_index = 0;
         let mut normal_index = 0;
@@ -210,8 +373,7 @@ impl Mesh {
         let mut roughness_index = 0;
         let mut ao_index = 0;
         if let Some(infos) = &self.textures {
-            for (texture, info) in textures.iter().zip(infos.iter()) {
-                texture.bind(gl::TEXTURE0 + info.id as u32);
+            for info in infos.iter() {
                 let (texture_type, texture_index) = if info.texture_type == TextureType::Diffuse {
                     let index = diffuse_index;
                     diffuse_index += 1;
@@ -243,9 +405,39 @@ impl Mesh {
                 } else {
                     panic!("Can't happen");
                 };
-                program.set_uniform_i1(&format!("material.{}{}", texture_type, texture_index), info.id as i32);
+                uniforms.push((format!("material.{}{}", texture_type, texture_index), info.id as i32));
             }
         }
+        (uniforms, diffuse_index, specular_index, height_index)
+    }
+
+    pub fn validate_program(&self, program: &Program) -> Result<(), Error> {
+        let (uniforms, _, _, _) = self.texture_uniforms();
+        let reflection = program.reflection();
+        for (name, _) in uniforms {
+            if let Some(uniform) = reflection.uniform(&name) {
+                if !uniform.is_sampler() {
+                    return Err(Error::UniformType {
+                        name,
+                        declared: gl_type_name(uniform.gl_type),
+                        given: "sampler",
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_program(&self, program: &Program, textures: &[Arc<Texture>]) {
+        if let Some(infos) = &self.textures {
+            for (texture, info) in textures.iter().zip(infos.iter()) {
+                texture.bind(gl::TEXTURE0 + info.id as u32);
+            }
+        }
+        let (uniforms, diffuse_index, specular_index, height_index) = self.texture_uniforms();
+        for (name, unit) in uniforms {
+            program.set_uniform_i1(&name, unit);
+        }
         program.set_uniform_i1("material.n_diffuse", diffuse_index);
         program.set_uniform_i1("material.n_specular", specular_index);
         program.set_uniform_i1("material.n_height", height_index);
@@ -408,14 +600,14 @@ impl Mesh {
 pub struct Model(pub Vec<(Mesh, Shader)>);
 
 impl Model {
-    pub fn from_meshes(meshes: Vec<Mesh>, rendering: &mut RenderingSystem) -> Result<Model, String> {
+    pub fn from_meshes(meshes: Vec<Mesh>, rendering: &mut RenderingSystem) -> Result<Model, Error> {
         Ok(Model(
             meshes.into_iter()
                 .map(|m| {
                     let shader = rendering.shader_for_mesh(&m)?;
                     Ok((m, shader))
                 })
-                .collect::<Result<Vec<(Mesh, Shader)>, String>>()?
+                .collect::<Result<Vec<(Mesh, Shader)>, Error>>()?
         ))
     }
 }
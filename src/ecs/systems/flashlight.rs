@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use hecs::World;
 use nalgebra::UnitVector3;
+use crate::error::Error;
 use crate::camera::Camera;
 use crate::ecs::systems::system::System;
 use crate::light::{FlashLight, SpotLight};
@@ -15,15 +16,15 @@ impl System for FlashLightSystem {
         "FlashLight"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn update(&self, world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
         for (_e, (flashlight, spotlight)) in world.query_mut::<(&FlashLight, &mut SpotLight)>() {
             spotlight.set_direction(UnitVector3::new_normalize((*self.camera).borrow().front() - flashlight.offset_from_camera));
             spotlight.set_position((*self.camera).borrow().position() - flashlight.offset_from_camera);
@@ -31,7 +32,7 @@ impl System for FlashLightSystem {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 }
\ No newline at end of file
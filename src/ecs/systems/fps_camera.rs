@@ -4,6 +4,7 @@ use hecs::World;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::mouse::MouseUtil;
+use crate::error::Error;
 use crate::camera::Camera;
 use crate::ecs::components::{FpsCamera, Input};
 use crate::ecs::systems::system::System;
@@ -18,12 +19,12 @@ impl System for FpsCameraSystem {
         "FPS Camera"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         self.mouse.show_cursor(false);
         Ok(())
     }
 
-    fn early_update(&self, world: &mut World, delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, world: &mut World, delta_time: f32) -> Result<(), Error> {
         for (_e, (input, fps_camera)) in world.query_mut::<(&Input, &FpsCamera)>() {
             let camera_speed = delta_time * fps_camera.camera_speed;
             for event in input.events.iter() {
@@ -68,11 +69,11 @@ impl System for FpsCameraSystem {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 }
\ No newline at end of file
@@ -4,6 +4,7 @@ use hecs::World;
 use sdl2::event::Event;
 use sdl2::EventPump;
 use sdl2::keyboard::Keycode;
+use crate::error::Error;
 use crate::ecs::components::Input;
 use crate::ecs::systems::system::System;
 
@@ -37,12 +38,12 @@ impl System for InputSystem {
         "Input"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         self.pressed_down.borrow_mut().drain();
         Ok(())
     }
 
-    fn early_update(&self, world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
         let mut events_by_type = HashMap::new();
         for event in self.event_pumper.borrow_mut().poll_iter() {
             let event_type = InputType::from(&event);
@@ -88,11 +89,11 @@ impl System for InputSystem {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 pub mod system;
 pub mod rendering;
+pub mod post_processing;
 pub mod flashlight;
 pub mod input;
 pub mod fps_camera;
-pub mod quit_system;
\ No newline at end of file
+pub mod quit_system;
+pub mod screenshot;
\ No newline at end of file
@@ -0,0 +1,174 @@
+use std::cell::{Cell, RefCell};
+use nalgebra::Vector2;
+use crate::ecs::systems::post_processing::EffectPass;
+use crate::error::Error;
+use crate::frame_buffer::FrameBuffer;
+use crate::gl_function;
+use crate::program::Program;
+use crate::quad::Quad;
+use crate::shader_loader::{ShaderLoader, ShaderType};
+use crate::texture::{Texture, TextureFormat};
+
+static VERTEX_SHADER: &'static str = "15.1-postprocessing_vertex.glsl";
+static DOWNSAMPLE_FRAGMENT_SHADER: &'static str = "post_bloom_downsample_fragment.glsl";
+static UPSAMPLE_FRAGMENT_SHADER: &'static str = "post_bloom_upsample_fragment.glsl";
+pub static BLOOM_FRAGMENT_SHADER: &'static str = "post_bloom_fragment.glsl";
+const BLOOM_TEXTURE_UNIT: u32 = 3;
+
+#[derive(Clone, Debug)]
+pub struct BloomSettings {
+    pub threshold: f32,
+    pub knee: f32,
+    pub radius: f32,
+    pub intensity: f32,
+    pub mips: usize,
+}
+
+impl Default for BloomSettings {
+    fn default() -> BloomSettings {
+        BloomSettings {
+            threshold: 1.0,
+            knee: 0.5,
+            radius: 0.005,
+            intensity: 0.5,
+            mips: 6,
+        }
+    }
+}
+
+fn mip_frame_buffer(width: u32, height: u32) -> Result<FrameBuffer, Error> {
+    let frame_buffer = FrameBuffer::intermediate_with_format(width, height, TextureFormat::FloatingPoint)?;
+    frame_buffer.texture.just_bind();
+    frame_buffer.texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+    frame_buffer.texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+    frame_buffer.texture.set_parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+    frame_buffer.texture.set_parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+    frame_buffer.texture.unbind();
+    Ok(frame_buffer)
+}
+
+fn texture_size(texture: &Texture) -> (u32, u32) {
+    let (mut width, mut height) = (0, 0);
+    texture.just_bind();
+    gl_function!(GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_WIDTH, &mut width));
+    gl_function!(GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_HEIGHT, &mut height));
+    (width.max(1) as u32, height.max(1) as u32)
+}
+
+pub struct Bloom {
+    downsample_program: Program,
+    mips: RefCell<Vec<FrameBuffer>>,
+    mips_size: Cell<(u32, u32, usize)>,
+    settings: RefCell<BloomSettings>,
+    upsample_program: Program,
+}
+
+impl Bloom {
+    pub fn new(shader_loader: &ShaderLoader, settings: BloomSettings) -> Result<Bloom, Error> {
+        let downsample_program = shader_loader.program(vec![
+            (ShaderType::Vertex, VERTEX_SHADER),
+            (ShaderType::Fragment, DOWNSAMPLE_FRAGMENT_SHADER),
+        ])?;
+        let upsample_program = shader_loader.program(vec![
+            (ShaderType::Vertex, VERTEX_SHADER),
+            (ShaderType::Fragment, UPSAMPLE_FRAGMENT_SHADER),
+        ])?;
+        Ok(Bloom {
+            downsample_program,
+            mips: RefCell::new(vec![]),
+            mips_size: Cell::new((0, 0, 0)),
+            settings: RefCell::new(settings),
+            upsample_program,
+        })
+    }
+
+    pub fn settings(&self) -> BloomSettings {
+        self.settings.borrow().clone()
+    }
+
+    pub fn set_settings(&self, settings: BloomSettings) {
+        self.settings.replace(settings);
+    }
+
+    pub fn set_threshold(&self, threshold: f32, knee: f32) {
+        self.set_settings(BloomSettings { threshold, knee, ..self.settings() });
+    }
+
+    pub fn set_radius(&self, radius: f32) {
+        self.set_settings(BloomSettings { radius, ..self.settings() });
+    }
+
+    pub fn set_intensity(&self, intensity: f32) {
+        self.set_settings(BloomSettings { intensity, ..self.settings() });
+    }
+
+    fn resize(&self, width: u32, height: u32, mips: usize) -> Result<(), Error> {
+        if self.mips_size.get() == (width, height, mips) {
+            return Ok(());
+        }
+        let mut chain = self.mips.borrow_mut();
+        let (mut mip_width, mut mip_height) = (width / 2, height / 2);
+        chain.clear();
+        while chain.len() < mips.max(1) && mip_width >= 2 && mip_height >= 2 {
+            chain.push(mip_frame_buffer(mip_width, mip_height)?);
+            mip_width /= 2;
+            mip_height /= 2;
+        }
+        if chain.is_empty() {
+            chain.push(mip_frame_buffer(1, 1)?);
+        }
+        self.mips_size.set((width, height, mips));
+        Ok(())
+    }
+}
+
+impl EffectPass for Bloom {
+    fn render(&self, input: &Texture, quad: &Quad, _delta_time: f32) -> Result<(), Error> {
+        let settings = self.settings.borrow();
+        let (width, height) = texture_size(input);
+        self.resize(width, height, settings.mips)?;
+        let mips = self.mips.borrow();
+
+        self.downsample_program.use_program();
+        self.downsample_program.set_uniform_i1("texture1", 0);
+        self.downsample_program.set_uniform_f1("threshold", settings.threshold);
+        self.downsample_program.set_uniform_f1("knee", settings.knee);
+        let mut source = input;
+        let mut source_size = (width, height);
+        for (i, mip) in mips.iter().enumerate() {
+            mip.bind();
+            gl_function!(Viewport(0, 0, mip.width() as _, mip.height() as _));
+            source.bind(gl::TEXTURE0);
+            self.downsample_program.set_uniform(
+                "source_texel_size", &Vector2::new(1.0 / source_size.0 as f32, 1.0 / source_size.1 as f32),
+            );
+            self.downsample_program.set_uniform("prefilter", &(i == 0));
+            quad.draw();
+            source = &mip.texture;
+            source_size = (mip.width(), mip.height());
+        }
+
+        self.upsample_program.use_program();
+        self.upsample_program.set_uniform_i1("texture1", 0);
+        self.upsample_program.set_uniform_f1("radius", settings.radius);
+        gl_function!(Enable(gl::BLEND));
+        gl_function!(BlendFunc(gl::ONE, gl::ONE));
+        for window in mips.windows(2).rev() {
+            let (target, source) = (&window[0], &window[1]);
+            target.bind();
+            gl_function!(Viewport(0, 0, target.width() as _, target.height() as _));
+            source.texture.bind(gl::TEXTURE0);
+            quad.draw();
+        }
+        gl_function!(Disable(gl::BLEND));
+        Ok(())
+    }
+
+    fn apply(&self, program: &Program) {
+        if let Some(mip) = self.mips.borrow().first() {
+            mip.texture.bind(gl::TEXTURE0 + BLOOM_TEXTURE_UNIT);
+        }
+        program.set_uniform_i1("bloom", BLOOM_TEXTURE_UNIT as _);
+        program.set_uniform_f1("bloom_intensity", self.settings.borrow().intensity);
+    }
+}
@@ -0,0 +1,315 @@
+use std::cell::{Cell, Ref, RefCell};
+use std::rc::Rc;
+use hecs::World;
+use sdl2::event::Event;
+use crate::ecs::components::{ExtraUniform, Input, PostEffectControl, UniformValue};
+use crate::ecs::systems::post_processing::bloom::{Bloom, BLOOM_FRAGMENT_SHADER};
+use crate::ecs::systems::post_processing::tone_mapping::{AutoExposure, TONE_MAPPING_FRAGMENT_SHADER, ToneMappingOperator};
+use crate::ecs::systems::system::System;
+use crate::error::Error;
+use crate::frame_buffer::FrameBuffer;
+use crate::gl_function;
+use crate::ping_pong_frame_buffer::PingPongFrameBuffer;
+use crate::program::Program;
+use crate::quad::Quad;
+use crate::shader_loader::{ShaderLoader, ShaderType};
+use crate::texture::{Texture, TextureFormat};
+
+pub mod bloom;
+pub mod tone_mapping;
+
+static VERTEX_SHADER: &'static str = "15.1-postprocessing_vertex.glsl";
+static COPY_FRAGMENT_SHADER: &'static str = "26.1-quad_fragment.glsl";
+pub static EXPOSURE_FRAGMENT_SHADER: &'static str = "24.1-hdr_fragment.glsl";
+pub static GAMMA_CORRECTION_FRAGMENT_SHADER: &'static str = "post_gamma_correction_fragment.glsl";
+pub static GREYSCALE_FRAGMENT_SHADER: &'static str = "post_greyscale_fragment.glsl";
+pub static INVERSION_FRAGMENT_SHADER: &'static str = "15.1-postprocessing_fragment.glsl";
+pub static KERNEL_FRAGMENT_SHADER: &'static str = "post_kernel_fragment.glsl";
+pub const SHARPEN_KERNEL: [f32; 9] = [
+    -1f32, -1f32, -1f32,
+    -1f32, 9f32, -1f32,
+    -1f32, -1f32, -1f32,
+];
+pub const BLUR_KERNEL: [f32; 9] = [
+    1f32 / 16f32, 2f32 / 16f32, 1f32 / 16f32,
+    2f32 / 16f32, 4f32 / 16f32, 2f32 / 16f32,
+    1f32 / 16f32, 2f32 / 16f32, 1f32 / 16f32,
+];
+pub const EDGE_DETECTION_KERNEL: [f32; 9] = [
+    1f32, 1f32, 1f32,
+    1f32, -8f32, 1f32,
+    1f32, 1f32, 1f32,
+];
+const INPUT_TEXTURE_UNIT: u32 = 0;
+const SCENE_TEXTURE_UNIT: u32 = 1;
+
+pub trait EffectPass {
+    fn render(&self, input: &Texture, quad: &Quad, delta_time: f32) -> Result<(), Error>;
+    fn apply(&self, program: &Program);
+}
+
+pub struct PostEffect {
+    enabled: Cell<bool>,
+    name: String,
+    parameters: RefCell<Vec<ExtraUniform>>,
+    passes: Vec<Rc<dyn EffectPass>>,
+    program: Program,
+}
+
+impl PostEffect {
+    pub fn new(name: &str, program: Program) -> PostEffect {
+        PostEffect {
+            enabled: Cell::new(true),
+            name: name.to_string(),
+            parameters: RefCell::new(vec![]),
+            passes: vec![],
+            program,
+        }
+    }
+
+    pub fn with_pass(mut self, pass: Rc<dyn EffectPass>) -> PostEffect {
+        self.passes.push(pass);
+        self
+    }
+
+    pub fn with_parameter(self, name: &'static str, value: UniformValue) -> PostEffect {
+        self.set_parameter(name, value);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+    }
+
+    pub fn toggle(&self) {
+        self.enabled.set(!self.enabled.get());
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<UniformValue> {
+        self.parameters.borrow().iter()
+            .find(|p| p.name == name)
+            .map(|p| p.value.clone())
+    }
+
+    pub fn set_parameter(&self, name: &'static str, value: UniformValue) {
+        let mut parameters = self.parameters.borrow_mut();
+        match parameters.iter_mut().find(|p| p.name == name) {
+            Some(parameter) => parameter.value = value,
+            None => parameters.push(ExtraUniform { name, value }),
+        }
+    }
+
+    fn use_program(&self) {
+        self.program.use_program();
+        self.program.set_uniform_i1("texture1", INPUT_TEXTURE_UNIT as _);
+        if self.program.reflection().uniform("scene").is_some() {
+            self.program.set_uniform_i1("scene", SCENE_TEXTURE_UNIT as _);
+        }
+        for parameter in self.parameters.borrow().iter() {
+            parameter.value.set(&self.program, parameter.name);
+        }
+        for pass in self.passes.iter() {
+            pass.apply(&self.program);
+        }
+    }
+}
+
+pub struct PostProcessStack {
+    copy_program: Program,
+    effects: Vec<Rc<PostEffect>>,
+    frame_buffer: RefCell<FrameBuffer>,
+    ping_pong: RefCell<PingPongFrameBuffer>,
+    quad: Quad,
+    shader_loader: ShaderLoader,
+}
+
+impl PostProcessStack {
+    pub fn new(shader_loader: &ShaderLoader, width: u32, height: u32) -> Result<PostProcessStack, Error> {
+        let copy_program = shader_loader.program(vec![
+            (ShaderType::Vertex, VERTEX_SHADER),
+            (ShaderType::Fragment, COPY_FRAGMENT_SHADER),
+        ])?;
+        Ok(PostProcessStack {
+            copy_program,
+            effects: vec![],
+            frame_buffer: RefCell::new(FrameBuffer::new_with_format(width, height, TextureFormat::FloatingPoint)?),
+            ping_pong: RefCell::new(PingPongFrameBuffer::new_with_format(width as _, height as _, TextureFormat::FloatingPoint)),
+            quad: Quad::new(),
+            shader_loader: shader_loader.clone(),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.frame_buffer.borrow().width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.frame_buffer.borrow().height()
+    }
+
+    pub fn resize(&self, width: u32, height: u32) -> Result<(), Error> {
+        if self.width() == width && self.height() == height {
+            return Ok(());
+        }
+        self.frame_buffer.replace(FrameBuffer::new_with_format(width, height, TextureFormat::FloatingPoint)?);
+        self.ping_pong.replace(PingPongFrameBuffer::new_with_format(width as _, height as _, TextureFormat::FloatingPoint));
+        Ok(())
+    }
+
+    pub fn frame_buffer(&self) -> Ref<'_, FrameBuffer> {
+        self.frame_buffer.borrow()
+    }
+
+    pub fn load_effect(&self, name: &str, fragment_shader: &str) -> Result<PostEffect, Error> {
+        let program = self.shader_loader.program(vec![
+            (ShaderType::Vertex, VERTEX_SHADER),
+            (ShaderType::Fragment, fragment_shader),
+        ])?;
+        Ok(PostEffect::new(name, program))
+    }
+
+    pub fn push(&mut self, effect: PostEffect) -> Rc<PostEffect> {
+        let effect = Rc::new(effect);
+        self.effects.push(effect.clone());
+        effect
+    }
+
+    pub fn add_effect(&mut self, name: &str, fragment_shader: &str) -> Result<Rc<PostEffect>, Error> {
+        let effect = self.load_effect(name, fragment_shader)?;
+        Ok(self.push(effect))
+    }
+
+    pub fn add_kernel(&mut self, name: &str, kernel: [f32; 9]) -> Result<Rc<PostEffect>, Error> {
+        let effect = self.load_effect(name, KERNEL_FRAGMENT_SHADER)?
+            .with_parameter("kernel", UniformValue::Array(kernel.iter().map(|v| UniformValue::Float(*v)).collect()));
+        Ok(self.push(effect))
+    }
+
+    pub fn add_bloom(&mut self, name: &str, bloom: Rc<Bloom>) -> Result<Rc<PostEffect>, Error> {
+        let effect = self.load_effect(name, BLOOM_FRAGMENT_SHADER)?.with_pass(bloom);
+        Ok(self.push(effect))
+    }
+
+    pub fn add_tone_mapping(
+        &mut self, name: &str, operator: ToneMappingOperator, auto_exposure: Option<Rc<AutoExposure>>,
+    ) -> Result<Rc<PostEffect>, Error> {
+        let mut effect = self.load_effect(name, TONE_MAPPING_FRAGMENT_SHADER)?
+            .with_parameter("tone_mapping_operator", operator.uniform())
+            .with_parameter("exposure", UniformValue::Float(1f32))
+            .with_parameter("gamma", UniformValue::Float(2.2f32));
+        if let Some(auto_exposure) = auto_exposure {
+            effect = effect.with_pass(auto_exposure);
+        }
+        Ok(self.push(effect))
+    }
+
+    pub fn effect(&self, name: &str) -> Option<Rc<PostEffect>> {
+        self.effects.iter().find(|e| e.name() == name).cloned()
+    }
+
+    pub fn effects(&self) -> &[Rc<PostEffect>] {
+        &self.effects
+    }
+
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> bool {
+        match self.effect(name) {
+            Some(effect) => {
+                effect.set_enabled(enabled);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn shader_loader(&self) -> &ShaderLoader {
+        &self.shader_loader
+    }
+
+    fn run_effects(&self, delta_time: f32) -> Result<(), Error> {
+        let frame_buffer = self.frame_buffer.borrow();
+        let ping_pong = self.ping_pong.borrow();
+        let effects = self.effects.iter().filter(|e| e.is_enabled()).collect::<Vec<_>>();
+        if effects.is_empty() {
+            FrameBuffer::unbind();
+            frame_buffer.texture.bind(gl::TEXTURE0 + INPUT_TEXTURE_UNIT);
+            self.copy_program.use_program();
+            self.copy_program.set_uniform_i1("texture1", INPUT_TEXTURE_UNIT as _);
+            self.quad.draw();
+            return Ok(());
+        }
+        for (i, effect) in effects.iter().enumerate() {
+            let pong = i % 2 == 1;
+            let input = if i == 0 { &frame_buffer.texture } else { ping_pong.texture(pong) };
+            for pass in effect.passes.iter() {
+                pass.render(input, &self.quad, delta_time)?;
+            }
+            if i + 1 == effects.len() {
+                FrameBuffer::unbind();
+            } else {
+                ping_pong.bind(pong, INPUT_TEXTURE_UNIT);
+            }
+            gl_function!(Viewport(0, 0, self.width() as _, self.height() as _));
+            frame_buffer.texture.bind(gl::TEXTURE0 + SCENE_TEXTURE_UNIT);
+            input.bind(gl::TEXTURE0 + INPUT_TEXTURE_UNIT);
+            effect.use_program();
+            self.quad.draw();
+        }
+        Ok(())
+    }
+}
+
+impl System for PostProcessStack {
+    fn name(&self) -> &str {
+        "Post Process Stack"
+    }
+
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        self.frame_buffer.borrow().bind();
+        Ok(())
+    }
+
+    fn update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        for (_e, (input, control)) in world.query_mut::<(&Input, &PostEffectControl)>() {
+            for event in input.events.iter() {
+                match event {
+                    Event::KeyDown {
+                        keycode: Some(k),
+                        repeat: false,
+                        ..
+                    } if *k == control.keycode => {
+                        if let Some(effect) = self.effect(&control.effect) {
+                            effect.toggle();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn late_update(&self, _world: &mut World, delta_time: f32) -> Result<(), Error> {
+        gl_function!(Viewport(0, 0, self.width() as _, self.height() as _));
+        gl_function!(Disable(gl::DEPTH_TEST));
+        gl_function!(Disable(gl::BLEND));
+        let result = self.run_effects(delta_time);
+        gl_function!(Enable(gl::DEPTH_TEST));
+        result
+    }
+}
@@ -0,0 +1,173 @@
+use std::cell::{Cell, RefCell};
+use crate::ecs::components::UniformValue;
+use crate::ecs::systems::post_processing::EffectPass;
+use crate::error::Error;
+use crate::frame_buffer::FrameBuffer;
+use crate::gl_function;
+use crate::ping_pong_frame_buffer::PingPongFrameBuffer;
+use crate::program::Program;
+use crate::quad::Quad;
+use crate::shader_loader::{ShaderLoader, ShaderType};
+use crate::texture::{Texture, TextureFormat};
+
+static VERTEX_SHADER: &'static str = "15.1-postprocessing_vertex.glsl";
+static LUMINANCE_FRAGMENT_SHADER: &'static str = "post_luminance_fragment.glsl";
+static ADAPTATION_FRAGMENT_SHADER: &'static str = "post_adaptation_fragment.glsl";
+pub static TONE_MAPPING_FRAGMENT_SHADER: &'static str = "post_tone_mapping_fragment.glsl";
+const LUMINANCE_SIZE: u32 = 256;
+const ADAPTED_LUMINANCE_TEXTURE_UNIT: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMappingOperator {
+    Reinhard = 0,
+    Aces = 1,
+    Uncharted2 = 2,
+    AgX = 3,
+}
+
+impl ToneMappingOperator {
+    pub fn next(&self) -> ToneMappingOperator {
+        match self {
+            ToneMappingOperator::Reinhard => ToneMappingOperator::Aces,
+            ToneMappingOperator::Aces => ToneMappingOperator::Uncharted2,
+            ToneMappingOperator::Uncharted2 => ToneMappingOperator::AgX,
+            ToneMappingOperator::AgX => ToneMappingOperator::Reinhard,
+        }
+    }
+
+    pub fn uniform(&self) -> UniformValue {
+        UniformValue::Int(*self as i32)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AutoExposureSettings {
+    pub min_ev: f32,
+    pub max_ev: f32,
+    pub compensation: f32,
+    pub adaptation_speed: f32,
+}
+
+impl Default for AutoExposureSettings {
+    fn default() -> AutoExposureSettings {
+        AutoExposureSettings {
+            min_ev: -4.0,
+            max_ev: 12.0,
+            compensation: 0.0,
+            adaptation_speed: 1.5,
+        }
+    }
+}
+
+pub struct AutoExposure {
+    adaptation: PingPongFrameBuffer,
+    adaptation_program: Program,
+    enabled: Cell<bool>,
+    luminance: FrameBuffer,
+    luminance_program: Program,
+    pong: Cell<bool>,
+    reset: Cell<bool>,
+    settings: RefCell<AutoExposureSettings>,
+}
+
+impl AutoExposure {
+    pub fn new(shader_loader: &ShaderLoader, settings: AutoExposureSettings) -> Result<AutoExposure, Error> {
+        let luminance_program = shader_loader.program(vec![
+            (ShaderType::Vertex, VERTEX_SHADER),
+            (ShaderType::Fragment, LUMINANCE_FRAGMENT_SHADER),
+        ])?;
+        let adaptation_program = shader_loader.program(vec![
+            (ShaderType::Vertex, VERTEX_SHADER),
+            (ShaderType::Fragment, ADAPTATION_FRAGMENT_SHADER),
+        ])?;
+        let luminance = FrameBuffer::intermediate_with_format(LUMINANCE_SIZE, LUMINANCE_SIZE, TextureFormat::FloatingPoint)?;
+        luminance.texture.just_bind();
+        luminance.texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST_MIPMAP_NEAREST);
+        luminance.texture.generate_mipmap();
+        luminance.texture.unbind();
+        Ok(AutoExposure {
+            adaptation: PingPongFrameBuffer::new_with_format(1, 1, TextureFormat::FloatingPoint),
+            adaptation_program,
+            enabled: Cell::new(true),
+            luminance,
+            luminance_program,
+            pong: Cell::new(false),
+            reset: Cell::new(true),
+            settings: RefCell::new(settings),
+        })
+    }
+
+    pub fn settings(&self) -> AutoExposureSettings {
+        self.settings.borrow().clone()
+    }
+
+    pub fn set_settings(&self, settings: AutoExposureSettings) {
+        self.settings.replace(settings);
+    }
+
+    pub fn set_ev_range(&self, min_ev: f32, max_ev: f32) {
+        self.set_settings(AutoExposureSettings { min_ev, max_ev, ..self.settings() });
+    }
+
+    pub fn set_compensation(&self, compensation: f32) {
+        self.set_settings(AutoExposureSettings { compensation, ..self.settings() });
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        if enabled && !self.enabled.get() {
+            self.reset();
+        }
+        self.enabled.set(enabled);
+    }
+
+    pub fn reset(&self) {
+        self.reset.set(true);
+    }
+}
+
+impl EffectPass for AutoExposure {
+    fn render(&self, input: &Texture, quad: &Quad, delta_time: f32) -> Result<(), Error> {
+        if !self.enabled.get() {
+            return Ok(());
+        }
+        self.luminance.bind();
+        gl_function!(Viewport(0, 0, LUMINANCE_SIZE as _, LUMINANCE_SIZE as _));
+        input.bind(gl::TEXTURE0);
+        self.luminance_program.use_program();
+        self.luminance_program.set_uniform_i1("texture1", 0);
+        quad.draw();
+        self.luminance.texture.just_bind();
+        self.luminance.texture.generate_mipmap();
+
+        let pong = !self.pong.get();
+        self.adaptation.bind(pong, 1);
+        gl_function!(Viewport(0, 0, 1, 1));
+        self.luminance.texture.bind(gl::TEXTURE0);
+        let settings = self.settings.borrow();
+        self.adaptation_program.use_program();
+        self.adaptation_program.set_uniform_i1("luminance", 0);
+        self.adaptation_program.set_uniform_i1("previous_luminance", 1);
+        self.adaptation_program.set_uniform_f1("luminance_level", (LUMINANCE_SIZE as f32).log2());
+        self.adaptation_program.set_uniform_f1("delta_time", delta_time / 1_000_000.0);
+        self.adaptation_program.set_uniform_f1("adaptation_speed", settings.adaptation_speed);
+        self.adaptation_program.set_uniform("reset", &self.reset.get());
+        quad.draw();
+        self.pong.set(pong);
+        self.reset.set(false);
+        Ok(())
+    }
+
+    fn apply(&self, program: &Program) {
+        let settings = self.settings.borrow();
+        self.adaptation.texture(!self.pong.get()).bind(gl::TEXTURE0 + ADAPTED_LUMINANCE_TEXTURE_UNIT);
+        program.set_uniform("auto_exposure", &self.enabled.get());
+        program.set_uniform_i1("adapted_luminance", ADAPTED_LUMINANCE_TEXTURE_UNIT as _);
+        program.set_uniform_f1("min_ev", settings.min_ev);
+        program.set_uniform_f1("max_ev", settings.max_ev.max(settings.min_ev));
+        program.set_uniform_f1("exposure_compensation", settings.compensation);
+    }
+}
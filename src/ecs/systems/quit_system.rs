@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use hecs::World;
 use sdl2::event::Event;
+use crate::error::Error;
 use crate::ecs::components::{Input, QuitControl};
 use crate::ecs::systems::system::System;
 
@@ -14,11 +15,11 @@ impl System for QuitSystem {
         "Quit"
     }
 
-    fn start(&self, _world: &mut World) -> Result<(), String> {
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
         Ok(())
     }
 
-    fn early_update(&self, world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
         for (_e, (input, quit_control)) in world.query_mut::<(&Input, &QuitControl)>() {
             for event in input.events.iter() {
                 match event {
@@ -38,11 +39,11 @@ impl System for QuitSystem {
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 }
@@ -0,0 +1,286 @@
+use std::cell::Cell;
+use nalgebra::{Point3, Vector2, Vector3, Vector4};
+use crate::buffer::Buffer;
+use crate::camera::Camera;
+use crate::gl_function;
+use crate::program::Program;
+use crate::texture::{Texture, TextureType};
+
+const CLUSTERED_LIGHTING_VARIABLE: &'static str = "LEARNOPENGL_CLUSTERED_LIGHTING";
+const CLUSTER_GRID_TEXTURE_UNIT: u32 = 14;
+const CLUSTER_INDICES_TEXTURE_UNIT: u32 = 15;
+pub const DEFAULT_CLUSTER_DIMENSIONS: (usize, usize, usize) = (16, 9, 24);
+
+pub struct LightBounds {
+    pub position: Vector3<f32>,
+    pub radius: f32,
+}
+
+struct ClusterBounds {
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+}
+
+impl ClusterBounds {
+    fn intersects(&self, center: &Vector3<f32>, radius: f32) -> bool {
+        let mut distance = 0.0;
+        for axis in 0..3 {
+            let d = (self.min[axis] - center[axis]).max(center[axis] - self.max[axis]).max(0.0);
+            distance += d * d;
+        }
+        distance <= radius * radius
+    }
+}
+
+struct ClusterBuffer {
+    buffer: Buffer,
+    texture: Texture,
+}
+
+impl ClusterBuffer {
+    fn new(internal_format: gl::types::GLenum) -> ClusterBuffer {
+        let buffer = Buffer::new(gl::TEXTURE_BUFFER);
+        buffer.bind();
+        buffer.set_data(&[0u32; 4], gl::DYNAMIC_DRAW);
+        buffer.unbind();
+        let texture = Texture::new(TextureType::TextureBuffer);
+        texture.just_bind();
+        texture.attach_buffer(&buffer, internal_format);
+        texture.unbind();
+        ClusterBuffer { buffer, texture }
+    }
+
+    fn set(&self, data: &[u32]) {
+        self.buffer.bind();
+        self.buffer.set_data(data, gl::DYNAMIC_DRAW);
+        self.buffer.unbind();
+    }
+}
+
+fn slice_depth(dimensions: (usize, usize, usize), slice: usize, near: f32, far: f32) -> f32 {
+    near * (far / near).powf(slice as f32 / dimensions.2 as f32)
+}
+
+fn slice(dimensions: (usize, usize, usize), depth: f32, near: f32, far: f32) -> usize {
+    let slice = ((depth.max(near) / near).ln() / (far / near).ln() * dimensions.2 as f32) as usize;
+    slice.min(dimensions.2 - 1)
+}
+
+fn cluster_bounds(dimensions: (usize, usize, usize), camera: &Camera) -> Vec<ClusterBounds> {
+    let (width, height, depth) = dimensions;
+    let (near, far) = (camera.near_plane(), camera.far_plane());
+    let tan_y = (camera.fov().to_radians() / 2.0).tan();
+    let tan_x = tan_y * camera.aspect_ratio();
+    let mut bounds = Vec::with_capacity(width * height * depth);
+    for z in 0..depth {
+        let depths = [slice_depth(dimensions, z, near, far), slice_depth(dimensions, z + 1, near, far)];
+        for y in 0..height {
+            let ndc_y = [y as f32 / height as f32 * 2.0 - 1.0, (y + 1) as f32 / height as f32 * 2.0 - 1.0];
+            for x in 0..width {
+                let ndc_x = [x as f32 / width as f32 * 2.0 - 1.0, (x + 1) as f32 / width as f32 * 2.0 - 1.0];
+                let mut min = Vector3::new(f32::MAX, f32::MAX, depths[0]);
+                let mut max = Vector3::new(f32::MIN, f32::MIN, depths[1]);
+                for d in depths {
+                    for nx in ndc_x {
+                        min.x = min.x.min(nx * d * tan_x);
+                        max.x = max.x.max(nx * d * tan_x);
+                    }
+                    for ny in ndc_y {
+                        min.y = min.y.min(ny * d * tan_y);
+                        max.y = max.y.max(ny * d * tan_y);
+                    }
+                }
+                bounds.push(ClusterBounds { min, max });
+            }
+        }
+    }
+    bounds
+}
+
+fn assign(dimensions: (usize, usize, usize), camera: &Camera, bounds: &[ClusterBounds], lights: &[LightBounds]) -> Vec<Vec<u32>> {
+    let (width, height, _) = dimensions;
+    let (near, far) = (camera.near_plane(), camera.far_plane());
+    let view = camera.look_at_matrix();
+    let mut clusters = vec![vec![]; bounds.len()];
+    for (index, light) in lights.iter().enumerate() {
+        let position = view.transform_point(&Point3::from(light.position));
+        let center = Vector3::new(position.x, position.y, -position.z);
+        if center.z + light.radius < near || center.z - light.radius > far {
+            continue;
+        }
+        let first = slice(dimensions, center.z - light.radius, near, far);
+        let last = slice(dimensions, center.z + light.radius, near, far);
+        for z in first..=last {
+            for cluster in z * width * height..(z + 1) * width * height {
+                if bounds[cluster].intersects(&center, light.radius) {
+                    clusters[cluster].push(index as u32);
+                }
+            }
+        }
+    }
+    clusters
+}
+
+pub struct ClusteredLighting {
+    dimensions: (usize, usize, usize),
+    debug: Cell<bool>,
+    grid: ClusterBuffer,
+    indices: ClusterBuffer,
+}
+
+impl ClusteredLighting {
+    pub fn new(dimensions: (usize, usize, usize)) -> ClusteredLighting {
+        ClusteredLighting {
+            dimensions,
+            debug: Cell::new(false),
+            grid: ClusterBuffer::new(gl::RGBA32UI),
+            indices: ClusterBuffer::new(gl::R32UI),
+        }
+    }
+
+    pub fn from_env() -> Option<ClusteredLighting> {
+        let value = std::env::var(CLUSTERED_LIGHTING_VARIABLE).ok()?;
+        let clustered_lighting = ClusteredLighting::new(DEFAULT_CLUSTER_DIMENSIONS);
+        clustered_lighting.set_debug(value == "debug");
+        Some(clustered_lighting)
+    }
+
+    pub fn with_env_override(dimensions: (usize, usize, usize)) -> ClusteredLighting {
+        let clustered_lighting = ClusteredLighting::new(dimensions);
+        if let Ok(value) = std::env::var(CLUSTERED_LIGHTING_VARIABLE) {
+            clustered_lighting.set_debug(value == "debug");
+        }
+        clustered_lighting
+    }
+
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        self.dimensions
+    }
+
+    pub fn cluster_count(&self) -> usize {
+        self.dimensions.0 * self.dimensions.1 * self.dimensions.2
+    }
+
+    pub fn debug(&self) -> bool {
+        self.debug.get()
+    }
+
+    pub fn set_debug(&self, debug: bool) {
+        self.debug.set(debug);
+    }
+
+    pub fn update(&self, camera: &Camera, point_lights: &[LightBounds], spot_lights: &[LightBounds]) {
+        let bounds = cluster_bounds(self.dimensions, camera);
+        let point_clusters = assign(self.dimensions, camera, &bounds, point_lights);
+        let spot_clusters = assign(self.dimensions, camera, &bounds, spot_lights);
+        let mut grid = Vec::with_capacity(bounds.len() * 4);
+        let mut indices = vec![];
+        for (points, spots) in point_clusters.iter().zip(spot_clusters.iter()) {
+            grid.extend([indices.len() as u32, points.len() as u32, (indices.len() + points.len()) as u32, spots.len() as u32]);
+            indices.extend(points);
+            indices.extend(spots);
+        }
+        if indices.is_empty() {
+            indices.push(0);
+        }
+        self.grid.set(&grid);
+        self.indices.set(&indices);
+    }
+
+    pub fn set_program(&self, program: &Program, camera: &Camera) {
+        let mut viewport = [0; 4];
+        gl_function!(GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()));
+        let (width, height, depth) = self.dimensions;
+        program.use_program();
+        program.set_uniform_i1("cluster_grid", CLUSTER_GRID_TEXTURE_UNIT as _);
+        program.set_uniform_i1("cluster_light_indices", CLUSTER_INDICES_TEXTURE_UNIT as _);
+        program.set_uniform("cluster_dimensions", &Vector3::new(width as u32, height as u32, depth as u32));
+        program.set_uniform("cluster_depth_range", &Vector2::new(camera.near_plane(), camera.far_plane()));
+        program.set_uniform("cluster_viewport", &Vector4::from(viewport.map(|v| v as f32)));
+        program.set_uniform("cluster_debug", &self.debug.get());
+    }
+
+    pub fn bind(&self) {
+        self.grid.texture.bind(gl::TEXTURE0 + CLUSTER_GRID_TEXTURE_UNIT);
+        self.indices.texture.bind(gl::TEXTURE0 + CLUSTER_INDICES_TEXTURE_UNIT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+    use super::*;
+
+    const DIMENSIONS: (usize, usize, usize) = (4, 3, 8);
+
+    fn camera() -> Camera {
+        Camera::new(Vector3::zeros(), Vector3::new(0.0, 0.0, -1.0), Vector3::y_axis())
+    }
+
+    fn light(distance: f32, radius: f32) -> LightBounds {
+        LightBounds {
+            position: Vector3::new(0.0, 0.0, -distance),
+            radius,
+        }
+    }
+
+    fn slices_with_light(clusters: &[Vec<u32>], light: u32) -> Vec<usize> {
+        let (width, height, _) = DIMENSIONS;
+        let mut slices = clusters.iter().enumerate()
+            .filter(|(_, lights)| lights.contains(&light))
+            .map(|(cluster, _)| cluster / (width * height))
+            .collect::<Vec<_>>();
+        slices.dedup();
+        slices
+    }
+
+    #[test]
+    fn depth_slices_are_logarithmic() {
+        let camera = camera();
+        let (near, far) = (camera.near_plane(), camera.far_plane());
+        let (width, height, depth) = DIMENSIONS;
+        assert_eq!(slice_depth(DIMENSIONS, 0, near, far), near);
+        assert!((slice_depth(DIMENSIONS, depth, near, far) - far).abs() < 1e-3);
+        for z in 1..depth {
+            let ratio = slice_depth(DIMENSIONS, z + 1, near, far) / slice_depth(DIMENSIONS, z, near, far);
+            assert!((ratio - (far / near).powf(1.0 / depth as f32)).abs() < 1e-3);
+        }
+        let bounds = cluster_bounds(DIMENSIONS, &camera);
+        assert_eq!(bounds.len(), width * height * depth);
+        for z in 0..depth {
+            let (start, end) = (slice_depth(DIMENSIONS, z, near, far), slice_depth(DIMENSIONS, z + 1, near, far));
+            for cluster in &bounds[z * width * height..(z + 1) * width * height] {
+                assert_eq!((cluster.min.z, cluster.max.z), (start, end));
+            }
+            assert_eq!(slice(DIMENSIONS, start * 1.001, near, far), z);
+            assert_eq!(slice(DIMENSIONS, end * 0.999, near, far), z);
+        }
+        assert_eq!(slice(DIMENSIONS, 0.0, near, far), 0);
+        assert_eq!(slice(DIMENSIONS, far * 2.0, near, far), depth - 1);
+    }
+
+    #[test]
+    fn light_straddling_a_slice_edge_is_in_both_slices() {
+        let camera = camera();
+        let edge = slice_depth(DIMENSIONS, 4, camera.near_plane(), camera.far_plane());
+        let bounds = cluster_bounds(DIMENSIONS, &camera);
+        let clusters = assign(DIMENSIONS, &camera, &bounds, &[light(edge, edge * 0.01)]);
+        assert_eq!(slices_with_light(&clusters, 0), vec![3, 4]);
+    }
+
+    #[test]
+    fn light_with_infinite_radius_is_in_every_cluster() {
+        let camera = camera();
+        let bounds = cluster_bounds(DIMENSIONS, &camera);
+        let clusters = assign(DIMENSIONS, &camera, &bounds, &[light(10.0, f32::INFINITY)]);
+        assert!(clusters.iter().all(|lights| lights == &vec![0]));
+    }
+
+    #[test]
+    fn light_behind_the_camera_is_in_no_cluster() {
+        let camera = camera();
+        let bounds = cluster_bounds(DIMENSIONS, &camera);
+        let clusters = assign(DIMENSIONS, &camera, &bounds, &[light(-10.0, 1.0), light(-5.0, 4.9)]);
+        assert!(clusters.iter().all(|lights| lights.is_empty()));
+    }
+}
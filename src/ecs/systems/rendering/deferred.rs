@@ -0,0 +1,297 @@
+use std::cell::{Ref, RefCell};
+use hecs::World;
+use nalgebra::Matrix4;
+use crate::ecs::components::{CastsShadows, Mesh, Model, Shader, SkipRendering, Transform, Transparent};
+use crate::ecs::systems::rendering::permutations::{ShaderFeatures, ShaderPermutations};
+use crate::ecs::systems::rendering::{HEIGHT_SCALE, MATRICES_BINDING_POINT, MESH_GEOMETRY_SHADER, MESH_VERTEX_SHADER, RenderingSystem, setup_mesh_program};
+use crate::ecs::systems::system::System;
+use crate::error::Error;
+use crate::gl_function;
+use crate::light::{PointLight, SpotLight};
+use crate::multiple_render_target::MultipleRenderTarget;
+use crate::program::Program;
+use crate::quad::Quad;
+use crate::shader_loader::ShaderType;
+use crate::sphere::sphere_mesh;
+use crate::texture::TextureFormat;
+
+static GEOMETRY_FRAGMENT_SHADER: &'static str = "deferred_geometry_fragment.glsl";
+static QUAD_VERTEX_SHADER: &'static str = "15.1-postprocessing_vertex.glsl";
+static DIRECTIONAL_FRAGMENT_SHADER: &'static str = "deferred_directional_fragment.glsl";
+static LIGHT_VOLUME_VERTEX_SHADER: &'static str = "deferred_light_volume_vertex.glsl";
+static LIGHT_VOLUME_FRAGMENT_SHADER: &'static str = "deferred_light_volume_fragment.glsl";
+static G_BUFFER_VIEW_FRAGMENT_SHADER: &'static str = "deferred_g_buffer_view_fragment.glsl";
+const G_BUFFER_TEXTURE_UNIT: u32 = 9;
+const G_BUFFER_TARGETS: [(&'static str, TextureFormat); 3] = [
+    ("gPosition", TextureFormat::HighPrecisionFloatingPoint),
+    ("gNormal", TextureFormat::FloatingPoint),
+    ("gAlbedoSpec", TextureFormat::UnsignedByteWithAlpha),
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GBufferView {
+    Shaded,
+    Position,
+    Normal,
+    Albedo,
+    Specular,
+}
+
+impl Default for GBufferView {
+    fn default() -> GBufferView {
+        GBufferView::Shaded
+    }
+}
+
+impl GBufferView {
+    const VIEWS: [GBufferView; 5] = [
+        GBufferView::Shaded, GBufferView::Position, GBufferView::Normal, GBufferView::Albedo, GBufferView::Specular,
+    ];
+
+    pub fn next(self) -> GBufferView {
+        GBufferView::VIEWS[(self as usize + 1) % GBufferView::VIEWS.len()]
+    }
+
+    pub fn previous(self) -> GBufferView {
+        GBufferView::VIEWS[(self as usize + GBufferView::VIEWS.len() - 1) % GBufferView::VIEWS.len()]
+    }
+}
+
+pub(crate) fn geometry_stages(features: ShaderFeatures) -> Vec<(ShaderType, &'static str)> {
+    let mut stages = vec![(ShaderType::Vertex, MESH_VERTEX_SHADER)];
+    if features.contains(ShaderFeatures::HAS_NORMAL_MAP) && !features.contains(ShaderFeatures::HAS_TANGENTS) {
+        stages.push((ShaderType::Geometry, MESH_GEOMETRY_SHADER));
+    }
+    stages.push((ShaderType::Fragment, GEOMETRY_FRAGMENT_SHADER));
+    stages
+}
+
+pub(crate) fn setup_geometry_program(features: ShaderFeatures, program: &Program) {
+    program.bind_uniform_block("Matrices", MATRICES_BINDING_POINT);
+    if features.contains(ShaderFeatures::HAS_NORMAL_MAP | ShaderFeatures::HAS_HEIGHT_MAP) {
+        program.use_program();
+        program.set_uniform_f1("height_scale", HEIGHT_SCALE);
+    }
+}
+
+fn g_buffer(width: u32, height: u32) -> Result<MultipleRenderTarget, Error> {
+    let formats = G_BUFFER_TARGETS.iter().map(|(_, format)| *format).collect::<Vec<_>>();
+    let g_buffer = MultipleRenderTarget::new_with_formats(width, height, &formats)?;
+    g_buffer.bind();
+    g_buffer.set_draw_buffers();
+    MultipleRenderTarget::unbind();
+    Ok(g_buffer)
+}
+
+pub struct DeferredRenderingSystem {
+    directional_program: Program,
+    g_buffer: RefCell<MultipleRenderTarget>,
+    g_buffer_view_program: Program,
+    geometry_programs: ShaderPermutations,
+    light_volume: (Mesh, Shader),
+    light_volume_program: Program,
+    quad: Quad,
+    rendering: RenderingSystem,
+}
+
+impl DeferredRenderingSystem {
+    pub fn new(mut rendering: RenderingSystem) -> Result<DeferredRenderingSystem, Error> {
+        let geometry_programs = ShaderPermutations::new(&rendering.shader_loader, geometry_stages, setup_geometry_program);
+        let directional_program = rendering.shader_loader.program(vec![
+            (ShaderType::Vertex, QUAD_VERTEX_SHADER),
+            (ShaderType::Fragment, DIRECTIONAL_FRAGMENT_SHADER),
+        ])?;
+        let light_volume_program = rendering.shader_loader.program(vec![
+            (ShaderType::Vertex, LIGHT_VOLUME_VERTEX_SHADER),
+            (ShaderType::Fragment, LIGHT_VOLUME_FRAGMENT_SHADER),
+        ])?;
+        let g_buffer_view_program = rendering.shader_loader.program(vec![
+            (ShaderType::Vertex, QUAD_VERTEX_SHADER),
+            (ShaderType::Fragment, G_BUFFER_VIEW_FRAGMENT_SHADER),
+        ])?;
+        g_buffer_view_program.setup(|program| {
+            program.use_program();
+            program.set_uniform_i1("g_buffer_texture", G_BUFFER_TEXTURE_UNIT as _);
+        });
+        for program in [&directional_program, &light_volume_program] {
+            program.setup(|program| {
+                setup_mesh_program(ShaderFeatures::empty(), program);
+                program.use_program();
+                for (i, (name, _)) in G_BUFFER_TARGETS.iter().enumerate() {
+                    program.set_uniform_i1(name, (G_BUFFER_TEXTURE_UNIT + i as u32) as _);
+                }
+            });
+        }
+        let mesh = sphere_mesh(vec![]);
+        let shader = rendering.shader_for_mesh(&mesh)?;
+        rendering.setup_gl_objects(&shader, &mesh)?;
+        Ok(DeferredRenderingSystem {
+            directional_program,
+            g_buffer: RefCell::new(g_buffer(1, 1)?),
+            g_buffer_view_program,
+            geometry_programs,
+            light_volume: (mesh, shader),
+            light_volume_program,
+            quad: Quad::new(),
+            rendering,
+        })
+    }
+
+    pub fn rendering(&self) -> &RenderingSystem {
+        &self.rendering
+    }
+
+    pub fn g_buffer(&self) -> Ref<'_, MultipleRenderTarget> {
+        self.g_buffer.borrow()
+    }
+
+    fn resize(&self, width: u32, height: u32) -> Result<(), Error> {
+        let current = self.g_buffer.borrow();
+        if current.width() == width && current.height() == height {
+            return Ok(());
+        }
+        drop(current);
+        self.g_buffer.replace(g_buffer(width, height)?);
+        Ok(())
+    }
+
+    fn setup_geometry_programs(&self, world: &World) -> Result<(), Error> {
+        let mut features = vec![];
+        for (_e, mesh) in world.query::<&Mesh>().iter() {
+            features.push(ShaderFeatures::for_mesh(mesh));
+        }
+        for (_e, model) in world.query::<&Model>().iter() {
+            features.extend(model.0.iter().map(|(mesh, _)| ShaderFeatures::for_mesh(mesh)));
+        }
+        for features in features {
+            self.geometry_programs.program(features)?;
+        }
+        let view_position = (*self.rendering.main_camera).borrow().position();
+        for program in self.geometry_programs.programs() {
+            program.use_program();
+            program.set_uniform_v3("viewPos", view_position);
+        }
+        Ok(())
+    }
+
+    fn geometry_pass(&self, world: &World, viewport: &[i32; 4]) -> Result<(), Error> {
+        let mut previous_frame_buffer = 0;
+        gl_function!(GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_frame_buffer));
+        self.g_buffer.borrow().bind();
+        gl_function!(Viewport(0, 0, viewport[2], viewport[3]));
+        gl_function!(ClearColor(0.0, 0.0, 0.0, 0.0));
+        gl_function!(Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT));
+        gl_function!(Disable(gl::BLEND));
+        gl_function!(Disable(gl::STENCIL_TEST));
+        self.rendering.render_objects(
+            &self.geometry_programs,
+            world.query::<(&Mesh, &Shader, &Transform)>().without::<Transparent>().without::<SkipRendering>().iter(),
+            world.query::<(&Model, &Transform)>().without::<Transparent>().without::<SkipRendering>().iter(),
+            world,
+        )?;
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, previous_frame_buffer as _));
+        gl_function!(Viewport(viewport[0], viewport[1], viewport[2], viewport[3]));
+        let clear_color = self.rendering.clear_color;
+        gl_function!(ClearColor(clear_color.x, clear_color.y, clear_color.z, 1.0));
+        gl_function!(Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT));
+        Ok(())
+    }
+
+    fn draw_light_volumes<T: Send + Sync + 'static, F: Fn(&T) -> Matrix4<f32>>(
+        &self, world: &World, spot_light: bool, volume_model: F,
+    ) {
+        self.light_volume_program.set_uniform("spot_light", &spot_light);
+        let (mesh, shader) = &self.light_volume;
+        for (i, (_e, (light, _))) in world.query::<(&T, Option<&CastsShadows>)>().iter()
+            .take(self.rendering.max_lights)
+            .enumerate() {
+            self.light_volume_program.set_uniform_i1("light_index", i as _);
+            self.light_volume_program.set_uniform_matrix4("model", &volume_model(light));
+            RenderingSystem::draw_mesh(mesh, shader);
+        }
+    }
+
+    fn lighting_pass(&self, world: &World, viewport: &[i32; 4]) {
+        for (i, texture) in self.g_buffer.borrow().textures.iter().enumerate() {
+            texture.bind(gl::TEXTURE0 + G_BUFFER_TEXTURE_UNIT + i as u32);
+        }
+        gl_function!(DepthFunc(gl::ALWAYS));
+        self.directional_program.use_program();
+        self.quad.draw();
+        gl_function!(DepthFunc(gl::LESS));
+
+        gl_function!(Disable(gl::DEPTH_TEST));
+        gl_function!(DepthMask(gl::FALSE));
+        gl_function!(Enable(gl::BLEND));
+        gl_function!(BlendFunc(gl::ONE, gl::ONE));
+        gl_function!(Enable(gl::CULL_FACE));
+        gl_function!(CullFace(gl::FRONT));
+        self.light_volume_program.use_program();
+        self.light_volume_program.set_uniform_v4(
+            "deferred_viewport", viewport[0] as f32, viewport[1] as f32, viewport[2] as f32, viewport[3] as f32,
+        );
+        self.draw_light_volumes(world, false, PointLight::volume_model);
+        self.draw_light_volumes(world, true, SpotLight::volume_model);
+        gl_function!(CullFace(gl::BACK));
+        gl_function!(Disable(gl::CULL_FACE));
+        gl_function!(Disable(gl::BLEND));
+        gl_function!(DepthMask(gl::TRUE));
+        gl_function!(Enable(gl::DEPTH_TEST));
+    }
+
+    fn draw_g_buffer_view(&self) {
+        let (target, alpha_channel) = match self.rendering.g_buffer_view.get() {
+            GBufferView::Shaded => return,
+            GBufferView::Position => (0, false),
+            GBufferView::Normal => (1, false),
+            GBufferView::Albedo => (2, false),
+            GBufferView::Specular => (2, true),
+        };
+        self.g_buffer().textures[target].bind(gl::TEXTURE0 + G_BUFFER_TEXTURE_UNIT);
+        gl_function!(Disable(gl::DEPTH_TEST));
+        self.g_buffer_view_program.use_program();
+        self.g_buffer_view_program.set_uniform("alpha_channel", &alpha_channel);
+        self.quad.draw();
+        gl_function!(Enable(gl::DEPTH_TEST));
+    }
+}
+
+impl System for DeferredRenderingSystem {
+    fn name(&self) -> &str {
+        "Deferred Rendering System"
+    }
+
+    fn start(&self, world: &mut World) -> Result<(), Error> {
+        self.rendering.start(world)
+    }
+
+    fn early_update(&self, world: &mut World, delta_time: f32) -> Result<(), Error> {
+        self.rendering.early_update(world, delta_time)
+    }
+
+    fn update(&self, world: &mut World, delta_time: f32) -> Result<(), Error> {
+        self.rendering.update(world, delta_time)
+    }
+
+    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        let mut viewport = [0; 4];
+        gl_function!(GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()));
+        self.resize(viewport[2].max(1) as u32, viewport[3].max(1) as u32)?;
+        self.rendering.compile_mesh_programs(world)?;
+        let light_counts = self.rendering.setup_program_globals(world)?;
+        self.rendering.set_rendering_program(&self.directional_program, light_counts);
+        self.rendering.set_rendering_program(&self.light_volume_program, light_counts);
+        self.setup_geometry_programs(world)?;
+        self.geometry_pass(world, &viewport)?;
+        if let Some(ssao) = self.rendering.ssao.as_ref() {
+            let g_buffer = self.g_buffer.borrow();
+            ssao.render(&g_buffer.textures[0], &g_buffer.textures[1])?;
+            ssao.bind();
+        }
+        self.lighting_pass(world, &viewport);
+        self.rendering.instanced_rendering.render_world(world, &self.rendering.mesh_programs)?;
+        self.rendering.render_forward_overlays(world)?;
+        self.draw_g_buffer_view();
+        Ok(())
+    }
+}
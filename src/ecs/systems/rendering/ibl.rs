@@ -0,0 +1,241 @@
+use std::cell::Cell;
+use image::io::Reader;
+use nalgebra::{Matrix4, Perspective3, Point3, Vector3};
+use crate::buffer::Buffer;
+use crate::ecs::components::SKYBOX_VERTICES;
+use crate::error::Error;
+use crate::frame_buffer::FrameBuffer;
+use crate::gl_function;
+use crate::program::Program;
+use crate::quad::Quad;
+use crate::shader_loader::{ShaderLoader, ShaderType};
+use crate::texture::{Texture, TextureFormat, TextureType};
+use crate::vertex_array::VertexArray;
+
+static CUBEMAP_VERTEX_SHADER: &'static str = "ibl_cubemap_vertex.glsl";
+static EQUIRECTANGULAR_FRAGMENT_SHADER: &'static str = "ibl_equirectangular_fragment.glsl";
+static IRRADIANCE_FRAGMENT_SHADER: &'static str = "ibl_irradiance_fragment.glsl";
+static PREFILTER_FRAGMENT_SHADER: &'static str = "ibl_prefilter_fragment.glsl";
+static QUAD_VERTEX_SHADER: &'static str = "15.1-postprocessing_vertex.glsl";
+static BRDF_FRAGMENT_SHADER: &'static str = "ibl_brdf_fragment.glsl";
+const IRRADIANCE_MAP_TEXTURE_UNIT: u32 = 5;
+const PREFILTER_MAP_TEXTURE_UNIT: u32 = 6;
+const BRDF_LUT_TEXTURE_UNIT: u32 = 7;
+
+#[derive(Clone, Debug)]
+pub struct ImageBasedLightingSettings {
+    pub environment_size: u32,
+    pub irradiance_size: u32,
+    pub prefilter_size: u32,
+    pub prefilter_levels: usize,
+    pub brdf_lut_size: u32,
+}
+
+impl Default for ImageBasedLightingSettings {
+    fn default() -> ImageBasedLightingSettings {
+        ImageBasedLightingSettings {
+            environment_size: 512,
+            irradiance_size: 32,
+            prefilter_size: 128,
+            prefilter_levels: 5,
+            brdf_lut_size: 512,
+        }
+    }
+}
+
+fn capture_views() -> Vec<Matrix4<f32>> {
+    vec![
+        (Vector3::new(1f32, 0f32, 0f32), Vector3::new(0f32, -1f32, 0f32)),
+        (Vector3::new(-1f32, 0f32, 0f32), Vector3::new(0f32, -1f32, 0f32)),
+        (Vector3::new(0f32, 1f32, 0f32), Vector3::new(0f32, 0f32, 1f32)),
+        (Vector3::new(0f32, -1f32, 0f32), Vector3::new(0f32, 0f32, -1f32)),
+        (Vector3::new(0f32, 0f32, 1f32), Vector3::new(0f32, -1f32, 0f32)),
+        (Vector3::new(0f32, 0f32, -1f32), Vector3::new(0f32, -1f32, 0f32)),
+    ].into_iter()
+        .map(|(direction, up)| Matrix4::look_at_rh(&Point3::origin(), &Point3::from(direction), &up))
+        .collect()
+}
+
+fn mip_levels(size: u32) -> usize {
+    (size.max(1) as f32).log2() as usize + 1
+}
+
+fn load_equirectangular(path: &str) -> Result<Texture, Error> {
+    let image = Reader::open(path).map_err(|e| Error::io(path, e))?
+        .decode().map_err(|e| Error::image_decode(path, e))?
+        .flipv()
+        .into_rgba32f();
+    let texture = Texture::new(TextureType::Texture2D);
+    texture.just_bind();
+    texture.set_image_2d_with_format(image.width(), image.height(), image.as_raw(), TextureFormat::FloatingPoint);
+    texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+    texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+    texture.set_parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+    texture.set_parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+    texture.unbind();
+    Ok(texture)
+}
+
+struct Cube {
+    vertex_array: VertexArray,
+    _vertex_buffer: Buffer,
+}
+
+impl Cube {
+    fn new() -> Cube {
+        let vertex_array = VertexArray::new();
+        let vertex_buffer = Buffer::new(gl::ARRAY_BUFFER);
+        vertex_array.bind();
+        vertex_buffer.bind();
+        vertex_buffer.set_data(&SKYBOX_VERTICES, gl::STATIC_DRAW);
+        VertexArray::set_vertex_attrib::<f32>(gl::FLOAT, 0, 3, false);
+        VertexArray::unbind();
+        Cube { vertex_array, _vertex_buffer: vertex_buffer }
+    }
+
+    fn draw_faces(&self, program: &Program, target: &FrameBuffer, level: usize) {
+        let size = (target.width() >> level).max(1);
+        let projection = Perspective3::new(1.0, 90f32.to_radians(), 0.1, 10.0).to_homogeneous();
+        program.use_program();
+        program.set_uniform_matrix4("projection", &projection);
+        target.bind();
+        gl_function!(Viewport(0, 0, size as _, size as _));
+        self.vertex_array.bind();
+        for (face, view) in capture_views().iter().enumerate() {
+            target.attach_cubemap_face(face as u32, level);
+            program.set_uniform_matrix4("view", view);
+            gl_function!(Clear(gl::COLOR_BUFFER_BIT));
+            gl_function!(DrawArrays(gl::TRIANGLES, 0, 36));
+        }
+        VertexArray::unbind();
+    }
+}
+
+pub struct ImageBasedLighting {
+    brdf_lut: FrameBuffer,
+    enabled: Cell<bool>,
+    environment: FrameBuffer,
+    irradiance: FrameBuffer,
+    prefilter: FrameBuffer,
+    prefilter_levels: usize,
+}
+
+impl ImageBasedLighting {
+    pub fn from_hdr(
+        shader_loader: &ShaderLoader, path: &str, settings: ImageBasedLightingSettings,
+    ) -> Result<ImageBasedLighting, Error> {
+        let cubemap_program = |fragment| shader_loader.program(vec![
+            (ShaderType::Vertex, CUBEMAP_VERTEX_SHADER),
+            (ShaderType::Fragment, fragment),
+        ]);
+        let equirectangular_program = cubemap_program(EQUIRECTANGULAR_FRAGMENT_SHADER)?;
+        let irradiance_program = cubemap_program(IRRADIANCE_FRAGMENT_SHADER)?;
+        let prefilter_program = cubemap_program(PREFILTER_FRAGMENT_SHADER)?;
+        let brdf_program = shader_loader.program(vec![
+            (ShaderType::Vertex, QUAD_VERTEX_SHADER),
+            (ShaderType::Fragment, BRDF_FRAGMENT_SHADER),
+        ])?;
+        let equirectangular = load_equirectangular(path)?;
+
+        let mut viewport = [0; 4];
+        gl_function!(GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()));
+        let mut previous_frame_buffer = 0;
+        gl_function!(GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_frame_buffer));
+        gl_function!(Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS));
+        gl_function!(Disable(gl::DEPTH_TEST));
+        gl_function!(Disable(gl::BLEND));
+        gl_function!(Disable(gl::CULL_FACE));
+        let cube = Cube::new();
+
+        let environment = FrameBuffer::cubemap(settings.environment_size, mip_levels(settings.environment_size))?;
+        equirectangular.bind(gl::TEXTURE0);
+        equirectangular_program.use_program();
+        equirectangular_program.set_uniform_i1("equirectangular_map", 0);
+        cube.draw_faces(&equirectangular_program, &environment, 0);
+        environment.texture.just_bind();
+        environment.texture.generate_mipmap();
+
+        let irradiance = FrameBuffer::cubemap(settings.irradiance_size, 1)?;
+        environment.texture.bind(gl::TEXTURE0);
+        irradiance_program.use_program();
+        irradiance_program.set_uniform_i1("environment_map", 0);
+        cube.draw_faces(&irradiance_program, &irradiance, 0);
+
+        let levels = settings.prefilter_levels.clamp(1, mip_levels(settings.prefilter_size));
+        let prefilter = FrameBuffer::cubemap(settings.prefilter_size, levels)?;
+        environment.texture.bind(gl::TEXTURE0);
+        prefilter_program.use_program();
+        prefilter_program.set_uniform_i1("environment_map", 0);
+        prefilter_program.set_uniform_f1("environment_resolution", settings.environment_size as f32);
+        for level in 0..levels {
+            let roughness = if levels > 1 { level as f32 / (levels - 1) as f32 } else { 0.0 };
+            prefilter_program.use_program();
+            prefilter_program.set_uniform_f1("roughness", roughness);
+            cube.draw_faces(&prefilter_program, &prefilter, level);
+        }
+
+        let brdf_lut = FrameBuffer::intermediate_with_format(
+            settings.brdf_lut_size, settings.brdf_lut_size, TextureFormat::FloatingPoint,
+        )?;
+        brdf_lut.texture.just_bind();
+        brdf_lut.texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+        brdf_lut.texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+        brdf_lut.texture.set_parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        brdf_lut.texture.set_parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        brdf_lut.texture.unbind();
+        brdf_lut.bind();
+        gl_function!(Viewport(0, 0, settings.brdf_lut_size as _, settings.brdf_lut_size as _));
+        gl_function!(Clear(gl::COLOR_BUFFER_BIT));
+        brdf_program.use_program();
+        Quad::new().draw();
+
+        gl_function!(Enable(gl::DEPTH_TEST));
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, previous_frame_buffer as _));
+        gl_function!(Viewport(viewport[0], viewport[1], viewport[2], viewport[3]));
+        Ok(ImageBasedLighting {
+            brdf_lut,
+            enabled: Cell::new(true),
+            environment,
+            irradiance,
+            prefilter,
+            prefilter_levels: levels,
+        })
+    }
+
+    pub fn environment(&self) -> &Texture {
+        &self.environment.texture
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+    }
+
+    pub fn bind(&self) {
+        self.irradiance.texture.bind(gl::TEXTURE0 + IRRADIANCE_MAP_TEXTURE_UNIT);
+        self.prefilter.texture.bind(gl::TEXTURE0 + PREFILTER_MAP_TEXTURE_UNIT);
+        self.brdf_lut.texture.bind(gl::TEXTURE0 + BRDF_LUT_TEXTURE_UNIT);
+    }
+
+    pub fn set_program(&self, program: &Program) {
+        set_texture_units(program, self.enabled.get(), (self.prefilter_levels - 1) as f32);
+    }
+
+    pub fn unset_program(program: &Program) {
+        set_texture_units(program, false, 0.0);
+    }
+}
+
+fn set_texture_units(program: &Program, enabled: bool, prefilter_max_lod: f32) {
+    if program.reflection().uniform("irradiance_map").is_none() {
+        return;
+    }
+    program.set_uniform("image_based_lighting", &enabled);
+    program.set_uniform_i1("irradiance_map", IRRADIANCE_MAP_TEXTURE_UNIT as _);
+    program.set_uniform_i1("prefilter_map", PREFILTER_MAP_TEXTURE_UNIT as _);
+    program.set_uniform_i1("brdf_lut", BRDF_LUT_TEXTURE_UNIT as _);
+    program.set_uniform_f1("prefilter_max_lod", prefilter_max_lod);
+}
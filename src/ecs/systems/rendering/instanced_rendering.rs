@@ -1,32 +1,22 @@
 use std::sync::Arc;
 use hecs::World;
 use nalgebra::{Matrix4, Vector4};
+use crate::error::Error;
 use crate::buffer::Buffer;
 use crate::ecs::components::{Border, get_flattened_matrices, InstancedMesh, InstancedModel, InstancedShader, Mesh, Shader, SkipRendering, Transparent};
+use crate::ecs::systems::rendering::permutations::{ShaderFeatures, ShaderPermutations};
 use crate::program::Program;
-use crate::shader_loader::{ShaderLoader, ShaderType};
 use crate::vertex_array::VertexArray;
 
-const INSTANCED_ARRAYS_VERTEX: &'static str = "19.1-instanced_arrays.glsl";
-const INSTANCED_ARRAYS_FRAGMENT: &'static str = "12.1-modelloading.glsl";
-
-pub struct InstancedRendering {
-    pub(crate) program: Program,
-}
+#[derive(Default)]
+pub struct InstancedRendering;
 
 impl InstancedRendering {
-    pub fn new(shader_loader: &ShaderLoader) -> Result<InstancedRendering, String> {
-        let program = Program::new(vec![
-            shader_loader.load(ShaderType::Vertex, INSTANCED_ARRAYS_VERTEX)?,
-            shader_loader.load(ShaderType::Fragment, INSTANCED_ARRAYS_FRAGMENT)?,
-        ])?;
-        program.bind_uniform_block("Matrices", 0);
-        Ok(InstancedRendering {
-            program
-        })
+    pub fn new() -> InstancedRendering {
+        InstancedRendering
     }
 
-    pub fn shader_for_mesh(&mut self, shader: &Shader) -> Result<InstancedShader, String> {
+    pub fn shader_for_mesh(&mut self, shader: &Shader) -> Result<InstancedShader, Error> {
         let offset_buffer = Arc::new(Buffer::new(gl::ARRAY_BUFFER));
         Ok(InstancedShader {
             offset_buffer,
@@ -37,7 +27,7 @@ impl InstancedRendering {
         })
     }
 
-    pub fn setup_world(&self, world: &mut World) -> Result<(), String> {
+    pub fn setup_world(&self, world: &mut World) -> Result<(), Error> {
         for (_e, (shader, mesh)) in world.query_mut::<(&InstancedShader, &InstancedMesh)>() {
             self.setup(shader, &mesh.mesh, &mesh.models)?;
         }
@@ -49,7 +39,7 @@ impl InstancedRendering {
         Ok(())
     }
 
-    fn setup(&self, shader: &InstancedShader, mesh: &Mesh, models: &[Matrix4<f32>]) -> Result<(), String> {
+    fn setup(&self, shader: &InstancedShader, mesh: &Mesh, models: &[Matrix4<f32>]) -> Result<(), Error> {
         shader.vertex_array.bind();
         shader.vertex_buffer.bind();
         shader.vertex_buffer.set_data(&mesh.flattened_data(), gl::STATIC_DRAW);
@@ -91,6 +81,22 @@ impl InstancedRendering {
             VertexArray::set_vertex_attrib_with_padding::<f32>(
                 gl::FLOAT, attribute, total_size, 2, offset, false
             );
+            offset += 2;
+            attribute += 1;
+        }
+
+        if mesh.tangents.is_some() {
+            VertexArray::set_vertex_attrib_with_padding::<f32>(
+                gl::FLOAT, attribute, total_size, 3, offset, false
+            );
+            offset += 3;
+            attribute += 1;
+        }
+
+        if mesh.bitangents.is_some() {
+            VertexArray::set_vertex_attrib_with_padding::<f32>(
+                gl::FLOAT, attribute, total_size, 3, offset, false
+            );
             attribute += 1;
         }
         shader.vertex_buffer.unbind();
@@ -108,22 +114,25 @@ impl InstancedRendering {
         Ok(())
     }
 
-    pub fn render_world(&self, world: &mut World) {
-        self.program.use_program();
+    pub fn render_world(&self, world: &mut World, programs: &ShaderPermutations) -> Result<(), Error> {
         for (_e, (mesh, shader)) in world.query::<(&InstancedMesh, &InstancedShader)>().without::<Border>().without::<Transparent>().without::<SkipRendering>().iter() {
             gl_function!(StencilMask(0x00));
-            self.render(&mesh.mesh, shader, mesh.models.len());
+            let program = programs.program(ShaderFeatures::for_instanced_mesh(&mesh.mesh))?;
+            self.render(&program, &mesh.mesh, shader, mesh.models.len());
         }
         for (_e, model) in world.query::<&InstancedModel>().without::<Border>().without::<Transparent>().without::<SkipRendering>().iter() {
             gl_function!(StencilMask(0x00));
             for (mesh, shader) in model.model.iter() {
-                self.render(mesh, shader, model.models.len());
+                let program = programs.program(ShaderFeatures::for_instanced_mesh(mesh))?;
+                self.render(&program, mesh, shader, model.models.len());
             }
         }
+        Ok(())
     }
 
-    fn render(&self, mesh: &Mesh, shader: &InstancedShader, models: usize) {
-        mesh.set_program(&self.program, &shader.textures);
+    fn render(&self, program: &Program, mesh: &Mesh, shader: &InstancedShader, models: usize) {
+        program.use_program();
+        mesh.set_program(program, &shader.textures);
         let n_vertices = mesh.vertices.len();
         shader.vertex_array.bind();
         gl_function!(DrawArraysInstanced(gl::TRIANGLES, 0, n_vertices as _, models as _));
@@ -1,4 +1,4 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -12,49 +12,188 @@ use image::io::Reader;
 use include_dir::{Dir, include_dir};
 use log::warn;
 use nalgebra::{Matrix4, Scale3, Vector3};
-use russimp::texture::{TextureType as AssimpTextureType};
+use crate::error::Error;
 use crate::buffer::Buffer;
 use crate::camera::Camera;
-use crate::ecs::components::{Border, ExtraUniform, Mesh, Model, Shader, SkipRendering, Skybox, SKYBOX_VERTICES, TextureInfo, Transform, Transparent, UniformValue};
+use crate::ecs::components::{Border, CastsShadows, ExtraUniform, InstancedMesh, InstancedModel, Mesh, Model, Shader, SkipRendering, Skybox, ShadowReceiver, SKYBOX_VERTICES, TextureInfo, Transform, Transparent};
+use crate::ecs::systems::rendering::clustered_lighting::{ClusteredLighting, LightBounds};
+use crate::ecs::systems::rendering::deferred::GBufferView;
+use crate::ecs::systems::rendering::ibl::{ImageBasedLighting, ImageBasedLightingSettings};
 use crate::ecs::systems::rendering::instanced_rendering::InstancedRendering;
-use crate::ecs::systems::rendering::normal_mapping_rendering::NormalMappingRendering;
+use crate::ecs::systems::rendering::permutations::{ShaderFeatures, ShaderPermutations};
+use crate::ecs::systems::rendering::point_shadows::{MAX_POINT_SHADOWS, PointShadowMaps, push_point_shadow};
+use crate::ecs::systems::rendering::shadows::{MAX_SHADOWS, push_directional_shadow, push_spot_shadow, SHADOWS_BINDING_POINT, ShadowMaps};
+use crate::ecs::systems::rendering::ssao::{Ssao, SsaoSettings};
 use crate::ecs::systems::system::System;
-use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+use crate::light::{DirectionalLight, DirectionalLightData, Light, PointLight, PointLightData, SpotLight, SpotLightData};
+use crate::std140::Std140;
 use crate::program::Program;
 use crate::shader_loader::{ShaderLoader, ShaderType};
+use crate::std140;
 use crate::texture::{Texture, TextureType};
+use crate::uniform_block::UniformBlock;
 use crate::vertex_array::VertexArray;
 
 static BORDER_VERTEX_SHADER: &'static str = "17.1-uniform_buffer_objects_vertex_border.glsl";
 static BORDER_FRAGMENT_SHADER: &'static str = "14.1-border_color.glsl";
 static SKYBOX_VERTEX_SHADER: &'static str = "17.1-uniform_buffer_object_vertex_skybox.glsl";
 static SKYBOX_FRAGMENT_SHADER: &'static str = "16.1-skybox_fragment.glsl";
+static MESH_VERTEX_SHADER: &'static str = "mesh_vertex.glsl";
+static MESH_GEOMETRY_SHADER: &'static str = "mesh_geometry.glsl";
+static MESH_FRAGMENT_SHADER: &'static str = "mesh_fragment.glsl";
+static HEIGHT_SCALE: f32 = 0.1f32;
 static SHADERS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/src/bin/shaders");
+pub const MATRICES_BINDING_POINT: usize = 0;
+pub const LIGHTS_BINDING_POINT: usize = 1;
+const MAX_STORAGE_LIGHTS: usize = 1024;
+const RENDERING_PATH_VARIABLE: &'static str = "LEARNOPENGL_RENDERING_PATH";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderingPath {
+    Forward,
+    Deferred,
+}
+
+impl RenderingPath {
+    pub fn from_env() -> RenderingPath {
+        match std::env::var(RENDERING_PATH_VARIABLE).as_deref() {
+            Ok("deferred") => RenderingPath::Deferred,
+            _ => RenderingPath::Forward,
+        }
+    }
+}
+
+fn collect_lights<T, D, F>(world: &World, max_lights: usize, mut data: F) -> (Vec<D>, usize)
+where
+    T: Send + Sync + 'static,
+    F: FnMut(Entity, &T, Option<&CastsShadows>) -> D,
+{
+    let lights = world.query::<(&T, Option<&CastsShadows>)>().iter()
+        .take(max_lights)
+        .map(|(e, (light, casts_shadows))| data(e, light, casts_shadows))
+        .collect::<Vec<_>>();
+    let total = world.query::<&T>().iter().count();
+    (lights, total)
+}
+
+pub fn storage_buffers_supported() -> bool {
+    let (mut major, mut minor) = (0, 0);
+    gl_function!(GetIntegerv(gl::MAJOR_VERSION, &mut major));
+    gl_function!(GetIntegerv(gl::MINOR_VERSION, &mut minor));
+    (major, minor) >= (4, 3)
+}
 
-fn set_lights<T: Light + Send + Sync + Debug + 'static>(program: &Program, world: &mut World, name: &str) {
-    let mut index = 0;
-    for (_e, light) in world.query_mut::<&T>() {
-        let name = format!("{}[{}]", name, index);
-        light.set_light_in_program(program, &name);
-        program.set_uniform_i1(&format!("{}.set", name), 1);
-        index += 1;
+fn light_data_sizes() -> [usize; 3] {
+    [
+        DirectionalLightData::default().std140_bytes().len(),
+        PointLightData::default().std140_bytes().len(),
+        SpotLightData::default().std140_bytes().len(),
+    ]
+}
+
+pub fn max_lights() -> usize {
+    let lights_size = light_data_sizes().iter().sum::<usize>();
+    if storage_buffers_supported() {
+        let mut max_block_size = 0;
+        gl_function!(GetInteger64v(gl::MAX_SHADER_STORAGE_BLOCK_SIZE, &mut max_block_size));
+        return (max_block_size as usize / lights_size).min(MAX_STORAGE_LIGHTS);
     }
+    let mut max_block_size = 0;
+    gl_function!(GetIntegerv(gl::MAX_UNIFORM_BLOCK_SIZE, &mut max_block_size));
+    max_block_size as usize / lights_size
 }
 
+// mesh_*.glsl is a Blinn-Phong uber-shader for what the chapter shaders can't draw: instancing and normal
+// mapping. Everything else, including metalness meshes that need the chapter's PBR shader, keeps using the
+// shaders the chapter passed in, so each chapter still renders with the shader it teaches.
+fn uses_mesh_shader(features: ShaderFeatures) -> bool {
+    features.contains(ShaderFeatures::INSTANCED) ||
+        (features.contains(ShaderFeatures::HAS_NORMAL_MAP) && !features.contains(ShaderFeatures::HAS_METALNESS))
+}
+
+fn mesh_stages(
+    features: ShaderFeatures,
+    meshes_vertex_shader: &'static str,
+    meshes_fragment_shader: &'static str,
+) -> Vec<(ShaderType, &'static str)> {
+    if !uses_mesh_shader(features) {
+        return vec![
+            (ShaderType::Vertex, meshes_vertex_shader),
+            (ShaderType::Fragment, meshes_fragment_shader),
+        ];
+    }
+    let mut stages = vec![(ShaderType::Vertex, MESH_VERTEX_SHADER)];
+    if features.contains(ShaderFeatures::HAS_NORMAL_MAP) && !features.contains(ShaderFeatures::HAS_TANGENTS) {
+        stages.push((ShaderType::Geometry, MESH_GEOMETRY_SHADER));
+    }
+    stages.push((ShaderType::Fragment, MESH_FRAGMENT_SHADER));
+    stages
+}
+
+std140! {
+    pub struct Matrices {
+        pub view: Matrix4<f32>,
+        pub projection: Matrix4<f32>,
+    }
+}
+
+std140! {
+    pub struct Lights {
+        pub directional_lights: Vec<DirectionalLightData>,
+        pub point_lights: Vec<PointLightData>,
+        pub spot_lights: Vec<SpotLightData>,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct LightCounts {
+    directional: usize,
+    point: usize,
+    spot: usize,
+}
+
+fn setup_mesh_program(features: ShaderFeatures, program: &Program) {
+    program.bind_uniform_block("Matrices", MATRICES_BINDING_POINT);
+    program.bind_uniform_block("Lights", LIGHTS_BINDING_POINT);
+    if storage_buffers_supported() {
+        program.bind_storage_block("Lights", LIGHTS_BINDING_POINT);
+    }
+    program.bind_uniform_block("Shadows", SHADOWS_BINDING_POINT);
+    if uses_mesh_shader(features) && features.contains(ShaderFeatures::HAS_NORMAL_MAP | ShaderFeatures::HAS_HEIGHT_MAP) {
+        program.use_program();
+        program.set_uniform_f1("height_scale", HEIGHT_SCALE);
+    }
+}
+
+pub mod clustered_lighting;
+pub mod deferred;
+pub mod ibl;
 pub mod instanced_rendering;
-pub mod normal_mapping_rendering;
+pub mod permutations;
+pub mod point_shadows;
+pub mod shadows;
+pub mod ssao;
 
 pub struct RenderingSystem {
     border_program: Program,
     clear_color: Vector3<f32>,
+    clustered_lighting: Option<ClusteredLighting>,
+    g_buffer_view: Rc<Cell<GBufferView>>,
+    image_based_lighting: Option<Rc<ImageBasedLighting>>,
     pub(crate) instanced_rendering: InstancedRendering,
-    pub(crate) normal_mapping_rendering: NormalMappingRendering,
     light_program: Program,
+    lights: UniformBlock<Lights>,
+    lights_truncated: Cell<bool>,
     main_camera: Rc<RefCell<Camera>>,
-    meshes_program: Program,
+    matrices: UniformBlock<Matrices>,
+    max_lights: usize,
+    mesh_programs: ShaderPermutations,
+    point_shadow_maps: PointShadowMaps,
+    shader_loader: ShaderLoader,
+    shadow_maps: ShadowMaps,
     skybox_program: Program,
+    ssao: Option<Rc<Ssao>>,
     textures_loaded: HashMap<String, Arc<Texture>>,
-    uniform_buffer: Buffer,
 }
 
 impl RenderingSystem {
@@ -65,49 +204,90 @@ impl RenderingSystem {
         light_fragment_shader: &'static str,
         meshes_vertex_shader: &'static str,
         meshes_fragment_shader: &'static str,
-    ) -> Result<RenderingSystem, String> {
-        let shader_loader = ShaderLoader::new(&SHADERS_DIR);
-        let border_program = Program::new(vec![
-            shader_loader.load(ShaderType::Vertex, BORDER_VERTEX_SHADER)?,
-            shader_loader.load(ShaderType::Fragment, BORDER_FRAGMENT_SHADER)?,
-        ])?;
-        let light_program = Program::new(vec![
-            shader_loader.load(ShaderType::Vertex, light_vertex_shader)?,
-            shader_loader.load(ShaderType::Fragment, light_fragment_shader)?,
+    ) -> Result<RenderingSystem, Error> {
+        let max_lights = max_lights();
+        let storage_buffers = storage_buffers_supported();
+        let clustered_lighting = ClusteredLighting::from_env();
+        let mut shader_loader = ShaderLoader::from_env(&SHADERS_DIR)
+            .define("MAX_LIGHTS", max_lights)
+            .define("MAX_SHADOWS", MAX_SHADOWS)
+            .define("MAX_POINT_SHADOWS", MAX_POINT_SHADOWS);
+        if storage_buffers {
+            shader_loader = shader_loader
+                .extension("GL_ARB_shader_storage_buffer_object")
+                .define("LIGHTS_STORAGE_BUFFER", 1);
+        }
+        if clustered_lighting.is_some() {
+            shader_loader = shader_loader.define("CLUSTERED_LIGHTING", 1);
+        }
+        let border_program = shader_loader.program(vec![
+            (ShaderType::Vertex, BORDER_VERTEX_SHADER),
+            (ShaderType::Fragment, BORDER_FRAGMENT_SHADER),
         ])?;
-        let meshes_program = Program::new(vec![
-            shader_loader.load(ShaderType::Vertex, meshes_vertex_shader)?,
-            shader_loader.load(ShaderType::Fragment, meshes_fragment_shader)?,
+        let light_program = shader_loader.program(vec![
+            (ShaderType::Vertex, light_vertex_shader),
+            (ShaderType::Fragment, light_fragment_shader),
         ])?;
-        let skybox_program = Program::new(vec![
-            shader_loader.load(ShaderType::Vertex, SKYBOX_VERTEX_SHADER)?,
-            shader_loader.load(ShaderType::Fragment, SKYBOX_FRAGMENT_SHADER)?,
+        let mesh_programs = ShaderPermutations::new(
+            &shader_loader,
+            move |features| mesh_stages(features, meshes_vertex_shader, meshes_fragment_shader),
+            setup_mesh_program,
+        );
+        let skybox_program = shader_loader.program(vec![
+            (ShaderType::Vertex, SKYBOX_VERTEX_SHADER),
+            (ShaderType::Fragment, SKYBOX_FRAGMENT_SHADER),
         ])?;
-        border_program.bind_uniform_block("Matrices", 0);
-        light_program.bind_uniform_block("Matrices", 0);
-        meshes_program.bind_uniform_block("Matrices", 0);
-        skybox_program.bind_uniform_block("Matrices", 0);
-        let uniform_buffer = Buffer::new(gl::UNIFORM_BUFFER);
-        let buffer_size = Matrix4::<f32>::identity().len() * 2;
-        uniform_buffer.bind();
-        uniform_buffer.allocate_data::<f32>(buffer_size);
-        uniform_buffer.unbind();
-        uniform_buffer.link_to_binding_point(0, 0, buffer_size);
+        skybox_program.setup(|program| {
+            program.use_program();
+            program.set_uniform_i1("skybox", 0);
+        });
+        let matrices = UniformBlock::new(MATRICES_BINDING_POINT, &Matrices {
+            view: Matrix4::identity(),
+            projection: Matrix4::identity(),
+        });
+        matrices.bind_to(&border_program, "Matrices")?;
+        matrices.bind_to(&light_program, "Matrices")?;
+        matrices.bind_to(&skybox_program, "Matrices")?;
+        let lights_data = Lights {
+            directional_lights: vec![DirectionalLightData::default(); max_lights],
+            point_lights: vec![PointLightData::default(); max_lights],
+            spot_lights: vec![SpotLightData::default(); max_lights],
+        };
+        let lights = if storage_buffers {
+            UniformBlock::storage(LIGHTS_BINDING_POINT, &lights_data)
+        } else {
+            UniformBlock::new(LIGHTS_BINDING_POINT, &lights_data)
+        };
+        let shadow_maps = ShadowMaps::new(&shader_loader)?;
+        let point_shadow_maps = PointShadowMaps::new(&shader_loader)?;
+        let ssao = match SsaoSettings::from_env() {
+            Some(settings) => Some(Rc::new(Ssao::new(&shader_loader, settings)?)),
+            None => None,
+        };
         Ok(RenderingSystem {
             border_program,
             clear_color,
+            clustered_lighting,
+            g_buffer_view: Rc::new(Cell::new(GBufferView::default())),
+            image_based_lighting: None,
             light_program,
-            meshes_program,
+            mesh_programs,
             skybox_program,
-            uniform_buffer,
-            instanced_rendering: InstancedRendering::new(&shader_loader)?,
-            normal_mapping_rendering: NormalMappingRendering::new(&shader_loader)?,
+            matrices,
+            lights,
+            lights_truncated: Cell::new(false),
+            max_lights,
+            instanced_rendering: InstancedRendering::new(),
             main_camera: camera,
+            point_shadow_maps,
+            shader_loader,
+            shadow_maps,
+            ssao,
             textures_loaded: HashMap::new(),
         })
     }
 
-    pub fn shader_for_mesh(&mut self, mesh: &Mesh) -> Result<Shader, String> {
+    pub fn shader_for_mesh(&mut self, mesh: &Mesh) -> Result<Shader, Error> {
         let vertex_array = Arc::new(VertexArray::new());
         let vertex_buffer = Arc::new(Buffer::new(gl::ARRAY_BUFFER));
         let elements_buffer = if mesh.indices.is_some() {
@@ -124,29 +304,79 @@ impl RenderingSystem {
         })
     }
 
-    pub fn shader_for_skybox(&mut self, skybox: &Skybox) -> Result<Shader, String> {
+    pub fn clustered_lighting(&self) -> Option<&ClusteredLighting> {
+        self.clustered_lighting.as_ref()
+    }
+
+    pub fn enable_clustered_lighting(&mut self, dimensions: (usize, usize, usize)) -> &ClusteredLighting {
+        if self.clustered_lighting.is_none() {
+            self.shader_loader = self.shader_loader.clone().define("CLUSTERED_LIGHTING", 1);
+            self.mesh_programs.set_shader_loader(&self.shader_loader);
+        }
+        self.clustered_lighting.insert(ClusteredLighting::with_env_override(dimensions))
+    }
+
+    pub fn shadow_maps(&self) -> &ShadowMaps {
+        &self.shadow_maps
+    }
+
+    pub fn image_based_lighting(&self) -> Option<Rc<ImageBasedLighting>> {
+        self.image_based_lighting.clone()
+    }
+
+    pub fn enable_image_based_lighting(
+        &mut self, path: &str, settings: ImageBasedLightingSettings,
+    ) -> Result<Rc<ImageBasedLighting>, Error> {
+        let image_based_lighting = Rc::new(ImageBasedLighting::from_hdr(&self.shader_loader, path, settings)?);
+        self.image_based_lighting = Some(image_based_lighting.clone());
+        Ok(image_based_lighting)
+    }
+
+    pub fn g_buffer_view(&self) -> Rc<Cell<GBufferView>> {
+        self.g_buffer_view.clone()
+    }
+
+    pub fn ssao(&self) -> Option<Rc<Ssao>> {
+        self.ssao.clone()
+    }
+
+    pub fn enable_ssao(&mut self, settings: SsaoSettings) -> Result<Rc<Ssao>, Error> {
+        let ssao = Rc::new(Ssao::new(&self.shader_loader, settings)?);
+        self.ssao = Some(ssao.clone());
+        Ok(ssao)
+    }
+
+    pub fn shader_loader(&self) -> &ShaderLoader {
+        &self.shader_loader
+    }
+
+    pub fn shader_for_skybox(&mut self, skybox: &Skybox) -> Result<Shader, Error> {
         let vertex_array = Arc::new(VertexArray::new());
         let vertex_buffer = Arc::new(Buffer::new(gl::ARRAY_BUFFER));
+        let textures = if skybox.texture_info.path.ends_with(".hdr") {
+            self.enable_image_based_lighting(&skybox.texture_info.path, ImageBasedLightingSettings::default())?;
+            vec![]
+        } else {
+            vec![self.setup_cubemap_texture(&skybox.texture_info)?]
+        };
         Ok(Shader {
             vertex_array,
             vertex_buffer,
             elements_buffer: None,
-            textures: vec![self.setup_cubemap_texture(&skybox.texture_info)?],
+            textures,
         })
     }
 
-    fn setup_skybox(&self, shader: &Shader) -> Result<(), String> {
+    fn setup_skybox(&self, shader: &Shader) -> Result<(), Error> {
         shader.vertex_array.bind();
         shader.vertex_buffer.bind();
         shader.vertex_buffer.set_data(&SKYBOX_VERTICES, gl::STATIC_DRAW);
         VertexArray::set_vertex_attrib::<f32>(gl::FLOAT, 0, 3, false);
         VertexArray::unbind();
-        self.skybox_program.use_program();
-        self.skybox_program.set_uniform_i1("skybox", 0);
         Ok(())
     }
 
-    fn setup_gl_objects(&self, shader: &Shader, mesh: &Mesh) -> Result<(), String> {
+    fn setup_gl_objects(&self, shader: &Shader, mesh: &Mesh) -> Result<(), Error> {
         shader.vertex_array.bind();
         shader.vertex_buffer.bind();
         shader.vertex_buffer.set_data(&mesh.flattened_data(), gl::STATIC_DRAW);
@@ -202,18 +432,18 @@ impl RenderingSystem {
         Ok(())
     }
 
-    fn setup_textures(&mut self, textures: &Option<Vec<TextureInfo>>) -> Result<Vec<Arc<Texture>>, String> {
+    fn setup_textures(&mut self, textures: &Option<Vec<TextureInfo>>) -> Result<Vec<Arc<Texture>>, Error> {
         match textures {
             None => Ok(vec![]),
             Some(textures) => {
                 textures.iter().map(|t| {
                     self.texture_info_to_texture(t)
-                }).collect::<Result<Vec<Arc<Texture>>, String>>()
+                }).collect::<Result<Vec<Arc<Texture>>, Error>>()
             }
         }
     }
 
-    fn setup_cubemap_texture(&mut self, texture_info: &TextureInfo) -> Result<Arc<Texture>, String> {
+    fn setup_cubemap_texture(&mut self, texture_info: &TextureInfo) -> Result<Arc<Texture>, Error> {
         if let Some(texture) = self.textures_loaded.get(&texture_info.path) {
             Ok(texture.clone())
         } else {
@@ -222,8 +452,8 @@ impl RenderingSystem {
             let root = Path::new(&texture_info.path);
             for (i, path) in vec!["right.jpg", "left.jpg", "top.jpg", "bottom.jpg", "front.jpg", "back.jpg"].into_iter().enumerate() {
                 let path = root.join(path);
-                let image = Reader::open(path).map_err(|e| e.to_string())?
-                    .decode().map_err(|e| e.to_string())?
+                let image = Reader::open(&path).map_err(|e| Error::io(&path, e))?
+                    .decode().map_err(|e| Error::image_decode(&path, e))?
                     .to_rgba8();
                 texture.set_cube_map_face(i as u32, image.width() as _, image.height() as _, image.as_bytes());
             }
@@ -236,7 +466,7 @@ impl RenderingSystem {
         }
     }
 
-    fn texture_info_to_texture(&mut self, texture_info: &TextureInfo) -> Result<Arc<Texture>, String> {
+    fn texture_info_to_texture(&mut self, texture_info: &TextureInfo) -> Result<Arc<Texture>, Error> {
         if let Some(texture) = self.textures_loaded.get(&texture_info.path) {
             Ok(texture.clone())
         } else {
@@ -246,8 +476,8 @@ impl RenderingSystem {
             texture.set_parameter(gl::TEXTURE_WRAP_T, gl::REPEAT);
             texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR);
             texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
-            let image = Reader::open(&texture_info.path).map_err(|e| e.to_string())?
-                .decode().map_err(|e| e.to_string())?
+            let image = Reader::open(&texture_info.path).map_err(|e| Error::io(&texture_info.path, e))?
+                .decode().map_err(|e| Error::image_decode(&texture_info.path, e))?
                 .flipv();
             match texture.set_image_2d_with_type(
                 image.width() as u32,
@@ -271,7 +501,7 @@ impl RenderingSystem {
         }
     }
 
-    fn draw_lights<T: Light + Send + Sync + 'static>(&self, world: &mut World) -> Result<(), String> {
+    fn draw_lights<T: Light + Send + Sync + 'static>(&self, world: &mut World) -> Result<(), Error> {
         for (_e, (light, mesh, shader)) in world.query_mut::<(&T, &Mesh, &Shader)>().without::<Skybox>().without::<SkipRendering>() {
             light.set_light_drawing_program_no_globals(
                 &self.light_program, "light.specular", "model",
@@ -306,10 +536,11 @@ impl RenderingSystem {
         }
     }
 
-    fn render_bordered_objects(&self, world: &mut World) -> Result<(), String> {
+    fn render_bordered_objects(&self, world: &mut World) -> Result<(), Error> {
         gl_function!(StencilFunc(gl::ALWAYS, 1, 0xff));
         gl_function!(StencilMask(0xff));
         self.render_objects(
+            &self.mesh_programs,
             world.query::<(&Mesh, &Shader, &Transform)>().without::<SkipRendering>().with::<Border>().iter(),
             world.query::<(&Model, &Transform)>().without::<SkipRendering>().with::<Border>().iter(),
             world,
@@ -333,35 +564,167 @@ impl RenderingSystem {
         Ok(())
     }
 
-    fn setup_program_globals(&self, world: &mut World) {
-        let projection = (*self.main_camera).borrow().projection();
-        let view = (*self.main_camera).borrow().look_at_matrix();
-        self.uniform_buffer.bind();
-        self.uniform_buffer.set_sub_data(0, view.len(), view.as_slice());
-        self.uniform_buffer.set_sub_data(view.len(), projection.len(), projection.as_slice());
-        self.uniform_buffer.unbind();
-        self.set_rendering_program(&self.meshes_program, world);
-        self.set_rendering_program(&self.normal_mapping_rendering.dynamic_calculation_program, world);
-        self.set_rendering_program(&self.normal_mapping_rendering.precomputed_program, world);
-        self.set_rendering_program(&self.instanced_rendering.program, world);
+    fn setup_program_globals(&self, world: &mut World) -> Result<LightCounts, Error> {
+        self.matrices.set(&Matrices {
+            view: (*self.main_camera).borrow().look_at_matrix(),
+            projection: (*self.main_camera).borrow().projection(),
+        })?;
+        let light_counts = self.update_lights(world)?;
+        if let Some(image_based_lighting) = self.image_based_lighting.as_ref() {
+            image_based_lighting.bind();
+        }
+        for program in self.mesh_programs.programs() {
+            self.set_rendering_program(&program, light_counts);
+        }
+        Ok(light_counts)
+    }
+
+    fn compile_mesh_programs(&self, world: &World) -> Result<(), Error> {
+        let mut features = vec![];
+        for (_e, mesh) in world.query::<&Mesh>().iter() {
+            features.push(ShaderFeatures::for_mesh(mesh));
+        }
+        for (_e, model) in world.query::<&Model>().iter() {
+            features.extend(model.0.iter().map(|(mesh, _)| ShaderFeatures::for_mesh(mesh)));
+        }
+        for (_e, mesh) in world.query::<&InstancedMesh>().iter() {
+            features.push(ShaderFeatures::for_instanced_mesh(&mesh.mesh));
+        }
+        for (_e, model) in world.query::<&InstancedModel>().iter() {
+            features.extend(model.model.iter().map(|(mesh, _)| ShaderFeatures::for_instanced_mesh(mesh)));
+        }
+        for features in features {
+            if !self.mesh_programs.is_compiled(features) {
+                self.mesh_programs.program(features)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_mesh_uniforms(&self, world: &World) -> Result<(), Error> {
+        for (e, mesh) in world.query::<&Mesh>().iter() {
+            self.validate_mesh(world, e, mesh)?;
+        }
+        for (e, model) in world.query::<&Model>().iter() {
+            for (mesh, _) in model.0.iter() {
+                self.validate_mesh(world, e, mesh)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_mesh(&self, world: &World, e: Entity, mesh: &Mesh) -> Result<(), Error> {
+        let program = self.get_rendering_program(mesh)?;
+        mesh.validate_program(&program)?;
+        if let Some(extra_uniforms) = world.query_one::<&Vec<ExtraUniform>>(e)?.get() {
+            for extra_uniform in extra_uniforms.iter() {
+                extra_uniform.validate(&program)?;
+            }
+        }
+        Ok(())
     }
 
-    fn set_rendering_program(&self, program: &Program, world: &mut World) {
+    fn update_lights(&self, world: &World) -> Result<LightCounts, Error> {
+        let camera = (*self.main_camera).borrow();
+        let mut shadows = vec![];
+        let (directional_lights, directional_total) = collect_lights(world, self.max_lights, |_e, light: &DirectionalLight, casts_shadows| {
+            let mut data = DirectionalLightData::from(light);
+            if let Some(casts_shadows) = casts_shadows {
+                data.shadow_index = push_directional_shadow(&mut shadows, light, casts_shadows, &camera);
+            }
+            data
+        });
+        let mut point_shadows = vec![];
+        let (point_lights, point_total) = collect_lights(world, self.max_lights, |e, light: &PointLight, casts_shadows| {
+            let mut data = PointLightData::from(light);
+            if let Some(casts_shadows) = casts_shadows {
+                data.shadow_index = push_point_shadow(&mut point_shadows, e, light, casts_shadows);
+            }
+            data
+        });
+        let (spot_lights, spot_total) = collect_lights(world, self.max_lights, |_e, light: &SpotLight, casts_shadows| {
+            let mut data = SpotLightData::from(light);
+            if let Some(casts_shadows) = casts_shadows {
+                data.shadow_index = push_spot_shadow(&mut shadows, light, casts_shadows, &camera);
+            }
+            data
+        });
+        let point_shadows = self.point_shadow_maps.update(world, &point_shadows)?;
+        self.shadow_maps.update(world, &camera, &shadows, point_shadows)?;
+        self.shadow_maps.bind();
+        self.point_shadow_maps.bind();
+        let [directional_size, point_size, _] = light_data_sizes();
+        self.lights.set_bytes(0, &directional_lights.std140_bytes())?;
+        self.lights.set_bytes(self.max_lights * directional_size, &point_lights.std140_bytes())?;
+        self.lights.set_bytes(self.max_lights * (directional_size + point_size), &spot_lights.std140_bytes())?;
+        self.warn_truncated_lights(directional_total, point_total, spot_total);
+        if let Some(clustered_lighting) = self.clustered_lighting.as_ref() {
+            let point_bounds = world.query::<&PointLight>().iter()
+                .take(self.max_lights)
+                .map(|(_e, light)| LightBounds { position: light.position, radius: light.radius() })
+                .collect::<Vec<_>>();
+            let spot_bounds = world.query::<&SpotLight>().iter()
+                .take(self.max_lights)
+                .map(|(_e, light)| LightBounds { position: light.position(), radius: light.radius() })
+                .collect::<Vec<_>>();
+            clustered_lighting.update(&camera, &point_bounds, &spot_bounds);
+            clustered_lighting.bind();
+        }
+        Ok(LightCounts {
+            directional: directional_lights.len(),
+            point: point_lights.len(),
+            spot: spot_lights.len(),
+        })
+    }
+
+    fn warn_truncated_lights(&self, directional: usize, point: usize, spot: usize) {
+        let truncated = directional.max(point).max(spot) > self.max_lights;
+        if truncated && !self.lights_truncated.get() {
+            warn!(
+                "Only {} lights of each type are rendered, found {} directional, {} point and {} spot lights",
+                self.max_lights, directional, point, spot,
+            );
+        }
+        self.lights_truncated.set(truncated);
+    }
+
+    fn set_rendering_program(&self, program: &Program, light_counts: LightCounts) {
         program.use_program();
-        set_lights::<DirectionalLight>(&program, world, "directional_lights");
-        set_lights::<SpotLight>(&program, world, "spot_lights");
-        set_lights::<PointLight>(&program, world, "point_lights");
+        program.set_uniform_i1("n_directional_lights", light_counts.directional as _);
+        program.set_uniform_i1("n_point_lights", light_counts.point as _);
+        program.set_uniform_i1("n_spot_lights", light_counts.spot as _);
         program.set_uniform_v3("viewPos", (*self.main_camera).borrow().position());
+        self.shadow_maps.set_program(program);
+        self.point_shadow_maps.set_program(program);
+        if let Some(ssao) = self.ssao.as_ref() {
+            ssao.set_program(program);
+        }
+        match self.image_based_lighting.as_ref() {
+            Some(image_based_lighting) => image_based_lighting.set_program(program),
+            None => ImageBasedLighting::unset_program(program),
+        }
+        if let Some(clustered_lighting) = self.clustered_lighting.as_ref() {
+            clustered_lighting.set_program(program, &(*self.main_camera).borrow());
+        }
     }
 
-    fn render_skybox(&self, world: &mut World) -> Result<(), String> {
+    fn render_skybox(&self, world: &mut World) -> Result<(), Error> {
         let skybox = world.query_mut::<&Shader>().with::<Skybox>().into_iter().next();
         if let Some((_e, shader)) = skybox {
             gl_function!(DepthFunc(gl::EQUAL));
             self.skybox_program.use_program();
             shader.vertex_array.bind();
-            let texture = shader.textures.get(0).ok_or("Skybox with no texture".to_string())?;
-            texture.bind(gl::TEXTURE0);
+            match self.image_based_lighting.as_ref() {
+                Some(image_based_lighting) if shader.textures.is_empty() => {
+                    image_based_lighting.environment().bind(gl::TEXTURE0);
+                    self.skybox_program.set_uniform("hdr", &true);
+                }
+                _ => {
+                    let texture = shader.textures.get(0).ok_or("Skybox with no texture".to_string())?;
+                    texture.bind(gl::TEXTURE0);
+                    self.skybox_program.set_uniform("hdr", &false);
+                }
+            }
             gl_function!(DrawArrays(gl::TRIANGLES, 0, 36));
             VertexArray::unbind();
             gl_function!(DepthFunc(gl::LESS));
@@ -369,9 +732,10 @@ impl RenderingSystem {
         Ok(())
     }
 
-    fn render_non_bordered_objects(&self, world: &mut World) -> Result<(), String> {
+    fn render_non_bordered_objects(&self, world: &mut World) -> Result<(), Error> {
         gl_function!(StencilMask(0x00));
         self.render_objects(
+            &self.mesh_programs,
             world.query::<(&Mesh, &Shader, &Transform)>().without::<Border>().without::<Transparent>().without::<SkipRendering>().iter(),
             world.query::<(&Model, &Transform)>().without::<Border>().without::<Transparent>().without::<SkipRendering>().iter(),
             world,
@@ -379,19 +743,8 @@ impl RenderingSystem {
         Ok(())
     }
 
-    fn get_rendering_program(&self, mesh: &Mesh) -> &Program {
-        match &mesh.textures {
-            Some(textures) => {
-                let normal_texture = textures.iter().find(|t| t.texture_type == AssimpTextureType::Normals);
-                let metalness_texture = textures.iter().find(|t| t.texture_type == AssimpTextureType::Metalness);
-                if normal_texture.is_some() && metalness_texture.is_none() {
-                    &self.normal_mapping_rendering.get_program_for_mesh(mesh)
-                } else {
-                    &self.meshes_program
-                }
-            }
-            None => &self.meshes_program
-        }
+    fn get_rendering_program(&self, mesh: &Mesh) -> Result<Rc<Program>, Error> {
+        self.mesh_programs.program(ShaderFeatures::for_mesh(mesh))
     }
 
     fn render_objects<
@@ -399,50 +752,38 @@ impl RenderingSystem {
         I: Iterator<Item=(Entity, (&'a Mesh, &'a Shader, &'a Transform))>,
         J: Iterator<Item=(Entity, (&'a Model, &'a Transform))>
     >(
-        &self, mesh_query_results: I, model_query_results: J, world: &World,
-    ) -> Result<(), String> {
+        &self, programs: &ShaderPermutations, mesh_query_results: I, model_query_results: J, world: &World,
+    ) -> Result<(), Error> {
         for (e, (mesh, shader, transform)) in mesh_query_results {
-            let program = self.get_rendering_program(mesh);
+            let program = programs.program(ShaderFeatures::for_mesh(mesh))?;
             program.use_program();
-            self.set_mesh_uniforms(program, world, e, &transform)?;
+            self.set_mesh_uniforms(&program, world, e, &transform)?;
             self.render_mesh(&program, shader, mesh);
         }
         for (e, (model, transform)) in model_query_results {
             for (mesh, shader) in model.0.iter() {
-                let program = self.get_rendering_program(mesh);
+                let program = programs.program(ShaderFeatures::for_mesh(mesh))?;
                 program.use_program();
-                self.set_mesh_uniforms(program, world, e, &transform)?;
+                self.set_mesh_uniforms(&program, world, e, &transform)?;
                 self.render_mesh(&program, shader, mesh);
             }
         }
         Ok(())
     }
 
-    fn set_mesh_uniforms(&self, program: &Program, world: &World, e: Entity, transform: &&Transform) -> Result<(), String> {
-        let extra_uniforms = world.query_one::<&Vec<ExtraUniform>>(e).map_err(|e| e.to_string())?.get().cloned();
+    fn set_mesh_uniforms(&self, program: &Program, world: &World, e: Entity, transform: &&Transform) -> Result<(), Error> {
+        let extra_uniforms = world.query_one::<&Vec<ExtraUniform>>(e)?.get().cloned();
         if let Some(extra_uniforms) = extra_uniforms {
             for eu in extra_uniforms {
-                match &eu.value {
-                    UniformValue::Float(f) => {
-                        program.set_uniform_f1(eu.name, *f);
-                    }
-                    UniformValue::Texture(i) => {
-                        program.set_uniform_i1(eu.name, *i as _);
-                    },
-                    UniformValue::Matrix(m) => {
-                        program.set_uniform_matrix4(eu.name, m);
-                    }
-                    UniformValue::Vector3(v) => {
-                        program.set_uniform_v3(eu.name, *v);
-                    }
-                }
+                eu.value.set(program, eu.name);
             }
         }
         program.set_uniform_matrix4("model", &transform.get_model_matrix());
+        program.set_uniform("receive_shadows", &world.get::<ShadowReceiver>(e).is_ok());
         Ok(())
     }
 
-    fn render_transparent_objects(&self, world: &mut World) -> Result<(), String> {
+    fn render_transparent_objects(&self, world: &mut World) -> Result<(), Error> {
         let mut entities = vec![];
         for (e, transform) in world.query::<&Transform>().without::<SkipRendering>()
             .with::<Transparent>()
@@ -460,25 +801,44 @@ impl RenderingSystem {
             }
         );
         gl_function!(StencilMask(0x00));
-        self.meshes_program.use_program();
         for (_, e) in entities {
-            self.render_entity(&self.meshes_program, e, world)?;
+            self.render_entity(e, world)?;
         }
         Ok(())
     }
 
-    fn render_entity(&self, program: &Program, e: Entity, world: &mut World) -> Result<(), String> {
-        let mut mesh = world.query_one::<(&Mesh, &Shader, &Transform)>(e).map_err(|e| e.to_string())?;
+    fn render_forward_overlays(&self, world: &mut World) -> Result<(), Error> {
+        if world.query_mut::<&Transparent>().into_iter().next().is_some() {
+            gl_function!(Enable(gl::BLEND));
+            gl_function!(BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+            self.render_transparent_objects(world)?;
+        } else {
+            gl_function!(Disable(gl::BLEND));
+        }
+        self.light_program.use_program();
+        self.draw_lights::<DirectionalLight>(world)?;
+        self.draw_lights::<SpotLight>(world)?;
+        self.draw_lights::<PointLight>(world)?;
+        self.render_skybox(world)?;
+        Ok(())
+    }
+
+    fn render_entity(&self, e: Entity, world: &mut World) -> Result<(), Error> {
+        let mut mesh = world.query_one::<(&Mesh, &Shader, &Transform)>(e)?;
         match mesh.get() {
             Some((mesh, shader, transform)) => {
-                self.set_mesh_uniforms(program, world, e, &transform)?;
+                let program = self.get_rendering_program(mesh)?;
+                program.use_program();
+                self.set_mesh_uniforms(&program, world, e, &transform)?;
                 self.render_mesh(&program, shader, mesh);
             }
             None => {
-                let mut model = world.query_one::<(&Model, &Transform)>(e).map_err(|e| e.to_string())?;
+                let mut model = world.query_one::<(&Model, &Transform)>(e)?;
                 if let Some((model, transform)) = model.get() {
-                    self.set_mesh_uniforms(program, world, e, &transform)?;
                     for (mesh, shader) in model.0.iter() {
+                        let program = self.get_rendering_program(mesh)?;
+                        program.use_program();
+                        self.set_mesh_uniforms(&program, world, e, &transform)?;
                         self.render_mesh(&program, shader, mesh);
                     }
                 } else {
@@ -495,7 +855,7 @@ impl System for RenderingSystem {
         "Rendering System"
     }
 
-    fn start(&self, world: &mut World) -> Result<(), String> {
+    fn start(&self, world: &mut World) -> Result<(), Error> {
         gl_function!(Enable(gl::DEPTH_TEST));
         gl_function!(DepthFunc(gl::LESS));
         for (_e, shader) in world.query_mut::<&Shader>().with::<Skybox>() {
@@ -510,23 +870,31 @@ impl System for RenderingSystem {
             }
         }
         self.instanced_rendering.setup_world(world)?;
+        self.compile_mesh_programs(world)?;
+        self.validate_mesh_uniforms(world)?;
         gl_function!(ClearStencil(0));
         gl_function!(ClearColor(self.clear_color.x, self.clear_color.y, self.clear_color.z, 1.0));
         Ok(())
     }
 
-    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        self.shader_loader.reload_changed();
         Ok(())
     }
 
-    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
         Ok(())
     }
 
-    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), String> {
+    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
         gl_function!(Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT));
-        self.setup_program_globals(world);
-        self.instanced_rendering.render_world(world);
+        self.compile_mesh_programs(world)?;
+        self.setup_program_globals(world)?;
+        if let Some(ssao) = self.ssao.as_ref() {
+            ssao.render_prepass(self, world)?;
+            ssao.bind();
+        }
+        self.instanced_rendering.render_world(world, &self.mesh_programs)?;
         self.render_non_bordered_objects(world)?;
         if world.query_mut::<&Border>().into_iter().next().is_some() {
             gl_function!(Enable(gl::STENCIL_TEST));
@@ -536,18 +904,6 @@ impl System for RenderingSystem {
         } else {
             gl_function!(Disable(gl::STENCIL_TEST));
         }
-        if world.query_mut::<&Transparent>().into_iter().next().is_some() {
-            gl_function!(Enable(gl::BLEND));
-            gl_function!(BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
-            self.render_transparent_objects(world)?;
-        } else {
-            gl_function!(Disable(gl::BLEND));
-        }
-        self.light_program.use_program();
-        self.draw_lights::<DirectionalLight>(world)?;
-        self.draw_lights::<SpotLight>(world)?;
-        self.draw_lights::<PointLight>(world)?;
-        self.render_skybox(world)?;
-        Ok(())
+        self.render_forward_overlays(world)
     }
 }
@@ -0,0 +1,276 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::BitOr;
+use std::rc::Rc;
+use russimp::texture::TextureType;
+use crate::ecs::components::Mesh;
+use crate::error::Error;
+use crate::program::Program;
+use crate::shader_loader::{ShaderLoader, ShaderType};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ShaderFeatures(u32);
+
+impl ShaderFeatures {
+    pub const HAS_NORMALS: ShaderFeatures = ShaderFeatures(1);
+    pub const HAS_TEXTURE_COORDINATES: ShaderFeatures = ShaderFeatures(1 << 1);
+    pub const HAS_TANGENTS: ShaderFeatures = ShaderFeatures(1 << 2);
+    pub const HAS_BITANGENTS: ShaderFeatures = ShaderFeatures(1 << 3);
+    pub const HAS_NORMAL_MAP: ShaderFeatures = ShaderFeatures(1 << 4);
+    pub const HAS_SPECULAR: ShaderFeatures = ShaderFeatures(1 << 5);
+    pub const HAS_HEIGHT_MAP: ShaderFeatures = ShaderFeatures(1 << 6);
+    pub const HAS_METALNESS: ShaderFeatures = ShaderFeatures(1 << 7);
+    pub const INSTANCED: ShaderFeatures = ShaderFeatures(1 << 8);
+
+    const NAMES: [(ShaderFeatures, &'static str); 9] = [
+        (ShaderFeatures::HAS_NORMALS, "HAS_NORMALS"),
+        (ShaderFeatures::HAS_TEXTURE_COORDINATES, "HAS_TEXTURE_COORDINATES"),
+        (ShaderFeatures::HAS_TANGENTS, "HAS_TANGENTS"),
+        (ShaderFeatures::HAS_BITANGENTS, "HAS_BITANGENTS"),
+        (ShaderFeatures::HAS_NORMAL_MAP, "HAS_NORMAL_MAP"),
+        (ShaderFeatures::HAS_SPECULAR, "HAS_SPECULAR"),
+        (ShaderFeatures::HAS_HEIGHT_MAP, "HAS_HEIGHT_MAP"),
+        (ShaderFeatures::HAS_METALNESS, "HAS_METALNESS"),
+        (ShaderFeatures::INSTANCED, "INSTANCED"),
+    ];
+
+    pub fn empty() -> ShaderFeatures {
+        ShaderFeatures(0)
+    }
+
+    pub fn contains(&self, features: ShaderFeatures) -> bool {
+        self.0 & features.0 == features.0
+    }
+
+    pub fn with(&self, features: ShaderFeatures) -> ShaderFeatures {
+        ShaderFeatures(self.0 | features.0)
+    }
+
+    pub fn without(&self, features: ShaderFeatures) -> ShaderFeatures {
+        ShaderFeatures(self.0 & !features.0)
+    }
+
+    pub fn for_mesh(mesh: &Mesh) -> ShaderFeatures {
+        let mut features = ShaderFeatures::empty();
+        if mesh.normals.is_some() {
+            features = features.with(ShaderFeatures::HAS_NORMALS);
+        }
+        if mesh.texture_coordinates.is_some() {
+            features = features.with(ShaderFeatures::HAS_TEXTURE_COORDINATES);
+        }
+        if mesh.tangents.is_some() {
+            features = features.with(ShaderFeatures::HAS_TANGENTS);
+        }
+        if mesh.bitangents.is_some() {
+            features = features.with(ShaderFeatures::HAS_BITANGENTS);
+        }
+        let has_texture = |texture_type: TextureType| mesh.textures.as_ref()
+            .map(|textures| textures.iter().any(|t| t.texture_type == texture_type))
+            .unwrap_or(false);
+        let can_map_normals = features.contains(ShaderFeatures::HAS_NORMALS | ShaderFeatures::HAS_TEXTURE_COORDINATES);
+        if can_map_normals && has_texture(TextureType::Normals) {
+            features = features.with(ShaderFeatures::HAS_NORMAL_MAP);
+        }
+        if has_texture(TextureType::Specular) {
+            features = features.with(ShaderFeatures::HAS_SPECULAR);
+        }
+        if has_texture(TextureType::Height) {
+            features = features.with(ShaderFeatures::HAS_HEIGHT_MAP);
+        }
+        if has_texture(TextureType::Metalness) {
+            features = features.with(ShaderFeatures::HAS_METALNESS);
+        }
+        features
+    }
+
+    pub fn for_instanced_mesh(mesh: &Mesh) -> ShaderFeatures {
+        ShaderFeatures::for_mesh(mesh).with(ShaderFeatures::INSTANCED)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        ShaderFeatures::NAMES.iter()
+            .filter(|(feature, _)| self.contains(*feature))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    fn attribute_locations(&self) -> Vec<(&'static str, usize)> {
+        let mut locations = vec![];
+        let mut location = 1;
+        for (feature, name) in vec![
+            (ShaderFeatures::HAS_NORMALS, "NORMAL_LOCATION"),
+            (ShaderFeatures::HAS_TEXTURE_COORDINATES, "TEXTURE_COORDINATES_LOCATION"),
+            (ShaderFeatures::HAS_TANGENTS, "TANGENT_LOCATION"),
+            (ShaderFeatures::HAS_BITANGENTS, "BITANGENT_LOCATION"),
+        ] {
+            if self.contains(feature) {
+                locations.push((name, location));
+                location += 1;
+            }
+        }
+        locations.push(("MODEL_LOCATION", location));
+        locations
+    }
+
+    pub fn apply(&self, shader_loader: &ShaderLoader) -> ShaderLoader {
+        let mut shader_loader = shader_loader.clone().define("PERMUTATION", 1);
+        for name in self.names() {
+            shader_loader = shader_loader.define(name, 1);
+        }
+        for (name, location) in self.attribute_locations() {
+            shader_loader = shader_loader.define(name, location);
+        }
+        shader_loader
+    }
+}
+
+impl BitOr for ShaderFeatures {
+    type Output = ShaderFeatures;
+
+    fn bitor(self, rhs: ShaderFeatures) -> ShaderFeatures {
+        self.with(rhs)
+    }
+}
+
+pub struct ShaderPermutations {
+    shader_loader: ShaderLoader,
+    stages: Box<dyn Fn(ShaderFeatures) -> Vec<(ShaderType, &'static str)>>,
+    setup: Rc<dyn Fn(ShaderFeatures, &Program)>,
+    variants: RefCell<HashMap<ShaderFeatures, Rc<Program>>>,
+}
+
+impl ShaderPermutations {
+    pub fn new<
+        S: Fn(ShaderFeatures) -> Vec<(ShaderType, &'static str)> + 'static,
+        F: Fn(ShaderFeatures, &Program) + 'static,
+    >(shader_loader: &ShaderLoader, stages: S, setup: F) -> ShaderPermutations {
+        ShaderPermutations {
+            shader_loader: shader_loader.clone(),
+            stages: Box::new(stages),
+            setup: Rc::new(setup),
+            variants: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_shader_loader(&mut self, shader_loader: &ShaderLoader) {
+        self.shader_loader = shader_loader.clone();
+        self.variants.borrow_mut().clear();
+    }
+
+    pub fn is_compiled(&self, features: ShaderFeatures) -> bool {
+        self.variants.borrow().contains_key(&features)
+    }
+
+    pub fn program(&self, features: ShaderFeatures) -> Result<Rc<Program>, Error> {
+        if let Some(program) = self.variants.borrow().get(&features) {
+            return Ok(program.clone());
+        }
+        let program = Rc::new(features.apply(&self.shader_loader).program((self.stages)(features))?);
+        let setup = self.setup.clone();
+        program.setup(move |program| setup(features, program));
+        self.variants.borrow_mut().insert(features, program.clone());
+        Ok(program)
+    }
+
+    pub fn programs(&self) -> Vec<Rc<Program>> {
+        self.variants.borrow().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Vector2, Vector3};
+    use crate::ecs::components::TextureInfo;
+    use super::*;
+
+    fn mesh(textures: &[TextureType]) -> Mesh {
+        Mesh {
+            vertices: vec![Vector3::zeros(); 3],
+            normals: None,
+            indices: None,
+            tangents: None,
+            bitangents: None,
+            textures: Some(textures.iter().map(|texture_type| TextureInfo {
+                id: 0,
+                texture_type: *texture_type,
+                path: String::new(),
+            }).collect()),
+            texture_coordinates: None,
+            shininess: None,
+        }
+    }
+
+    fn with_attributes(mut mesh: Mesh) -> Mesh {
+        mesh.normals = Some(vec![Vector3::zeros(); 3]);
+        mesh.texture_coordinates = Some(vec![Vector2::zeros(); 3]);
+        mesh.tangents = Some(vec![Vector3::zeros(); 3]);
+        mesh.bitangents = Some(vec![Vector3::zeros(); 3]);
+        mesh
+    }
+
+    fn defines(features: ShaderFeatures) -> Vec<(String, String)> {
+        features.apply(&ShaderLoader::from_directory("")).defines().to_vec()
+    }
+
+    fn feature_defines(features: ShaderFeatures) -> Vec<String> {
+        defines(features).into_iter()
+            .filter(|(name, _)| name != "PERMUTATION" && !name.ends_with("_LOCATION"))
+            .map(|(name, value)| {
+                assert_eq!(value, "1");
+                name
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_mesh_feature_defines_its_flag() {
+        let mut normals = mesh(&[]);
+        normals.normals = Some(vec![Vector3::zeros(); 3]);
+        let mut texture_coordinates = mesh(&[]);
+        texture_coordinates.texture_coordinates = Some(vec![Vector2::zeros(); 3]);
+        let mut tangents = mesh(&[]);
+        tangents.tangents = Some(vec![Vector3::zeros(); 3]);
+        let mut bitangents = mesh(&[]);
+        bitangents.bitangents = Some(vec![Vector3::zeros(); 3]);
+        let cases = vec![
+            (ShaderFeatures::for_mesh(&mesh(&[])), vec![]),
+            (ShaderFeatures::for_mesh(&normals), vec!["HAS_NORMALS"]),
+            (ShaderFeatures::for_mesh(&texture_coordinates), vec!["HAS_TEXTURE_COORDINATES"]),
+            (ShaderFeatures::for_mesh(&tangents), vec!["HAS_TANGENTS"]),
+            (ShaderFeatures::for_mesh(&bitangents), vec!["HAS_BITANGENTS"]),
+            (ShaderFeatures::for_mesh(&mesh(&[TextureType::Normals])), vec![]),
+            (ShaderFeatures::for_mesh(&mesh(&[TextureType::Specular])), vec!["HAS_SPECULAR"]),
+            (ShaderFeatures::for_mesh(&mesh(&[TextureType::Height])), vec!["HAS_HEIGHT_MAP"]),
+            (ShaderFeatures::for_mesh(&mesh(&[TextureType::Metalness])), vec!["HAS_METALNESS"]),
+            (ShaderFeatures::for_instanced_mesh(&mesh(&[])), vec!["INSTANCED"]),
+            (
+                ShaderFeatures::for_mesh(&with_attributes(mesh(&[TextureType::Normals]))),
+                vec!["HAS_NORMALS", "HAS_TEXTURE_COORDINATES", "HAS_TANGENTS", "HAS_BITANGENTS", "HAS_NORMAL_MAP"],
+            ),
+        ];
+        for (features, expected) in cases {
+            assert_eq!(feature_defines(features), expected, "{:?}", features);
+        }
+    }
+
+    #[test]
+    fn attribute_locations_follow_present_attributes() {
+        let location = |features: ShaderFeatures, name: &str| defines(features).into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value);
+        let plain = ShaderFeatures::for_mesh(&mesh(&[]));
+        assert_eq!(location(plain, "PERMUTATION"), Some("1".to_string()));
+        assert_eq!(location(plain, "NORMAL_LOCATION"), None);
+        assert_eq!(location(plain, "MODEL_LOCATION"), Some("1".to_string()));
+        let full = ShaderFeatures::for_mesh(&with_attributes(mesh(&[])));
+        for (name, expected) in [
+            ("NORMAL_LOCATION", "1"),
+            ("TEXTURE_COORDINATES_LOCATION", "2"),
+            ("TANGENT_LOCATION", "3"),
+            ("BITANGENT_LOCATION", "4"),
+            ("MODEL_LOCATION", "5"),
+        ] {
+            assert_eq!(location(full, name), Some(expected.to_string()));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use hecs::{Entity, World};
+use log::warn;
+use nalgebra::{Matrix4, Perspective3, Point3, Vector3};
+use crate::ecs::components::{CastsShadows, Mesh, Model, ShadowCaster, SkipRendering, Transform};
+use crate::ecs::systems::rendering::shadows::{render_shadow_casters, SHADOW_NEAR_PLANE, with_shadow_frame_buffer};
+use crate::error::Error;
+use crate::frame_buffer::FrameBuffer;
+use crate::gl_function;
+use crate::light::PointLight;
+use crate::program::Program;
+use crate::shader_loader::{ShaderLoader, ShaderType};
+use crate::std140;
+
+static DEPTH_VERTEX_SHADER: &'static str = "point_shadow_depth_vertex.glsl";
+static DEPTH_FRAGMENT_SHADER: &'static str = "point_shadow_depth_fragment.glsl";
+const POINT_SHADOW_MAPS_TEXTURE_UNIT: u32 = 12;
+pub const MAX_POINT_SHADOWS: usize = 8;
+
+std140! {
+    #[derive(Clone, Debug, Default)]
+    pub struct PointShadowData {
+        pub far_plane: f32,
+        pub bias: f32,
+        pub pcf_kernel: i32,
+    }
+}
+
+pub struct PointShadowRequest {
+    pub entity: Entity,
+    pub position: Vector3<f32>,
+    pub far_plane: f32,
+    pub settings: CastsShadows,
+}
+
+pub fn push_point_shadow(
+    requests: &mut Vec<PointShadowRequest>, entity: Entity, light: &PointLight, settings: &CastsShadows,
+) -> i32 {
+    if requests.len() >= MAX_POINT_SHADOWS {
+        warn!("Only {} point light shadows are supported, ignoring the rest", MAX_POINT_SHADOWS);
+        return -1;
+    }
+    requests.push(PointShadowRequest {
+        entity,
+        position: light.position,
+        far_plane: light.radius().min(settings.distance).max(SHADOW_NEAR_PLANE * 2.0),
+        settings: settings.clone(),
+    });
+    requests.len() as i32 - 1
+}
+
+fn face_views(position: Vector3<f32>) -> Vec<Matrix4<f32>> {
+    vec![
+        (Vector3::new(1f32, 0f32, 0f32), Vector3::new(0f32, -1f32, 0f32)),
+        (Vector3::new(-1f32, 0f32, 0f32), Vector3::new(0f32, -1f32, 0f32)),
+        (Vector3::new(0f32, 1f32, 0f32), Vector3::new(0f32, 0f32, 1f32)),
+        (Vector3::new(0f32, -1f32, 0f32), Vector3::new(0f32, 0f32, -1f32)),
+        (Vector3::new(0f32, 0f32, 1f32), Vector3::new(0f32, -1f32, 0f32)),
+        (Vector3::new(0f32, 0f32, -1f32), Vector3::new(0f32, -1f32, 0f32)),
+    ].into_iter()
+        .map(|(direction, up)| Matrix4::look_at_rh(&Point3::from(position), &Point3::from(position + direction), &up))
+        .collect()
+}
+
+fn mesh_radius(mesh: &Mesh) -> f32 {
+    mesh.vertices.iter().map(|v| v.norm()).fold(0.0, f32::max)
+}
+
+#[derive(PartialEq)]
+struct PointShadowSlot {
+    entity: Entity,
+    position: Vector3<f32>,
+    far_plane: f32,
+    resolution: u32,
+    casters: Vec<(Entity, Matrix4<f32>)>,
+}
+
+pub struct PointShadowMaps {
+    caster_radii: RefCell<HashMap<Entity, f32>>,
+    depth_program: Program,
+    frame_buffer: RefCell<FrameBuffer>,
+    size: Cell<(u32, usize)>,
+    slots: RefCell<Vec<Option<PointShadowSlot>>>,
+}
+
+impl PointShadowMaps {
+    pub fn new(shader_loader: &ShaderLoader) -> Result<PointShadowMaps, Error> {
+        let depth_program = shader_loader.program(vec![
+            (ShaderType::Vertex, DEPTH_VERTEX_SHADER),
+            (ShaderType::Fragment, DEPTH_FRAGMENT_SHADER),
+        ])?;
+        Ok(PointShadowMaps {
+            caster_radii: RefCell::new(HashMap::new()),
+            depth_program,
+            frame_buffer: RefCell::new(FrameBuffer::depth_cubemap_array(1, 1)?),
+            size: Cell::new((1, 1)),
+            slots: RefCell::new(vec![]),
+        })
+    }
+
+    fn resize(&self, requests: &[PointShadowRequest]) -> Result<(), Error> {
+        let resolution = requests.iter().map(|r| r.settings.resolution).max().unwrap_or(1);
+        let size = (resolution, requests.len().max(1));
+        if size != self.size.get() {
+            self.frame_buffer.replace(FrameBuffer::depth_cubemap_array(size.0, size.1)?);
+            self.size.set(size);
+            self.slots.borrow_mut().clear();
+        }
+        Ok(())
+    }
+
+    fn caster_radius(&self, entity: Entity, world: &World) -> f32 {
+        *self.caster_radii.borrow_mut().entry(entity).or_insert_with(|| {
+            if let Ok(mesh) = world.get::<Mesh>(entity) {
+                mesh_radius(&mesh)
+            } else if let Ok(model) = world.get::<Model>(entity) {
+                model.0.iter().map(|(mesh, _)| mesh_radius(mesh)).fold(0.0, f32::max)
+            } else {
+                0.0
+            }
+        })
+    }
+
+    fn nearby_casters(&self, world: &World, request: &PointShadowRequest) -> Vec<(Entity, Matrix4<f32>)> {
+        world.query::<&Transform>().with::<ShadowCaster>().without::<SkipRendering>().iter()
+            .filter(|(e, transform)| {
+                let scale = transform.scale.abs().max();
+                let radius = self.caster_radius(*e, world) * scale;
+                (transform.position - request.position).norm() <= request.far_plane + radius
+            })
+            .map(|(e, transform)| (e, transform.get_model_matrix()))
+            .collect()
+    }
+
+    pub fn update(&self, world: &World, requests: &[PointShadowRequest]) -> Result<Vec<PointShadowData>, Error> {
+        self.resize(requests)?;
+        let (size, _) = self.size.get();
+        let mut slots = self.slots.borrow_mut();
+        slots.resize_with(requests.len(), || None);
+        let dirty = requests.iter().enumerate().filter_map(|(index, request)| {
+            let slot = PointShadowSlot {
+                entity: request.entity,
+                position: request.position,
+                far_plane: request.far_plane,
+                resolution: request.settings.resolution,
+                casters: self.nearby_casters(world, request),
+            };
+            if slots[index].as_ref() == Some(&slot) {
+                None
+            } else {
+                slots[index] = Some(slot);
+                Some(index)
+            }
+        }).collect::<Vec<_>>();
+
+        if !dirty.is_empty() {
+            let frame_buffer = self.frame_buffer.borrow();
+            with_shadow_frame_buffer(&frame_buffer, || {
+                self.depth_program.use_program();
+                gl_function!(Viewport(0, 0, size as _, size as _));
+                for index in dirty {
+                    let request = &requests[index];
+                    let projection = Perspective3::new(1.0, 90f32.to_radians(), SHADOW_NEAR_PLANE, request.far_plane);
+                    self.depth_program.set_uniform_v3("lightPos", request.position);
+                    self.depth_program.set_uniform_f1("far_plane", request.far_plane);
+                    for (face, view) in face_views(request.position).into_iter().enumerate() {
+                        frame_buffer.attach_depth_layer(index * 6 + face);
+                        gl_function!(Clear(gl::DEPTH_BUFFER_BIT));
+                        self.depth_program.set_uniform_matrix4("space_matrix", &(projection.to_homogeneous() * view));
+                        render_shadow_casters(&self.depth_program, world);
+                    }
+                }
+            });
+        }
+
+        let mut point_shadows = requests.iter().map(|request| PointShadowData {
+            far_plane: request.far_plane,
+            bias: request.settings.bias,
+            pcf_kernel: request.settings.pcf_kernel as i32,
+        }).collect::<Vec<_>>();
+        point_shadows.resize_with(MAX_POINT_SHADOWS, PointShadowData::default);
+        Ok(point_shadows)
+    }
+
+    pub fn bind(&self) {
+        self.frame_buffer.borrow().texture.bind(gl::TEXTURE0 + POINT_SHADOW_MAPS_TEXTURE_UNIT);
+    }
+
+    pub fn set_program(&self, program: &Program) {
+        program.set_uniform_i1("point_shadow_maps", POINT_SHADOW_MAPS_TEXTURE_UNIT as _);
+    }
+}
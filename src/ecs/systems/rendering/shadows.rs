@@ -0,0 +1,247 @@
+use std::cell::{Cell, RefCell};
+use hecs::World;
+use log::warn;
+use nalgebra::{Matrix4, Orthographic3, Perspective3, Point3, Vector3};
+use crate::camera::Camera;
+use crate::ecs::components::{CastsShadows, Mesh, Model, Shader, ShadowCaster, SkipRendering, Transform};
+use crate::ecs::systems::rendering::point_shadows::{MAX_POINT_SHADOWS, PointShadowData};
+use crate::ecs::systems::rendering::RenderingSystem;
+use crate::error::Error;
+use crate::frame_buffer::FrameBuffer;
+use crate::gl_function;
+use crate::light::{DirectionalLight, SpotLight};
+use crate::program::Program;
+use crate::shader_loader::{ShaderLoader, ShaderType};
+use crate::std140;
+use crate::uniform_block::UniformBlock;
+
+static DEPTH_VERTEX_SHADER: &'static str = "21.1-depth_calculation_vertex.glsl";
+static DEPTH_FRAGMENT_SHADER: &'static str = "21.1-depth_calculation_fragment.glsl";
+const SHADOW_CASCADES_DEBUG_VARIABLE: &'static str = "LEARNOPENGL_SHADOW_CASCADES_DEBUG";
+const SHADOW_MAPS_TEXTURE_UNIT: u32 = 13;
+pub(crate) const SHADOW_NEAR_PLANE: f32 = 0.1;
+pub const SHADOWS_BINDING_POINT: usize = 2;
+pub const MAX_SHADOWS: usize = 16;
+
+std140! {
+    #[derive(Clone, Debug, Default)]
+    pub struct ShadowData {
+        pub light_space: Matrix4<f32>,
+        pub bias: f32,
+        pub pcf_kernel: i32,
+        pub scale: f32,
+        pub split_depth: f32,
+        pub cascades: i32,
+    }
+}
+
+std140! {
+    pub struct Shadows {
+        pub view: Matrix4<f32>,
+        pub shadows: Vec<ShadowData>,
+        pub point_shadows: Vec<PointShadowData>,
+    }
+}
+
+pub struct ShadowRequest {
+    pub light_space: Matrix4<f32>,
+    pub settings: CastsShadows,
+    pub split_depth: f32,
+    pub cascades: usize,
+}
+
+pub(crate) fn render_shadow_casters(program: &Program, world: &World) {
+    for (_e, (mesh, shader, transform)) in world.query::<(&Mesh, &Shader, &Transform)>().with::<ShadowCaster>().without::<SkipRendering>().iter() {
+        program.set_uniform_matrix4("model", &transform.get_model_matrix());
+        RenderingSystem::draw_mesh(mesh, shader);
+    }
+    for (_e, (model, transform)) in world.query::<(&Model, &Transform)>().with::<ShadowCaster>().without::<SkipRendering>().iter() {
+        program.set_uniform_matrix4("model", &transform.get_model_matrix());
+        for (mesh, shader) in model.0.iter() {
+            RenderingSystem::draw_mesh(mesh, shader);
+        }
+    }
+}
+
+pub(crate) fn with_shadow_frame_buffer<F: FnOnce()>(frame_buffer: &FrameBuffer, render: F) {
+    let mut viewport = [0; 4];
+    let mut previous_frame_buffer = 0;
+    gl_function!(GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()));
+    gl_function!(GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_frame_buffer));
+    frame_buffer.bind();
+    render();
+    gl_function!(BindFramebuffer(gl::FRAMEBUFFER, previous_frame_buffer as _));
+    gl_function!(Viewport(viewport[0], viewport[1], viewport[2], viewport[3]));
+}
+
+fn light_view(position: Vector3<f32>, direction: Vector3<f32>) -> Matrix4<f32> {
+    let up = if direction.y.abs() > 0.99 { Vector3::z() } else { Vector3::y() };
+    Matrix4::look_at_rh(&Point3::from(position), &Point3::from(position + direction), &up)
+}
+
+pub fn cascade_splits(settings: &CastsShadows, camera: &Camera) -> Vec<f32> {
+    let near = camera.near_plane();
+    let far = settings.distance.min(camera.far_plane()).max(near);
+    let cascades = settings.cascades.max(1);
+    (0..=cascades).map(|i| {
+        let ratio = i as f32 / cascades as f32;
+        let logarithmic = near * (far / near).powf(ratio);
+        let uniform = near + (far - near) * ratio;
+        settings.split_lambda * logarithmic + (1.0 - settings.split_lambda) * uniform
+    }).collect()
+}
+
+fn cascade_light_space(direction: Vector3<f32>, settings: &CastsShadows, camera: &Camera, near: f32, far: f32) -> Matrix4<f32> {
+    let projection = Perspective3::new(camera.aspect_ratio(), camera.fov().to_radians(), near, far);
+    let inverse = (projection.to_homogeneous() * camera.look_at_matrix())
+        .try_inverse()
+        .unwrap_or_else(Matrix4::identity);
+    let mut corners = vec![];
+    for x in [-1.0, 1.0] {
+        for y in [-1.0, 1.0] {
+            for z in [-1.0, 1.0] {
+                corners.push(inverse.transform_point(&Point3::new(x, y, z)).coords);
+            }
+        }
+    }
+    let center = corners.iter().sum::<Vector3<f32>>() / corners.len() as f32;
+    let radius = corners.iter().map(|c| (c - center).norm()).fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let view = light_view(Vector3::zeros(), direction);
+    let light_center = view.transform_point(&Point3::from(center));
+    let texel_size = 2.0 * radius / settings.resolution as f32;
+    let x = (light_center.x / texel_size).floor() * texel_size;
+    let y = (light_center.y / texel_size).floor() * texel_size;
+    let projection = Orthographic3::new(
+        x - radius, x + radius, y - radius, y + radius,
+        -light_center.z - radius - settings.distance, -light_center.z + radius,
+    );
+    projection.to_homogeneous() * view
+}
+
+fn push_requests(requests: &mut Vec<ShadowRequest>, new_requests: Vec<ShadowRequest>) -> i32 {
+    if requests.len() + new_requests.len() > MAX_SHADOWS {
+        warn!("Only {} shadow maps are supported, ignoring the rest", MAX_SHADOWS);
+        return -1;
+    }
+    let index = requests.len() as i32;
+    requests.extend(new_requests);
+    index
+}
+
+pub fn push_directional_shadow(
+    requests: &mut Vec<ShadowRequest>, light: &DirectionalLight, settings: &CastsShadows, camera: &Camera,
+) -> i32 {
+    let splits = cascade_splits(settings, camera);
+    let cascades = splits.len() - 1;
+    let new_requests = splits.windows(2).map(|split| ShadowRequest {
+        light_space: cascade_light_space(light.direction().into_inner(), settings, camera, split[0], split[1]),
+        settings: settings.clone(),
+        split_depth: split[1],
+        cascades,
+    }).collect();
+    push_requests(requests, new_requests)
+}
+
+pub fn push_spot_shadow(
+    requests: &mut Vec<ShadowRequest>, light: &SpotLight, settings: &CastsShadows, camera: &Camera,
+) -> i32 {
+    let fov = 2.0 * light.outer_cut_off().clamp(-1.0, 1.0).acos();
+    let far = light.radius().min(camera.far_plane()).max(SHADOW_NEAR_PLANE * 2.0);
+    let projection = Perspective3::new(1.0, fov, SHADOW_NEAR_PLANE, far);
+    let light_space = projection.to_homogeneous() * light_view(light.position(), light.direction().into_inner());
+    push_requests(requests, vec![ShadowRequest {
+        light_space,
+        settings: settings.clone(),
+        split_depth: far,
+        cascades: 1,
+    }])
+}
+
+pub struct ShadowMaps {
+    block: UniformBlock<Shadows>,
+    depth_program: Program,
+    debug: Cell<bool>,
+    frame_buffer: RefCell<FrameBuffer>,
+    size: Cell<(u32, usize)>,
+}
+
+impl ShadowMaps {
+    pub fn new(shader_loader: &ShaderLoader) -> Result<ShadowMaps, Error> {
+        let depth_program = shader_loader.program(vec![
+            (ShaderType::Vertex, DEPTH_VERTEX_SHADER),
+            (ShaderType::Fragment, DEPTH_FRAGMENT_SHADER),
+        ])?;
+        let block = UniformBlock::new(SHADOWS_BINDING_POINT, &Shadows {
+            view: Matrix4::identity(),
+            shadows: vec![ShadowData::default(); MAX_SHADOWS],
+            point_shadows: vec![PointShadowData::default(); MAX_POINT_SHADOWS],
+        });
+        Ok(ShadowMaps {
+            block,
+            depth_program,
+            debug: Cell::new(std::env::var(SHADOW_CASCADES_DEBUG_VARIABLE).is_ok()),
+            frame_buffer: RefCell::new(FrameBuffer::depth_array(1, 1, 1)?),
+            size: Cell::new((1, 1)),
+        })
+    }
+
+    pub fn debug(&self) -> bool {
+        self.debug.get()
+    }
+
+    pub fn set_debug(&self, debug: bool) {
+        self.debug.set(debug);
+    }
+
+    fn resize(&self, requests: &[ShadowRequest]) -> Result<(), Error> {
+        let resolution = requests.iter().map(|r| r.settings.resolution).max().unwrap_or(1);
+        let size = (resolution, requests.len().max(1));
+        if size != self.size.get() {
+            self.frame_buffer.replace(FrameBuffer::depth_array(size.0, size.0, size.1)?);
+            self.size.set(size);
+        }
+        Ok(())
+    }
+
+    pub fn update(&self, world: &World, camera: &Camera, requests: &[ShadowRequest], point_shadows: Vec<PointShadowData>) -> Result<(), Error> {
+        self.resize(requests)?;
+        let (size, _) = self.size.get();
+        let mut shadows = requests.iter().map(|request| ShadowData {
+            light_space: request.light_space,
+            bias: request.settings.bias,
+            pcf_kernel: request.settings.pcf_kernel.max(1) as i32,
+            scale: request.settings.resolution as f32 / size as f32,
+            split_depth: request.split_depth,
+            cascades: request.cascades as i32,
+        }).collect::<Vec<_>>();
+        shadows.resize_with(MAX_SHADOWS, ShadowData::default);
+        self.block.set(&Shadows { view: camera.look_at_matrix(), shadows, point_shadows })?;
+        if requests.is_empty() {
+            return Ok(());
+        }
+
+        let frame_buffer = self.frame_buffer.borrow();
+        with_shadow_frame_buffer(&frame_buffer, || {
+            self.depth_program.use_program();
+            for (layer, request) in requests.iter().enumerate() {
+                frame_buffer.attach_depth_layer(layer);
+                gl_function!(Viewport(0, 0, request.settings.resolution as _, request.settings.resolution as _));
+                gl_function!(Clear(gl::DEPTH_BUFFER_BIT));
+                self.depth_program.set_uniform_matrix4("space_matrix", &request.light_space);
+                render_shadow_casters(&self.depth_program, world);
+            }
+        });
+        Ok(())
+    }
+
+    pub fn bind(&self) {
+        self.frame_buffer.borrow().texture.bind(gl::TEXTURE0 + SHADOW_MAPS_TEXTURE_UNIT);
+    }
+
+    pub fn set_program(&self, program: &Program) {
+        program.set_uniform_i1("shadow_maps", SHADOW_MAPS_TEXTURE_UNIT as _);
+        program.set_uniform("shadow_cascades_debug", &self.debug.get());
+    }
+}
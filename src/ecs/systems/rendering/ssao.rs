@@ -0,0 +1,315 @@
+use std::cell::{Cell, RefCell};
+use hecs::World;
+use nalgebra::Vector3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::ecs::components::{Mesh, Model, Shader, SkipRendering, Transform, Transparent};
+use crate::ecs::systems::rendering::deferred::{geometry_stages, setup_geometry_program};
+use crate::ecs::systems::rendering::permutations::{ShaderFeatures, ShaderPermutations};
+use crate::ecs::systems::rendering::{MATRICES_BINDING_POINT, RenderingSystem};
+use crate::error::Error;
+use crate::frame_buffer::FrameBuffer;
+use crate::gl_function;
+use crate::multiple_render_target::MultipleRenderTarget;
+use crate::program::Program;
+use crate::quad::Quad;
+use crate::shader_loader::{ShaderLoader, ShaderType};
+use crate::texture::{Texture, TextureFormat, TextureType};
+
+static VERTEX_SHADER: &'static str = "15.1-postprocessing_vertex.glsl";
+static SSAO_FRAGMENT_SHADER: &'static str = "ssao_fragment.glsl";
+static BLUR_FRAGMENT_SHADER: &'static str = "ssao_blur_fragment.glsl";
+const SSAO_VARIABLE: &'static str = "LEARNOPENGL_SSAO";
+const AMBIENT_OCCLUSION_TEXTURE_UNIT: u32 = 8;
+const NOISE_SIZE: u32 = 4;
+const KERNEL_SEED: u64 = 0x55a0;
+const NOISE_SEED: u64 = 0x4e01;
+pub const MAX_SSAO_SAMPLES: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct SsaoSettings {
+    pub samples: usize,
+    pub radius: f32,
+    pub bias: f32,
+    pub power: f32,
+    pub half_resolution: bool,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> SsaoSettings {
+        SsaoSettings {
+            samples: 32,
+            radius: 0.5,
+            bias: 0.025,
+            power: 1.0,
+            half_resolution: false,
+        }
+    }
+}
+
+impl SsaoSettings {
+    pub fn from_env() -> Option<SsaoSettings> {
+        let value = std::env::var(SSAO_VARIABLE).ok()?;
+        Some(SsaoSettings {
+            half_resolution: value == "half",
+            ..SsaoSettings::default()
+        })
+    }
+}
+
+fn kernel(samples: usize) -> Vec<f32> {
+    let mut rng = StdRng::seed_from_u64(KERNEL_SEED);
+    let mut kernel = vec![];
+    for i in 0..samples {
+        let sample = Vector3::new(
+            rng.gen_range(-1f32..1f32), rng.gen_range(-1f32..1f32), rng.gen_range(0f32..1f32)
+        ).normalize() * rng.gen_range(0f32..1f32);
+        let scale = i as f32 / samples as f32;
+        let scale = 0.1f32 + scale * scale * (1f32 - 0.1f32);
+        kernel.extend_from_slice(&[sample.x * scale, sample.y * scale, sample.z * scale, 0f32]);
+    }
+    kernel
+}
+
+fn noise() -> Vec<f32> {
+    let mut rng = StdRng::seed_from_u64(NOISE_SEED);
+    (0..NOISE_SIZE * NOISE_SIZE)
+        .flat_map(|_| [rng.gen_range(-1f32..1f32), rng.gen_range(-1f32..1f32), 0f32, 0f32])
+        .collect()
+}
+
+fn data_texture(width: u32, height: u32, data: &[f32]) -> Texture {
+    let texture = Texture::new(TextureType::Texture2D);
+    texture.just_bind();
+    texture.set_image_2d_with_format(width, height, data, TextureFormat::FloatingPoint);
+    texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST);
+    texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST);
+    texture.set_parameter(gl::TEXTURE_WRAP_S, gl::REPEAT);
+    texture.set_parameter(gl::TEXTURE_WRAP_T, gl::REPEAT);
+    texture.unbind();
+    texture
+}
+
+fn occlusion_frame_buffer(width: u32, height: u32) -> Result<FrameBuffer, Error> {
+    let frame_buffer = FrameBuffer::intermediate_with_format(width, height, TextureFormat::Grey)?;
+    frame_buffer.texture.just_bind();
+    frame_buffer.texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+    frame_buffer.texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+    frame_buffer.texture.set_parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+    frame_buffer.texture.set_parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+    frame_buffer.texture.unbind();
+    Ok(frame_buffer)
+}
+
+fn prepass(width: u32, height: u32) -> Result<MultipleRenderTarget, Error> {
+    let prepass = MultipleRenderTarget::new_with_formats(width, height, &[
+        TextureFormat::HighPrecisionFloatingPoint,
+        TextureFormat::FloatingPoint,
+    ])?;
+    prepass.bind();
+    prepass.set_draw_buffers();
+    MultipleRenderTarget::unbind();
+    Ok(prepass)
+}
+
+pub struct Ssao {
+    blur_program: Program,
+    blurred: RefCell<FrameBuffer>,
+    enabled: Cell<bool>,
+    kernel: RefCell<Texture>,
+    kernel_samples: Cell<usize>,
+    noise: Texture,
+    occlusion: RefCell<FrameBuffer>,
+    prepass: RefCell<MultipleRenderTarget>,
+    prepass_programs: ShaderPermutations,
+    quad: Quad,
+    settings: RefCell<SsaoSettings>,
+    size: Cell<(u32, u32, bool)>,
+    ssao_program: Program,
+}
+
+impl Ssao {
+    pub fn new(shader_loader: &ShaderLoader, settings: SsaoSettings) -> Result<Ssao, Error> {
+        let ssao_program = shader_loader.program(vec![
+            (ShaderType::Vertex, VERTEX_SHADER),
+            (ShaderType::Fragment, SSAO_FRAGMENT_SHADER),
+        ])?;
+        let blur_program = shader_loader.program(vec![
+            (ShaderType::Vertex, VERTEX_SHADER),
+            (ShaderType::Fragment, BLUR_FRAGMENT_SHADER),
+        ])?;
+        for program in [&ssao_program, &blur_program] {
+            program.bind_uniform_block("Matrices", MATRICES_BINDING_POINT);
+        }
+        let prepass_programs = ShaderPermutations::new(
+            &shader_loader.clone().define("SSAO_PREPASS", 1),
+            geometry_stages,
+            setup_geometry_program,
+        );
+        let samples = settings.samples.clamp(1, MAX_SSAO_SAMPLES);
+        Ok(Ssao {
+            blur_program,
+            blurred: RefCell::new(occlusion_frame_buffer(1, 1)?),
+            enabled: Cell::new(true),
+            kernel: RefCell::new(data_texture(samples as _, 1, &kernel(samples))),
+            kernel_samples: Cell::new(samples),
+            noise: data_texture(NOISE_SIZE, NOISE_SIZE, &noise()),
+            occlusion: RefCell::new(occlusion_frame_buffer(1, 1)?),
+            prepass: RefCell::new(prepass(1, 1)?),
+            prepass_programs,
+            quad: Quad::new(),
+            settings: RefCell::new(settings),
+            size: Cell::new((1, 1, false)),
+            ssao_program,
+        })
+    }
+
+    pub fn settings(&self) -> SsaoSettings {
+        self.settings.borrow().clone()
+    }
+
+    pub fn set_settings(&self, settings: SsaoSettings) {
+        self.settings.replace(settings);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+    }
+
+    pub fn ambient_occlusion(&self) -> std::cell::Ref<'_, FrameBuffer> {
+        self.blurred.borrow()
+    }
+
+    fn resize(&self, width: u32, height: u32, half_resolution: bool) -> Result<(), Error> {
+        let size = (width, height, half_resolution);
+        if self.size.get() == size {
+            return Ok(());
+        }
+        let (occlusion_width, occlusion_height) = if half_resolution {
+            ((width / 2).max(1), (height / 2).max(1))
+        } else {
+            (width, height)
+        };
+        self.occlusion.replace(occlusion_frame_buffer(occlusion_width, occlusion_height)?);
+        self.blurred.replace(occlusion_frame_buffer(width, height)?);
+        self.size.set(size);
+        Ok(())
+    }
+
+    fn update_kernel(&self, samples: usize) -> usize {
+        let samples = samples.clamp(1, MAX_SSAO_SAMPLES);
+        if self.kernel_samples.get() != samples {
+            self.kernel.replace(data_texture(samples as _, 1, &kernel(samples)));
+            self.kernel_samples.set(samples);
+        }
+        samples
+    }
+
+    pub(crate) fn render_prepass(&self, rendering: &RenderingSystem, world: &World) -> Result<(), Error> {
+        if !self.enabled.get() {
+            return Ok(());
+        }
+        let mut viewport = [0; 4];
+        gl_function!(GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()));
+        let (width, height) = (viewport[2].max(1) as u32, viewport[3].max(1) as u32);
+        if self.prepass.borrow().width() != width || self.prepass.borrow().height() != height {
+            self.prepass.replace(prepass(width, height)?);
+        }
+        for (_e, mesh) in world.query::<&Mesh>().iter() {
+            self.prepass_programs.program(ShaderFeatures::for_mesh(mesh))?;
+        }
+        for (_e, model) in world.query::<&Model>().iter() {
+            for (mesh, _) in model.0.iter() {
+                self.prepass_programs.program(ShaderFeatures::for_mesh(mesh))?;
+            }
+        }
+        let view_position = (*rendering.main_camera).borrow().position();
+        for program in self.prepass_programs.programs() {
+            program.use_program();
+            program.set_uniform_v3("viewPos", view_position);
+        }
+        let mut previous_frame_buffer = 0;
+        gl_function!(GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_frame_buffer));
+        self.prepass.borrow().bind();
+        gl_function!(Viewport(0, 0, width as _, height as _));
+        gl_function!(ClearColor(0.0, 0.0, 0.0, 0.0));
+        gl_function!(Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
+        let result = rendering.render_objects(
+            &self.prepass_programs,
+            world.query::<(&Mesh, &Shader, &Transform)>().without::<Transparent>().without::<SkipRendering>().iter(),
+            world.query::<(&Model, &Transform)>().without::<Transparent>().without::<SkipRendering>().iter(),
+            world,
+        );
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, previous_frame_buffer as _));
+        gl_function!(Viewport(viewport[0], viewport[1], viewport[2], viewport[3]));
+        let clear_color = rendering.clear_color;
+        gl_function!(ClearColor(clear_color.x, clear_color.y, clear_color.z, 1.0));
+        result?;
+        let prepass = self.prepass.borrow();
+        self.render(&prepass.textures[0], &prepass.textures[1])
+    }
+
+    pub fn render(&self, position: &Texture, normal: &Texture) -> Result<(), Error> {
+        if !self.enabled.get() {
+            return Ok(());
+        }
+        let settings = self.settings.borrow();
+        let mut viewport = [0; 4];
+        gl_function!(GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()));
+        let mut previous_frame_buffer = 0;
+        gl_function!(GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_frame_buffer));
+        self.resize(viewport[2].max(1) as u32, viewport[3].max(1) as u32, settings.half_resolution)?;
+        let samples = self.update_kernel(settings.samples);
+        gl_function!(Disable(gl::DEPTH_TEST));
+        gl_function!(Disable(gl::BLEND));
+
+        let occlusion = self.occlusion.borrow();
+        occlusion.bind();
+        gl_function!(Viewport(0, 0, occlusion.width() as _, occlusion.height() as _));
+        position.bind(gl::TEXTURE0);
+        normal.bind(gl::TEXTURE1);
+        self.kernel.borrow().bind(gl::TEXTURE2);
+        self.noise.bind(gl::TEXTURE3);
+        self.ssao_program.use_program();
+        self.ssao_program.set_uniform_i1("gPosition", 0);
+        self.ssao_program.set_uniform_i1("gNormal", 1);
+        self.ssao_program.set_uniform_i1("ssao_kernel", 2);
+        self.ssao_program.set_uniform_i1("ssao_noise", 3);
+        self.ssao_program.set_uniform_i1("ssao_samples", samples as _);
+        self.ssao_program.set_uniform_f1("ssao_radius", settings.radius);
+        self.ssao_program.set_uniform_f1("ssao_bias", settings.bias);
+        self.ssao_program.set_uniform_f1("ssao_power", settings.power);
+        self.quad.draw();
+
+        let blurred = self.blurred.borrow();
+        blurred.bind();
+        gl_function!(Viewport(0, 0, blurred.width() as _, blurred.height() as _));
+        occlusion.texture.bind(gl::TEXTURE1);
+        self.blur_program.use_program();
+        self.blur_program.set_uniform_i1("gPosition", 0);
+        self.blur_program.set_uniform_i1("ssao_input", 1);
+        self.blur_program.set_uniform_f1("ssao_radius", settings.radius);
+        self.quad.draw();
+
+        gl_function!(Enable(gl::DEPTH_TEST));
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, previous_frame_buffer as _));
+        gl_function!(Viewport(viewport[0], viewport[1], viewport[2], viewport[3]));
+        Ok(())
+    }
+
+    pub fn bind(&self) {
+        self.blurred.borrow().texture.bind(gl::TEXTURE0 + AMBIENT_OCCLUSION_TEXTURE_UNIT);
+    }
+
+    pub fn set_program(&self, program: &Program) {
+        if program.reflection().uniform("ambient_occlusion_map").is_none() {
+            return;
+        }
+        program.set_uniform("ambient_occlusion_enabled", &self.enabled.get());
+        program.set_uniform_i1("ambient_occlusion_map", AMBIENT_OCCLUSION_TEXTURE_UNIT as _);
+    }
+}
@@ -0,0 +1,73 @@
+use hecs::World;
+use log::info;
+use sdl2::event::Event;
+use crate::error::Error;
+use crate::ecs::components::{Input, ScreenshotControl};
+use crate::ecs::systems::system::System;
+use crate::frame_buffer::default_frame_buffer;
+use crate::pixels::{read_rgba32f, read_rgba8, ScreenshotFormat, timestamped_path};
+use crate::window::Window;
+
+pub struct ScreenshotSystem {
+    width: usize,
+    height: usize,
+    read_buffer: gl::types::GLenum,
+}
+
+impl ScreenshotSystem {
+    pub fn new(window: &Window) -> ScreenshotSystem {
+        ScreenshotSystem {
+            width: window.width(),
+            height: window.height(),
+            read_buffer: window.read_buffer(),
+        }
+    }
+
+    fn take_screenshot(&self, control: &ScreenshotControl) -> Result<(), Error> {
+        std::fs::create_dir_all(&control.directory).map_err(|e| Error::io(&control.directory, e))?;
+        let path = timestamped_path(&control.directory, "screenshot", control.format.extension())?;
+        let frame_buffer = default_frame_buffer();
+        match control.format {
+            ScreenshotFormat::Png => read_rgba8(frame_buffer, Some(self.read_buffer), self.width as _, self.height as _)?
+                .save(&path),
+            ScreenshotFormat::Exr => read_rgba32f(frame_buffer, Some(self.read_buffer), self.width as _, self.height as _)?
+                .save(&path),
+        }.map_err(|e| Error::image_encode(&path, e))?;
+        info!("Screenshot saved to {}", path.display());
+        Ok(())
+    }
+}
+
+impl System for ScreenshotSystem {
+    fn name(&self) -> &str {
+        "Screenshot"
+    }
+
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        for (_e, (input, control)) in world.query_mut::<(&Input, &ScreenshotControl)>() {
+            for event in input.events.iter() {
+                match event {
+                    Event::KeyDown {
+                        keycode: Some(k),
+                        repeat: false,
+                        ..
+                    } if *k == control.keycode => self.take_screenshot(control)?,
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
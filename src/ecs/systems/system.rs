@@ -1,9 +1,10 @@
 use hecs::World;
+use crate::error::Error;
 
 pub trait System {
     fn name(&self) -> &str;
-    fn start(&self, world: &mut World) -> Result<(), String>;
-    fn early_update(&self, world: &mut World, _delta_time: f32) -> Result<(), String>;
-    fn update(&self, world: &mut World, _delta_time: f32) -> Result<(), String>;
-    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), String>;
+    fn start(&self, world: &mut World) -> Result<(), Error>;
+    fn early_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error>;
+    fn update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error>;
+    fn late_update(&self, world: &mut World, _delta_time: f32) -> Result<(), Error>;
 }
\ No newline at end of file
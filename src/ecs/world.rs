@@ -1,18 +1,23 @@
 use crate::ecs::systems::system::System;
+use crate::error::Error;
 use hecs::{World as HecsWorld};
-use log::error;
+use log::{error, warn};
 
-pub(crate) fn handle_result<T: Default, E: ToString>(result: Result<T, E>) -> T {
-    match result {
-        Ok(v) => v,
-        Err(s) => {
-            error!("{}", &s.to_string());
-            T::default()
-        }
+pub(crate) fn log_error(e: &Error) {
+    match e.root() {
+        Error::Query(_) => warn!("{}", e),
+        _ => error!("{}", e),
+    }
+}
+
+fn handle_result(error_handler: &dyn Fn(&Error), system: &dyn System, result: Result<(), Error>) {
+    if let Err(e) = result {
+        error_handler(&e.in_system(system.name()));
     }
 }
 
 pub struct World {
+    error_handler: Box<dyn Fn(&Error)>,
     systems: Vec<Box<dyn System>>,
     world: HecsWorld,
 }
@@ -20,6 +25,7 @@ pub struct World {
 impl World {
     pub fn new() -> World {
         World {
+            error_handler: Box::new(log_error),
             systems: vec![],
             world: HecsWorld::new(),
         }
@@ -29,47 +35,35 @@ impl World {
         self.systems.push(system);
     }
 
+    pub fn set_error_handler(&mut self, error_handler: impl Fn(&Error) + 'static) {
+        self.error_handler = Box::new(error_handler);
+    }
+
     pub fn get_mut(&mut self) -> &mut HecsWorld {
         &mut self.world
     }
 
     pub fn start(&mut self) {
         for system in self.systems.iter() {
-            handle_result(system.start(&mut self.world)
-                .map_err(|s| {
-                    format!("There was an error on {}: {}", system.name(), &s)
-                })
-            );
+            handle_result(self.error_handler.as_ref(), system.as_ref(), system.start(&mut self.world));
         }
     }
 
     pub fn early_update(&mut self, delta_time: f32) {
         for system in self.systems.iter() {
-            handle_result(system.early_update(&mut self.world, delta_time)
-                .map_err(|s| {
-                    format!("There was an error on {}: {}", system.name(), &s)
-                })
-            );
+            handle_result(self.error_handler.as_ref(), system.as_ref(), system.early_update(&mut self.world, delta_time));
         }
     }
 
     pub fn update(&mut self, delta_time: f32) {
         for system in self.systems.iter() {
-            handle_result(system.update(&mut self.world, delta_time)
-                .map_err(|s| {
-                    format!("There was an error on {}: {}", system.name(), &s)
-                })
-            );
+            handle_result(self.error_handler.as_ref(), system.as_ref(), system.update(&mut self.world, delta_time));
         }
     }
 
     pub fn late_update(&mut self, delta_time: f32) {
         for system in self.systems.iter() {
-            handle_result(system.late_update(&mut self.world, delta_time)
-                .map_err(|s| {
-                    format!("There was an error on {}: {}", system.name(), &s)
-                })
-            );
+            handle_result(self.error_handler.as_ref(), system.as_ref(), system.late_update(&mut self.world, delta_time));
         }
     }
 }
\ No newline at end of file
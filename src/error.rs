@@ -0,0 +1,217 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use hecs::{ComponentError, NoSuchEntity, QueryOneError};
+use image::ImageError;
+use russimp::RussimpError;
+use crate::shader_diagnostic::ShaderDiagnostic;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum QueryError {
+    Component(ComponentError),
+    NoSuchEntity(NoSuchEntity),
+    QueryOne(QueryOneError),
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ShaderCompile {
+        stage: gl::types::GLenum,
+        log: String,
+        diagnostics: Vec<ShaderDiagnostic>,
+    },
+    ProgramLink {
+        log: String,
+    },
+    IncludeCycle {
+        chain: Vec<String>,
+    },
+    MissingAsset {
+        path: PathBuf,
+    },
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ImageDecode {
+        path: PathBuf,
+        source: ImageError,
+    },
+    ImageEncode {
+        path: PathBuf,
+        source: ImageError,
+    },
+    ModelImport {
+        path: PathBuf,
+        source: RussimpError,
+    },
+    UniformType {
+        name: String,
+        declared: &'static str,
+        given: &'static str,
+    },
+    UniformBlockLayout {
+        name: String,
+        declared: usize,
+        given: usize,
+    },
+    FrameBufferIncomplete {
+        status: gl::types::GLenum,
+    },
+    Query(QueryError),
+    System {
+        system: String,
+        source: Box<Error>,
+    },
+    Other(String),
+}
+
+pub fn shader_stage_name(stage: gl::types::GLenum) -> &'static str {
+    match stage {
+        gl::VERTEX_SHADER => "vertex",
+        gl::FRAGMENT_SHADER => "fragment",
+        gl::GEOMETRY_SHADER => "geometry",
+        gl::TESS_CONTROL_SHADER => "tessellation control",
+        gl::TESS_EVALUATION_SHADER => "tessellation evaluation",
+        gl::COMPUTE_SHADER => "compute",
+        _ => "unknown",
+    }
+}
+
+impl Error {
+    pub fn io<P: Into<PathBuf>>(path: P, source: std::io::Error) -> Error {
+        let path = path.into();
+        match source.kind() {
+            std::io::ErrorKind::NotFound => Error::MissingAsset { path },
+            _ => Error::Io { path, source },
+        }
+    }
+
+    pub fn image_decode<P: Into<PathBuf>>(path: P, source: ImageError) -> Error {
+        let path = path.into();
+        match source {
+            ImageError::IoError(source) => Error::io(path, source),
+            source => Error::ImageDecode { path, source },
+        }
+    }
+
+    pub fn image_encode<P: Into<PathBuf>>(path: P, source: ImageError) -> Error {
+        Error::ImageEncode { path: path.into(), source }
+    }
+
+    pub fn in_system(self, system: &str) -> Error {
+        Error::System {
+            system: system.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::System { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    pub fn is_query(&self) -> bool {
+        matches!(self.root(), Error::Query(_))
+    }
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::Component(e) => write!(f, "{}", e),
+            QueryError::NoSuchEntity(e) => write!(f, "{}", e),
+            QueryError::QueryOne(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::ShaderCompile { stage, log, diagnostics } if diagnostics.is_empty() =>
+                write!(f, "Error compiling {} shader: {}", shader_stage_name(*stage), log),
+            Error::ShaderCompile { stage, diagnostics, .. } => {
+                write!(f, "Error compiling {} shader:\n", shader_stage_name(*stage))?;
+                diagnostics.iter().try_for_each(|d| write!(f, "{}", d))
+            }
+            Error::ProgramLink { log } => write!(f, "Error linking program: {}", log),
+            Error::IncludeCycle { chain } => write!(f, "Cyclic shader include: {}", chain.join(" -> ")),
+            Error::MissingAsset { path } => write!(f, "Asset {} not found", path.display()),
+            Error::Io { path, source } => write!(f, "Error accessing {}: {}", path.display(), source),
+            Error::ImageDecode { path, source } => write!(f, "Error decoding image {}: {}", path.display(), source),
+            Error::ImageEncode { path, source } => write!(f, "Error encoding image {}: {}", path.display(), source),
+            Error::ModelImport { path, source } => write!(f, "Error importing model {}: {}", path.display(), source),
+            Error::UniformType { name, declared, given } =>
+                write!(f, "Uniform {} is declared as {} but was given a {}", name, declared, given),
+            Error::UniformBlockLayout { name, declared, given } =>
+                write!(f, "Uniform block {} needs {} bytes but was given {}", name, declared, given),
+            Error::FrameBufferIncomplete { status } => write!(f, "Frame buffer incomplete, status code {}", status),
+            Error::Query(e) => write!(f, "Error querying the world: {}", e),
+            Error::System { system, source } => write!(f, "There was an error on {}: {}", system, source),
+            Error::Other(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QueryError::Component(e) => Some(e),
+            QueryError::NoSuchEntity(e) => Some(e),
+            QueryError::QueryOne(e) => Some(e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::ImageDecode { source, .. } | Error::ImageEncode { source, .. } => Some(source),
+            Error::ModelImport { source, .. } => Some(source),
+            Error::Query(e) => Some(e),
+            Error::System { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(s: String) -> Self {
+        Error::Other(s)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(s: &str) -> Self {
+        Error::Other(s.to_string())
+    }
+}
+
+impl From<ComponentError> for Error {
+    fn from(e: ComponentError) -> Self {
+        Error::Query(QueryError::Component(e))
+    }
+}
+
+impl From<NoSuchEntity> for Error {
+    fn from(e: NoSuchEntity) -> Self {
+        Error::Query(QueryError::NoSuchEntity(e))
+    }
+}
+
+impl From<QueryOneError> for Error {
+    fn from(e: QueryOneError) -> Self {
+        Error::Query(QueryError::QueryOne(e))
+    }
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> Self {
+        e.to_string()
+    }
+}
@@ -1,20 +1,46 @@
-use log::error;
+use std::cell::Cell;
+use image::{Rgba32FImage, RgbaImage};
+use crate::error::Error;
+use crate::pixels::{DepthImage, read_depth, read_rgba32f, read_rgba8};
 use crate::render_buffer::RenderBuffer;
 use crate::texture::{Texture, TextureFormat, TextureType};
 
+thread_local! {
+    static DEFAULT_FRAME_BUFFER: Cell<gl::types::GLuint> = Cell::new(0);
+}
+
+pub fn default_frame_buffer() -> gl::types::GLuint {
+    DEFAULT_FRAME_BUFFER.with(|frame_buffer| frame_buffer.get())
+}
+
+#[cfg(feature = "headless")]
+pub(crate) fn set_default_frame_buffer(frame_buffer: gl::types::GLuint) {
+    DEFAULT_FRAME_BUFFER.with(|default| default.set(frame_buffer));
+}
+
+pub(crate) fn check_frame_buffer_status() -> Result<(), Error> {
+    let status = gl_function!(CheckFramebufferStatus(gl::FRAMEBUFFER));
+    if status != gl::FRAMEBUFFER_COMPLETE {
+        return Err(Error::FrameBufferIncomplete { status });
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct FrameBuffer {
     _render_buffer: Option<RenderBuffer>,
-    resource: gl::types::GLuint,
+    pub(crate) resource: gl::types::GLuint,
     pub texture: Texture,
+    width: u32,
+    height: u32,
 }
 
 impl FrameBuffer {
-    pub fn new(width: u32, height: u32) -> FrameBuffer {
+    pub fn new(width: u32, height: u32) -> Result<FrameBuffer, Error> {
         FrameBuffer::new_with_format(width, height, TextureFormat::UnsignedByte)
     }
 
-    pub fn new_with_format(width: u32, height: u32, format: TextureFormat) -> FrameBuffer {
+    pub fn new_with_format(width: u32, height: u32, format: TextureFormat) -> Result<FrameBuffer, Error> {
         let mut frame_buffer = 0 as gl::types::GLuint;
         gl_function!(GenFramebuffers(1, &mut frame_buffer));
         gl_function!(BindFramebuffer(gl::FRAMEBUFFER, frame_buffer));
@@ -34,19 +60,18 @@ impl FrameBuffer {
         RenderBuffer::unbind();
         gl_function!(FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, render_buffer.0));
 
-        let status = gl_function!(CheckFramebufferStatus(gl::FRAMEBUFFER));
-        if status != gl::FRAMEBUFFER_COMPLETE {
-            error!("Error creating frame buffer, status code {}", status);
-        }
+        let status = check_frame_buffer_status();
         FrameBuffer::unbind();
-        FrameBuffer {
+        status.and(Ok(FrameBuffer {
             texture,
             _render_buffer: Some(render_buffer),
             resource: frame_buffer,
-        }
+            width,
+            height,
+        }))
     }
 
-    pub fn intermediate_with_format(width: u32, height: u32, format: TextureFormat) -> FrameBuffer {
+    pub fn intermediate_with_format(width: u32, height: u32, format: TextureFormat) -> Result<FrameBuffer, Error> {
         let mut frame_buffer = 0 as gl::types::GLuint;
         gl_function!(GenFramebuffers(1, &mut frame_buffer));
         gl_function!(BindFramebuffer(gl::FRAMEBUFFER, frame_buffer));
@@ -58,19 +83,18 @@ impl FrameBuffer {
         gl_function!(TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as _));
         gl_function!(FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, TextureType::Texture2D as u32, texture.0, 0));
 
-        let status = gl_function!(CheckFramebufferStatus(gl::FRAMEBUFFER));
-        if status != gl::FRAMEBUFFER_COMPLETE {
-            error!("Error creating frame buffer, status code {}", status);
-        }
+        let status = check_frame_buffer_status();
         FrameBuffer::unbind();
-        FrameBuffer {
+        status.and(Ok(FrameBuffer {
             texture,
             _render_buffer: None,
             resource: frame_buffer,
-        }
+            width,
+            height,
+        }))
     }
 
-    pub fn intermediate(width: u32, height: u32) -> FrameBuffer {
+    pub fn intermediate(width: u32, height: u32) -> Result<FrameBuffer, Error> {
         let mut frame_buffer = 0 as gl::types::GLuint;
         gl_function!(GenFramebuffers(1, &mut frame_buffer));
         gl_function!(BindFramebuffer(gl::FRAMEBUFFER, frame_buffer));
@@ -82,19 +106,18 @@ impl FrameBuffer {
         gl_function!(TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _));
         gl_function!(FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, TextureType::Texture2D as u32, texture.0, 0));
 
-        let status = gl_function!(CheckFramebufferStatus(gl::FRAMEBUFFER));
-        if status != gl::FRAMEBUFFER_COMPLETE {
-            error!("Error creating frame buffer, status code {}", status);
-        }
+        let status = check_frame_buffer_status();
         FrameBuffer::unbind();
-        FrameBuffer {
+        status.and(Ok(FrameBuffer {
             texture,
             _render_buffer: None,
             resource: frame_buffer,
-        }
+            width,
+            height,
+        }))
     }
 
-    pub fn multisample(width: u32, height: u32) -> FrameBuffer {
+    pub fn multisample(width: u32, height: u32) -> Result<FrameBuffer, Error> {
         let mut frame_buffer = 0 as gl::types::GLuint;
         gl_function!(GenFramebuffers(1, &mut frame_buffer));
         gl_function!(BindFramebuffer(gl::FRAMEBUFFER, frame_buffer));
@@ -112,19 +135,18 @@ impl FrameBuffer {
         RenderBuffer::unbind();
         gl_function!(FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, render_buffer.0));
 
-        let status = gl_function!(CheckFramebufferStatus(gl::FRAMEBUFFER));
-        if status != gl::FRAMEBUFFER_COMPLETE {
-            error!("Error creating frame buffer, status code {}", status);
-        }
+        let status = check_frame_buffer_status();
         FrameBuffer::unbind();
-        FrameBuffer {
+        status.and(Ok(FrameBuffer {
             texture,
             _render_buffer: Some(render_buffer),
             resource: frame_buffer,
-        }
+            width,
+            height,
+        }))
     }
 
-    pub fn depth_buffer(width: u32, height: u32) -> FrameBuffer {
+    pub fn depth_buffer(width: u32, height: u32) -> Result<FrameBuffer, Error> {
         let mut frame_buffer = 0 as gl::types::GLuint;
         gl_function!(GenFramebuffers(1, &mut frame_buffer));
 
@@ -141,33 +163,171 @@ impl FrameBuffer {
         gl_function!(FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture.1, texture.0, 0));
         gl_function!(DrawBuffer(gl::NONE));
         gl_function!(ReadBuffer(gl::NONE));
+        let status = check_frame_buffer_status();
+        FrameBuffer::unbind();
+
+        status.and(Ok(FrameBuffer {
+            texture,
+            _render_buffer: None,
+            resource: frame_buffer,
+            width,
+            height,
+        }))
+    }
+
+    pub fn depth_array(width: u32, height: u32, layers: usize) -> Result<FrameBuffer, Error> {
+        FrameBuffer::depth_layers(TextureType::Texture2DArray, width, height, layers)
+    }
+
+    pub fn depth_cubemap_array(size: u32, cubemaps: usize) -> Result<FrameBuffer, Error> {
+        FrameBuffer::depth_layers(TextureType::CubeMapArray, size, size, cubemaps * 6)
+    }
+
+    fn depth_layers(texture_type: TextureType, width: u32, height: u32, layers: usize) -> Result<FrameBuffer, Error> {
+        let texture = Texture::new(texture_type);
+        texture.just_bind();
+        texture.allocate_depth_layers(width, height, layers)?;
+        texture.set_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR);
+        texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+        texture.set_parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        texture.set_parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        texture.set_parameter(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE);
+        texture.set_parameter(gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL);
+        texture.unbind();
+
+        let mut frame_buffer = 0 as gl::types::GLuint;
+        gl_function!(GenFramebuffers(1, &mut frame_buffer));
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, frame_buffer));
+        gl_function!(FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture.0, 0, 0));
+        gl_function!(DrawBuffer(gl::NONE));
+        gl_function!(ReadBuffer(gl::NONE));
+        let status = check_frame_buffer_status();
         FrameBuffer::unbind();
 
-        FrameBuffer {
+        status.and(Ok(FrameBuffer {
             texture,
             _render_buffer: None,
             resource: frame_buffer,
+            width,
+            height,
+        }))
+    }
+
+    pub fn attach_depth_layer(&self, layer: usize) {
+        gl_function!(FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, self.texture.0, 0, layer as _));
+    }
+
+    pub fn cubemap(size: u32, levels: usize) -> Result<FrameBuffer, Error> {
+        let mut frame_buffer = 0 as gl::types::GLuint;
+        gl_function!(GenFramebuffers(1, &mut frame_buffer));
+
+        let texture = Texture::new(TextureType::CubeMap);
+        texture.just_bind();
+        for level in 0..levels.max(1) {
+            let level_size = (size >> level).max(1) as usize;
+            for face in 0..6 {
+                texture.alloc_float_cube_map_face(face, level, level_size, level_size);
+            }
         }
+        let min_filter = if levels > 1 { gl::LINEAR_MIPMAP_LINEAR } else { gl::LINEAR };
+        texture.set_parameter(gl::TEXTURE_MIN_FILTER, min_filter);
+        texture.set_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR);
+        texture.set_parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE);
+        texture.set_parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE);
+        texture.set_parameter(gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE);
+        texture.set_parameter(gl::TEXTURE_MAX_LEVEL, (levels.max(1) - 1) as _);
+        texture.unbind();
+
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, frame_buffer));
+        gl_function!(FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_CUBE_MAP_POSITIVE_X, texture.0, 0));
+        let status = check_frame_buffer_status();
+        FrameBuffer::unbind();
+
+        status.and(Ok(FrameBuffer {
+            texture,
+            _render_buffer: None,
+            resource: frame_buffer,
+            width: size,
+            height: size,
+        }))
     }
 
-    pub fn depth_cubemap_with_texture(texture: Texture) -> FrameBuffer {
+    pub fn attach_cubemap_face(&self, face: u32, level: usize) {
+        gl_function!(FramebufferTexture2D(
+            gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_CUBE_MAP_POSITIVE_X + face, self.texture.0, level as _,
+        ));
+    }
+
+    pub fn depth_cubemap_with_texture(texture: Texture) -> Result<FrameBuffer, Error> {
         let mut frame_buffer = 0 as gl::types::GLuint;
         gl_function!(GenFramebuffers(1, &mut frame_buffer));
 
         texture.just_bind();
+        let mut width = 0;
+        let mut height = 0;
+        gl_function!(GetTexLevelParameteriv(gl::TEXTURE_CUBE_MAP_POSITIVE_X, 0, gl::TEXTURE_WIDTH, &mut width));
+        gl_function!(GetTexLevelParameteriv(gl::TEXTURE_CUBE_MAP_POSITIVE_X, 0, gl::TEXTURE_HEIGHT, &mut height));
         gl_function!(BindFramebuffer(gl::FRAMEBUFFER, frame_buffer));
         gl_function!(FramebufferTexture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture.0, 0));
         gl_function!(DrawBuffer(gl::NONE));
         gl_function!(ReadBuffer(gl::NONE));
+        let status = check_frame_buffer_status();
         FrameBuffer::unbind();
 
-        FrameBuffer {
+        status.and(Ok(FrameBuffer {
             texture,
             _render_buffer: None,
             resource: frame_buffer,
+            width: width as _,
+            height: height as _,
+        }))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn status(&self) -> Result<(), Error> {
+        self.bind();
+        let status = check_frame_buffer_status();
+        FrameBuffer::unbind();
+        status
+    }
+
+    fn resolve(&self, format: TextureFormat) -> Result<Option<FrameBuffer>, Error> {
+        if self.texture.1 != gl::TEXTURE_2D_MULTISAMPLE {
+            return Ok(None);
+        }
+        let resolved = FrameBuffer::intermediate_with_format(self.width, self.height, format)?;
+        self.read_bind();
+        resolved.draw_bind();
+        gl_function!(BlitFramebuffer(0, 0, self.width as _, self.height as _, 0, 0, self.width as _, self.height as _, gl::COLOR_BUFFER_BIT, gl::NEAREST));
+        FrameBuffer::unbind();
+        Ok(Some(resolved))
+    }
+
+    pub fn read_pixels(&self) -> Result<RgbaImage, Error> {
+        match self.resolve(TextureFormat::UnsignedByteWithAlpha)? {
+            Some(resolved) => resolved.read_pixels(),
+            None => read_rgba8(self.resource, Some(gl::COLOR_ATTACHMENT0), self.width, self.height),
         }
     }
 
+    pub fn read_pixels_hdr(&self) -> Result<Rgba32FImage, Error> {
+        match self.resolve(TextureFormat::FloatingPoint)? {
+            Some(resolved) => resolved.read_pixels_hdr(),
+            None => read_rgba32f(self.resource, Some(gl::COLOR_ATTACHMENT0), self.width, self.height),
+        }
+    }
+
+    pub fn read_depth(&self) -> Result<DepthImage, Error> {
+        read_depth(self.resource, self.width, self.height)
+    }
+
     pub fn draw_bind(&self) {
         gl_function!(BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.resource));
     }
@@ -181,7 +341,15 @@ impl FrameBuffer {
     }
 
     pub fn unbind() {
-        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, 0));
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, default_frame_buffer()));
+    }
+
+    pub fn draw_unbind() {
+        gl_function!(BindFramebuffer(gl::DRAW_FRAMEBUFFER, default_frame_buffer()));
+    }
+
+    pub fn read_unbind() {
+        gl_function!(BindFramebuffer(gl::READ_FRAMEBUFFER, default_frame_buffer()));
     }
 }
 
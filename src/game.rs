@@ -1,28 +1,46 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 use hecs::{Component, DynamicBundle, Entity};
+use log::warn;
 use nalgebra::{Matrix4, Vector3};
 use sdl2::keyboard::Keycode;
 use crate::camera::Camera;
-use crate::ecs::components::{FpsCamera, Input, InstancedMesh, InstancedModel, Mesh, Model, QuitControl, Skybox, Transform};
+use crate::capture::{CaptureOptions, FrameCapture};
+use crate::ecs::components::{FpsCamera, Input, InstancedMesh, InstancedModel, Mesh, Model, QuitControl, ScreenshotControl, Skybox, Transform};
 use crate::ecs::systems::fps_camera::FpsCameraSystem;
 use crate::ecs::systems::input::{InputSystem, InputType};
+use crate::ecs::systems::post_processing::PostProcessStack;
 use crate::ecs::systems::quit_system::QuitSystem;
-use crate::ecs::systems::rendering::RenderingSystem;
+use crate::ecs::systems::rendering::{RenderingPath, RenderingSystem};
+use crate::ecs::systems::rendering::clustered_lighting::ClusteredLighting;
+use crate::ecs::systems::rendering::deferred::{DeferredRenderingSystem, GBufferView};
+use crate::ecs::systems::rendering::ssao::{Ssao, SsaoSettings};
+use crate::ecs::systems::screenshot::ScreenshotSystem;
 use crate::ecs::systems::system::System;
 use crate::ecs::world::World;
+use crate::error::Error;
+use crate::gl_error::{enable_debug_output, GlError, GlErrorPolicy, set_gl_error_policy, take_gl_errors};
 use crate::light::{FlashLight, Light, SpotLight};
 use crate::loader::{load_instanced_model, load_model};
-use crate::window::Window;
+use crate::pixels::ScreenshotFormat;
+use crate::window::{Window, WindowOptions};
+#[cfg(feature = "headless")]
+use crate::headless::HeadlessOptions;
 
 pub struct Game {
     camera: Rc<RefCell<Camera>>,
+    capture_options: Option<CaptureOptions>,
     _fps: usize,
     game_ended: Rc<RefCell<bool>>,
+    gl_errors: Vec<GlError>,
+    post_process_stack: Option<PostProcessStack>,
+    rendering_path: RenderingPath,
     rendering_system: Option<RenderingSystem>,
-    window: Window,
+    screenshot_control: ScreenshotControl,
     world: World,
+    window: Window,
 }
 
 impl Game {
@@ -37,10 +55,12 @@ impl Game {
         light_vertex_shader: &'static str,
         light_fragment_shader: &'static str,
         samples: u8,
-    ) -> Result<Game, String> {
-        env_logger::init();
-        let window = Window::new_with_anti_alias(name, width, height, samples).unwrap();
-        Self::build_with_window(fps, clear_color, model_vertex_shader, model_fragment_shader, light_vertex_shader, light_fragment_shader, window)
+    ) -> Result<Game, Error> {
+        Self::new_with_options(
+            name, width, height, fps, clear_color,
+            model_vertex_shader, model_fragment_shader, light_vertex_shader, light_fragment_shader,
+            WindowOptions { samples: Some(samples), ..WindowOptions::default() },
+        )
     }
 
     pub fn new(
@@ -53,12 +73,47 @@ impl Game {
         model_fragment_shader: &'static str,
         light_vertex_shader: &'static str,
         light_fragment_shader: &'static str,
-    ) -> Result<Game, String> {
-        env_logger::init();
-        let window = Window::new(name, width, height).unwrap();
+    ) -> Result<Game, Error> {
+        Self::new_with_options(
+            name, width, height, fps, clear_color,
+            model_vertex_shader, model_fragment_shader, light_vertex_shader, light_fragment_shader,
+            WindowOptions::default(),
+        )
+    }
+
+    pub fn new_with_options(
+        name: &str,
+        width: usize,
+        height: usize,
+        fps: usize,
+        clear_color: Vector3<f32>,
+        model_vertex_shader: &'static str,
+        model_fragment_shader: &'static str,
+        light_vertex_shader: &'static str,
+        light_fragment_shader: &'static str,
+        options: WindowOptions,
+    ) -> Result<Game, Error> {
+        let _ = env_logger::try_init();
+        let window = Window::new_with_options(name, width, height, options)?;
         Self::build_with_window(fps, clear_color, model_vertex_shader, model_fragment_shader, light_vertex_shader, light_fragment_shader, window)
     }
 
+    #[cfg(feature = "headless")]
+    pub fn new_headless(
+        width: usize,
+        height: usize,
+        options: HeadlessOptions,
+        clear_color: Vector3<f32>,
+        model_vertex_shader: &'static str,
+        model_fragment_shader: &'static str,
+        light_vertex_shader: &'static str,
+        light_fragment_shader: &'static str,
+    ) -> Result<Game, Error> {
+        let _ = env_logger::try_init();
+        let window = Window::new_headless(width, height, options)?;
+        Self::build_with_window(0, clear_color, model_vertex_shader, model_fragment_shader, light_vertex_shader, light_fragment_shader, window)
+    }
+
     fn build_with_window(
         fps: usize,
         clear_color: Vector3<f32>,
@@ -67,7 +122,12 @@ impl Game {
         light_vertex_shader: &'static str,
         light_fragment_shader: &'static str,
         window: Window
-    ) -> Result<Game, String> {
+    ) -> Result<Game, Error> {
+        if window.gl_debug() {
+            if let Err(e) = enable_debug_output() {
+                warn!("Couldn't enable GL debug output: {}", e);
+            }
+        }
         let world = World::new();
         let camera = Rc::new(RefCell::new(Camera::new(
             Vector3::new(0.0f32, 0f32, 3f32),
@@ -84,11 +144,20 @@ impl Game {
         )?;
         Ok(Game {
             camera,
+            capture_options: CaptureOptions::from_env()?,
             _fps: fps,
             window,
             world,
             game_ended: Rc::new(RefCell::new(false)),
+            gl_errors: vec![],
+            post_process_stack: None,
+            rendering_path: RenderingPath::from_env(),
             rendering_system: Some(rendering),
+            screenshot_control: ScreenshotControl {
+                keycode: Keycode::F12,
+                format: ScreenshotFormat::Png,
+                directory: PathBuf::from("screenshots"),
+            },
         })
     }
 
@@ -96,17 +165,69 @@ impl Game {
         self.camera.clone()
     }
 
-    pub fn load_model(&mut self, model: &str) -> Result<Model, String> {
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    pub fn enable_gl_debug_output(&self) -> Result<(), Error> {
+        enable_debug_output()
+    }
+
+    pub fn set_gl_error_policy(&self, policy: GlErrorPolicy) {
+        set_gl_error_policy(policy);
+    }
+
+    pub fn gl_errors(&self) -> &[GlError] {
+        &self.gl_errors
+    }
+
+    pub fn capture(&mut self, capture_options: CaptureOptions) {
+        self.capture_options = Some(capture_options);
+    }
+
+    pub fn set_rendering_path(&mut self, rendering_path: RenderingPath) {
+        self.rendering_path = rendering_path;
+    }
+
+    pub fn g_buffer_view(&self) -> Result<Rc<Cell<GBufferView>>, Error> {
+        let rendering = self.rendering_system.as_ref().ok_or("No Rendering system".to_string())?;
+        Ok(rendering.g_buffer_view())
+    }
+
+    pub fn enable_ssao(&mut self, settings: SsaoSettings) -> Result<Rc<Ssao>, Error> {
+        let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
+        rendering.enable_ssao(settings)
+    }
+
+    pub fn enable_clustered_lighting(&mut self, dimensions: (usize, usize, usize)) -> Result<&ClusteredLighting, Error> {
+        let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
+        Ok(rendering.enable_clustered_lighting(dimensions))
+    }
+
+    pub fn post_process_stack(&self) -> Result<PostProcessStack, Error> {
+        let rendering = self.rendering_system.as_ref().ok_or("No Rendering system".to_string())?;
+        PostProcessStack::new(rendering.shader_loader(), self.window.width() as _, self.window.height() as _)
+    }
+
+    pub fn set_post_process_stack(&mut self, post_process_stack: PostProcessStack) {
+        self.post_process_stack = Some(post_process_stack);
+    }
+
+    pub fn set_screenshot_control(&mut self, screenshot_control: ScreenshotControl) {
+        self.screenshot_control = screenshot_control;
+    }
+
+    pub fn load_model(&mut self, model: &str) -> Result<Model, Error> {
         let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
         let model = load_model(model, rendering)?;
         Ok(model)
     }
 
-    pub fn spawn_loaded_model(&mut self, model: &Model, transform: Transform) -> Result<Entity, String> {
+    pub fn spawn_loaded_model(&mut self, model: &Model, transform: Transform) -> Result<Entity, Error> {
         Ok(self.world.get_mut().spawn((model.clone(), transform)))
     }
 
-    pub fn spawn_model(&mut self, model: Vec<Mesh>, transform: Transform) -> Result<Entity, String> {
+    pub fn spawn_model(&mut self, model: Vec<Mesh>, transform: Transform) -> Result<Entity, Error> {
         let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
         Ok(self.world.get_mut().spawn((
             Model::from_meshes(model, rendering)?,
@@ -114,42 +235,40 @@ impl Game {
         )))
     }
 
-    pub fn spawn_model_from_file(&mut self, model: &str, transform: Transform) -> Result<Entity, String> {
+    pub fn spawn_model_from_file(&mut self, model: &str, transform: Transform) -> Result<Entity, Error> {
         let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
         let model = load_model(model, rendering)?;
         Ok(self.world.get_mut().spawn((model, transform)))
     }
 
-    pub fn spawn_instanced_model_from_file(&mut self, model: &str, models: Vec<Matrix4<f32>>) -> Result<Entity, String> {
+    pub fn spawn_instanced_model_from_file(&mut self, model: &str, models: Vec<Matrix4<f32>>) -> Result<Entity, Error> {
         let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
         let model = load_instanced_model(model, rendering, models)?;
         let entity = self.world.get_mut().spawn(());
-        self.world.get_mut().insert_one(entity, model)
-            .map_err(|e| e.to_string())?;
+        self.world.get_mut().insert_one(entity, model)?;
         Ok(entity)
     }
 
-    pub fn spawn_skybox(&mut self, skybox: &Skybox) -> Result<Entity, String> {
+    pub fn spawn_skybox(&mut self, skybox: &Skybox) -> Result<Entity, Error> {
         let shader = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?
             .shader_for_skybox(skybox)?;
         Ok(self.world.get_mut().spawn((shader, skybox.clone())))
     }
 
-    pub fn spawn_mesh(&mut self, mesh: &Mesh, transform: Transform) -> Result<Entity, String> {
+    pub fn spawn_mesh(&mut self, mesh: &Mesh, transform: Transform) -> Result<Entity, Error> {
         let shader = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?
             .shader_for_mesh(&mesh)?;
         Ok(self.world.get_mut().spawn((mesh.clone(), shader, transform)))
     }
 
-    pub fn spawn_instanced_model(&mut self, model: Vec<Mesh>, models: Vec<Matrix4<f32>>) -> Result<Entity, String> {
+    pub fn spawn_instanced_model(&mut self, model: Vec<Mesh>, models: Vec<Matrix4<f32>>) -> Result<Entity, Error> {
         let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
         let entity = self.world.get_mut().spawn(());
-        self.world.get_mut().insert_one(entity, InstancedModel::new(model, rendering, models))
-            .map_err(|e| e.to_string())?;
+        self.world.get_mut().insert_one(entity, InstancedModel::new(model, rendering, models)?)?;
         Ok(entity)
     }
 
-    pub fn spawn_instanced_mesh(&mut self, mesh: &Mesh, models: Vec<Matrix4<f32>>) -> Result<Entity, String> {
+    pub fn spawn_instanced_mesh(&mut self, mesh: &Mesh, models: Vec<Matrix4<f32>>) -> Result<Entity, Error> {
         let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
         let shader = rendering.shader_for_mesh(mesh)?;
         let shader = rendering.instanced_rendering.shader_for_mesh(&shader)?;
@@ -159,7 +278,7 @@ impl Game {
         }, shader)))
     }
 
-    pub fn spawn_light<L: Light + Send + Sync + 'static>(&mut self, light: L, mesh: &Mesh) -> Result<Entity, String> {
+    pub fn spawn_light<L: Light + Send + Sync + 'static>(&mut self, light: L, mesh: &Mesh) -> Result<Entity, Error> {
         let rendering = self.rendering_system.as_mut().ok_or("No Rendering system".to_string())?;
         let shader = rendering.shader_for_mesh(&mesh)?;
         Ok(self.world.get_mut().spawn((mesh.clone(), shader, light)))
@@ -169,8 +288,8 @@ impl Game {
         self.world.get_mut().spawn(components)
     }
 
-    pub fn add_to(&mut self, entity: Entity, component: impl Component) -> Result<(), String> {
-        self.world.get_mut().insert_one(entity, component).map_err(|e| e.to_string())
+    pub fn add_to(&mut self, entity: Entity, component: impl Component) -> Result<(), Error> {
+        Ok(self.world.get_mut().insert_one(entity, component)?)
     }
 
     pub fn spawn_flash_light(&mut self, light: SpotLight, mesh: &Mesh, offset: Vector3<f32>) {
@@ -179,7 +298,7 @@ impl Game {
         }));
     }
 
-    pub fn play_with_fps_camera(&mut self, systems: Vec<Box<dyn System>>) -> Result<(), String> {
+    pub fn play_with_fps_camera(&mut self, systems: Vec<Box<dyn System>>) -> Result<(), Error> {
         self.spawn((Input::new(vec![InputType::Quit, InputType::Keyboard]), QuitControl {
             quit_keycode: Keycode::Escape,
         }));
@@ -188,24 +307,53 @@ impl Game {
         }));
         let rendering = self.rendering_system.take()
             .ok_or("No rendering system".to_string())?;
-        self.world.add_system(Box::new(rendering));
-        self.world.add_system(Box::new(InputSystem { event_pumper: RefCell::new(self.window.get_pumper()), pressed_down: RefCell::new(HashMap::new()) }));
-        self.world.add_system(Box::new(QuitSystem { game_ended: self.game_ended.clone() }));
-        self.world.add_system(Box::new(FpsCameraSystem { camera: self.camera.clone(), mouse: self.window.mouse() }));
+        match self.rendering_path {
+            RenderingPath::Forward => self.world.add_system(Box::new(rendering)),
+            RenderingPath::Deferred => self.world.add_system(Box::new(DeferredRenderingSystem::new(rendering)?)),
+        }
+        if !self.window.is_headless() {
+            self.world.add_system(Box::new(InputSystem { event_pumper: RefCell::new(self.window.get_pumper()?), pressed_down: RefCell::new(HashMap::new()) }));
+            self.world.add_system(Box::new(QuitSystem { game_ended: self.game_ended.clone() }));
+            self.world.add_system(Box::new(FpsCameraSystem { camera: self.camera.clone(), mouse: self.window.mouse()? }));
+        }
         for system in systems {
             self.world.add_system(system);
         }
+        if let Some(post_process_stack) = self.post_process_stack.take() {
+            self.world.add_system(Box::new(post_process_stack));
+        }
+        if !self.window.is_headless() {
+            self.spawn((Input::new(vec![InputType::Keyboard]), self.screenshot_control.clone()));
+            self.world.add_system(Box::new(ScreenshotSystem::new(&self.window)));
+        }
 
+        let mut capture = match self.capture_options.take() {
+            Some(options) => Some(FrameCapture::new(options, self.window.width(), self.window.height())?),
+            None => None,
+        };
         self.window.start_timer();
         self.world.start();
-        while !(*self.game_ended.borrow()) {
-            let delta_time = self.window.delta_time();
+        while !(*self.game_ended.borrow()) && !self.window.should_close() {
+            let delta_time = match &capture {
+                Some(capture) => capture.timestep(),
+                None => self.window.delta_time(),
+            };
 
             self.world.early_update(delta_time);
             self.world.update(delta_time);
             self.world.late_update(delta_time);
 
+            if let Some(capture) = capture.as_mut() {
+                capture.record(&self.window)?;
+                if capture.finished() {
+                    *self.game_ended.borrow_mut() = true;
+                }
+            }
             self.window.swap_buffers();
+            self.gl_errors = take_gl_errors();
+            if !self.gl_errors.is_empty() {
+                warn!("{} GL errors during the last frame", self.gl_errors.len());
+            }
         }
         Ok(())
     }
@@ -0,0 +1,129 @@
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::ffi::{c_void, CStr};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use log::{debug, error, info, warn};
+use crate::error::Error;
+
+const GL_DEBUG_VARIABLE: &'static str = "LEARNOPENGL_GL_DEBUG";
+
+#[derive(Clone, Debug)]
+pub struct GlError {
+    pub code: gl::types::GLenum,
+    pub function: &'static str,
+    pub arguments: &'static str,
+}
+
+impl Display for GlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ERROR CODE {} on gl{}({})", self.code, self.function, self.arguments)
+    }
+}
+
+pub enum GlErrorPolicy {
+    Exit,
+    Log,
+    Panic,
+    Collect,
+    Callback(Rc<dyn Fn(&GlError)>),
+}
+
+thread_local! {
+    static POLICY: RefCell<GlErrorPolicy> = RefCell::new(GlErrorPolicy::Exit);
+    static ERRORS: RefCell<Vec<GlError>> = RefCell::new(vec![]);
+}
+
+pub fn set_gl_error_policy(policy: GlErrorPolicy) {
+    POLICY.with(|p| *p.borrow_mut() = policy);
+}
+
+pub fn take_gl_errors() -> Vec<GlError> {
+    ERRORS.with(|errors| errors.borrow_mut().drain(..).collect())
+}
+
+#[doc(hidden)]
+pub fn report_gl_error(code: gl::types::GLenum, function: &'static str, arguments: &'static str) {
+    let gl_error = GlError {
+        code,
+        function,
+        arguments,
+    };
+    let callback = POLICY.with(|policy| match &*policy.borrow() {
+        GlErrorPolicy::Exit => {
+            error!("{}", gl_error);
+            std::process::exit(code as i32);
+        }
+        GlErrorPolicy::Log => {
+            error!("{}", gl_error);
+            None
+        }
+        GlErrorPolicy::Panic => panic!("{}\n{}", gl_error, Backtrace::force_capture()),
+        GlErrorPolicy::Collect => {
+            ERRORS.with(|errors| errors.borrow_mut().push(gl_error.clone()));
+            None
+        }
+        GlErrorPolicy::Callback(callback) => Some(callback.clone()),
+    });
+    if let Some(callback) = callback {
+        callback(&gl_error);
+    }
+}
+
+pub fn gl_debug_requested() -> bool {
+    std::env::var(GL_DEBUG_VARIABLE).is_ok()
+}
+
+fn source_name(source: gl::types::GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "API",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn type_name(message_type: gl::types::GLenum) -> &'static str {
+    match message_type {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        _ => "other",
+    }
+}
+
+extern "system" fn debug_message_callback(
+    source: gl::types::GLenum,
+    message_type: gl::types::GLenum,
+    id: gl::types::GLuint,
+    severity: gl::types::GLenum,
+    _length: gl::types::GLsizei,
+    message: *const gl::types::GLchar,
+    _user_param: *mut c_void,
+) {
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    let source = source_name(source);
+    let message_type = type_name(message_type);
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => error!("GL {} {} {}: {}", source, message_type, id, message),
+        gl::DEBUG_SEVERITY_MEDIUM => warn!("GL {} {} {}: {}", source, message_type, id, message),
+        gl::DEBUG_SEVERITY_LOW => info!("GL {} {} {}: {}", source, message_type, id, message),
+        _ => debug!("GL {} {} {}: {}", source, message_type, id, message),
+    }
+}
+
+pub fn enable_debug_output() -> Result<(), Error> {
+    if !gl::DebugMessageCallback::is_loaded() {
+        return Err("glDebugMessageCallback is not available in this context".into());
+    }
+    gl_function!(Enable(gl::DEBUG_OUTPUT));
+    gl_function!(Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS));
+    gl_function!(DebugMessageCallback(Some(debug_message_callback), std::ptr::null()));
+    gl_function!(DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, 0, std::ptr::null(), gl::TRUE));
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::path::{Path, PathBuf};
+use image::{Rgba, RgbaImage};
+use log::warn;
+use crate::error::Error;
+use crate::ecs::systems::system::System;
+use crate::game::Game;
+
+const BLESS_VARIABLE: &'static str = "LEARNOPENGL_BLESS";
+
+pub fn render_game(game: &mut Game, systems: Vec<Box<dyn System>>) -> Result<RgbaImage, Error> {
+    if !game.window().is_headless() {
+        return Err("Golden images can only be rendered by headless games".into());
+    }
+    game.play_with_fps_camera(systems)?;
+    game.window().read_pixels()
+}
+
+pub fn diff_images(reference: &RgbaImage, image: &RgbaImage, tolerance: u8) -> (RgbaImage, usize) {
+    let mut mismatched = 0;
+    let diff = RgbaImage::from_fn(reference.width(), reference.height(), |x, y| {
+        let expected = reference.get_pixel(x, y);
+        let actual = image.get_pixel(x, y);
+        let distance = expected.0.iter()
+            .zip(actual.0.iter())
+            .map(|(e, a)| (*e as i16 - *a as i16).unsigned_abs() as u8)
+            .max()
+            .unwrap_or(0);
+        if distance > tolerance {
+            mismatched += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let luma = (expected.0[0] as u16 + expected.0[1] as u16 + expected.0[2] as u16) / 12;
+            Rgba([luma as u8, luma as u8, luma as u8, 255])
+        }
+    });
+    (diff, mismatched)
+}
+
+pub struct GoldenImage {
+    reference: PathBuf,
+    tolerance: u8,
+    max_mismatched_pixels: usize,
+}
+
+impl GoldenImage {
+    pub fn new<P: AsRef<Path>>(reference: P) -> GoldenImage {
+        GoldenImage {
+            reference: reference.as_ref().to_path_buf(),
+            tolerance: 2,
+            max_mismatched_pixels: 0,
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: u8) -> GoldenImage {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_max_mismatched_pixels(mut self, max_mismatched_pixels: usize) -> GoldenImage {
+        self.max_mismatched_pixels = max_mismatched_pixels;
+        self
+    }
+
+    pub fn reference(&self) -> &Path {
+        &self.reference
+    }
+
+    pub fn actual_path(&self) -> PathBuf {
+        self.reference.with_extension("actual.png")
+    }
+
+    pub fn diff_path(&self) -> PathBuf {
+        self.reference.with_extension("diff.png")
+    }
+
+    pub fn check(&self, image: &RgbaImage) -> Result<(), Error> {
+        if std::env::var(BLESS_VARIABLE).is_ok() {
+            warn!("Writing golden image {}", self.reference.display());
+            return image.save(&self.reference).map_err(|e| Error::image_encode(&self.reference, e));
+        }
+        if !self.reference.exists() {
+            image.save(self.actual_path()).map_err(|e| Error::image_encode(self.actual_path(), e))?;
+            return Err(format!(
+                "Golden image {} does not exist, run with {} set to create it",
+                self.reference.display(),
+                BLESS_VARIABLE,
+            ).into());
+        }
+        let reference = image::open(&self.reference)
+            .map_err(|e| Error::image_decode(&self.reference, e))?
+            .to_rgba8();
+        if reference.dimensions() != image.dimensions() {
+            image.save(self.actual_path()).map_err(|e| Error::image_encode(self.actual_path(), e))?;
+            return Err(format!(
+                "Golden image {} is {:?} but the rendered image is {:?}",
+                self.reference.display(),
+                reference.dimensions(),
+                image.dimensions(),
+            ).into());
+        }
+        let (diff, mismatched) = diff_images(&reference, image, self.tolerance);
+        if mismatched > self.max_mismatched_pixels {
+            image.save(self.actual_path()).map_err(|e| Error::image_encode(self.actual_path(), e))?;
+            diff.save(self.diff_path()).map_err(|e| Error::image_encode(self.diff_path(), e))?;
+            return Err(format!(
+                "{} pixels differ from golden image {} by more than {}, diff written to {}",
+                mismatched,
+                self.reference.display(),
+                self.tolerance,
+                self.diff_path().display(),
+            ).into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use std::ptr;
+use khronos_egl as egl;
+use crate::error::Error;
+use crate::frame_buffer::{FrameBuffer, set_default_frame_buffer};
+use crate::gl_error::gl_debug_requested;
+use crate::texture::TextureFormat;
+
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+const HEADLESS_FRAMES_VARIABLE: &'static str = "LEARNOPENGL_HEADLESS_FRAMES";
+const HEADLESS_DELTA_TIME_VARIABLE: &'static str = "LEARNOPENGL_HEADLESS_DELTA_TIME";
+
+#[derive(Clone, Copy, Debug)]
+pub struct HeadlessOptions {
+    pub frames: usize,
+    pub delta_time: f32,
+    pub gl_debug: bool,
+}
+
+impl HeadlessOptions {
+    pub fn new(frames: usize) -> HeadlessOptions {
+        HeadlessOptions {
+            frames,
+            delta_time: 16_666f32,
+            gl_debug: gl_debug_requested(),
+        }
+    }
+
+    pub fn with_delta_time(frames: usize, delta_time: f32) -> HeadlessOptions {
+        HeadlessOptions {
+            frames,
+            delta_time,
+            gl_debug: gl_debug_requested(),
+        }
+    }
+
+    pub fn from_env() -> Option<HeadlessOptions> {
+        let frames = std::env::var(HEADLESS_FRAMES_VARIABLE).ok()?.parse().ok()?;
+        let options = HeadlessOptions::new(frames);
+        Some(match std::env::var(HEADLESS_DELTA_TIME_VARIABLE).ok().and_then(|d| d.parse().ok()) {
+            Some(delta_time) => HeadlessOptions::with_delta_time(frames, delta_time),
+            None => options,
+        })
+    }
+}
+
+pub struct HeadlessContext {
+    frame_buffer: Option<FrameBuffer>,
+    display: egl::Display,
+    context: egl::Context,
+    options: HeadlessOptions,
+    rendered_frames: usize,
+    width: usize,
+    height: usize,
+}
+
+impl HeadlessContext {
+    pub fn new(width: usize, height: usize, options: HeadlessOptions) -> Result<HeadlessContext, Error> {
+        let display = egl::API.get_platform_display(
+            PLATFORM_SURFACELESS_MESA, ptr::null_mut(), &[egl::ATTRIB_NONE],
+        ).map_err(|e| format!("Error getting surfaceless display: {}", e))?;
+        egl::API.initialize(display).map_err(|e| e.to_string())?;
+        egl::API.bind_api(egl::OPENGL_API).map_err(|e| e.to_string())?;
+        let config = egl::API.choose_first_config(display, &[
+            egl::SURFACE_TYPE, egl::PBUFFER_BIT,
+            egl::RENDERABLE_TYPE, egl::OPENGL_BIT,
+            egl::NONE,
+        ]).map_err(|e| e.to_string())?
+            .ok_or("No EGL config supports OpenGL".to_string())?;
+        let context = egl::API.create_context(display, config, None, &[
+            egl::CONTEXT_MAJOR_VERSION, 4,
+            egl::CONTEXT_MINOR_VERSION, 1,
+            egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+            egl::CONTEXT_OPENGL_DEBUG, options.gl_debug as egl::Int,
+            egl::NONE,
+        ]).map_err(|e| e.to_string())?;
+        egl::API.make_current(display, None, None, Some(context)).map_err(|e| e.to_string())?;
+        gl::load_with(|s| {
+            egl::API.get_proc_address(s).map_or(ptr::null(), |f| f as *const std::os::raw::c_void)
+        });
+
+        let frame_buffer = FrameBuffer::new_with_format(width as _, height as _, TextureFormat::UnsignedByteWithAlpha)?;
+        set_default_frame_buffer(frame_buffer.resource);
+        FrameBuffer::unbind();
+        gl_function!(Viewport(0, 0, width as _, height as _));
+        Ok(HeadlessContext {
+            frame_buffer: Some(frame_buffer),
+            display,
+            context,
+            options,
+            rendered_frames: 0,
+            width,
+            height,
+        })
+    }
+
+    pub fn frame_buffer(&self) -> &FrameBuffer {
+        self.frame_buffer.as_ref().unwrap()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn delta_time(&self) -> f32 {
+        self.options.delta_time
+    }
+
+    pub fn ticks(&self) -> u32 {
+        (self.rendered_frames as f32 * self.options.delta_time / 1000f32) as u32
+    }
+
+    pub fn rendered_frames(&self) -> usize {
+        self.rendered_frames
+    }
+
+    pub fn finished(&self) -> bool {
+        self.rendered_frames >= self.options.frames
+    }
+
+    pub fn swap_buffers(&mut self) {
+        gl_function!(Finish());
+        self.rendered_frames += 1;
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        set_default_frame_buffer(0);
+        self.frame_buffer.take();
+        let _ = egl::API.make_current(self.display, None, None, None);
+        let _ = egl::API.destroy_context(self.display, self.context);
+        let _ = egl::API.terminate(self.display);
+    }
+}
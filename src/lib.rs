@@ -8,8 +8,7 @@ macro_rules! gl_function {
                 log::trace!("gl{}({})", stringify!($a), stringify!($($b)*));
                 let error_code = gl::GetError();
                 if error_code != gl::NO_ERROR {
-                    log::error!("ERROR CODE {} on gl{}({})", error_code, stringify!($a), stringify!($($b)*));
-                    std::process::exit(error_code as i32);
+                    $crate::gl_error::report_gl_error(error_code, stringify!($a), stringify!($($b)*));
                 }
             }
             return_value
@@ -20,19 +19,35 @@ macro_rules! gl_function {
 pub mod buffer;
 pub mod camera;
 pub mod program;
+pub mod reflection;
+pub mod uniform;
+pub mod std140;
+pub mod uniform_block;
 pub mod shader;
 pub mod texture;
 pub mod vertex_array;
 pub mod cube;
 pub mod window;
 pub mod shader_loader;
+pub mod preprocessor;
+pub mod shader_diagnostic;
 pub mod light;
 pub mod ecs;
 pub mod game;
 pub mod loader;
 pub mod plane;
+pub mod quad;
 pub mod frame_buffer;
 pub mod render_buffer;
 pub mod multiple_render_target;
 pub mod ping_pong_frame_buffer;
-pub mod sphere;
\ No newline at end of file
+pub mod sphere;
+pub mod error;
+pub mod gl_error;
+pub mod pixels;
+pub mod capture;
+#[cfg(feature = "headless")]
+pub mod headless;
+
+#[cfg(feature = "headless")]
+pub mod golden_image;
@@ -1,5 +1,54 @@
+use std::f32::consts::PI;
 use nalgebra::{Matrix4, Translation3, UnitVector3, Vector3};
+use crate::error::Error;
 use crate::program::Program;
+use crate::std140;
+
+const MINIMUM_LIGHT_LEVEL: f32 = 5.0 / 256.0;
+
+fn attenuation_radius(constant: f32, linear: f32, quadratic: f32, diffuse: Vector3<f32>, specular: Vector3<f32>) -> f32 {
+    let brightness = diffuse.max().max(specular.max());
+    let attenuation = brightness / MINIMUM_LIGHT_LEVEL;
+    if quadratic > 0.0 {
+        (-linear + (linear * linear - 4.0 * quadratic * (constant - attenuation)).max(0.0).sqrt()) / (2.0 * quadratic)
+    } else if linear > 0.0 {
+        ((attenuation - constant) / linear).max(0.0)
+    } else {
+        f32::INFINITY
+    }
+}
+
+fn range_attenuation(range: f32) -> Result<(f32, f32, f32), Error> {
+    if range.is_nan() || range <= 0.0 {
+        return Err(format!("Light range must be positive, got {}", range).into());
+    }
+    Ok((1.0, 4.5 / range, 75.0 / (range * range)))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LightIntensity {
+    Scale(f32),
+    Lumens(f32),
+    Candela(f32),
+}
+
+impl LightIntensity {
+    pub fn point_candela(&self) -> f32 {
+        match self {
+            LightIntensity::Scale(scale) => *scale,
+            LightIntensity::Lumens(lumens) => lumens / (4.0 * PI),
+            LightIntensity::Candela(candela) => *candela,
+        }
+    }
+
+    pub fn spot_candela(&self, outer_cut_off: f32) -> f32 {
+        match self {
+            LightIntensity::Scale(scale) => *scale,
+            LightIntensity::Lumens(lumens) => lumens / (2.0 * PI * (1.0 - outer_cut_off).max(f32::EPSILON)),
+            LightIntensity::Candela(candela) => *candela,
+        }
+    }
+}
 
 pub trait Light {
     fn set_light_in_program(&self, program: &Program, name: &str);
@@ -7,6 +56,53 @@ pub trait Light {
     fn set_light_drawing_program_no_globals(&self, program: &Program, color_name: &str, model_name: &str);
 }
 
+std140! {
+    #[derive(Clone, Debug, Default)]
+    pub struct DirectionalLightData {
+        pub direction: Vector3<f32>,
+        pub ambient: Vector3<f32>,
+        pub diffuse: Vector3<f32>,
+        pub specular: Vector3<f32>,
+        pub set: bool,
+        pub shadow_index: i32,
+    }
+}
+
+std140! {
+    #[derive(Clone, Debug, Default)]
+    pub struct PointLightData {
+        pub position: Vector3<f32>,
+        pub constant: f32,
+        pub linear: f32,
+        pub quadratic: f32,
+        pub ambient: Vector3<f32>,
+        pub diffuse: Vector3<f32>,
+        pub specular: Vector3<f32>,
+        pub set: bool,
+        pub shadow_index: i32,
+        pub range: f32,
+    }
+}
+
+std140! {
+    #[derive(Clone, Debug, Default)]
+    pub struct SpotLightData {
+        pub direction: Vector3<f32>,
+        pub position: Vector3<f32>,
+        pub cut_off: f32,
+        pub outer_cut_off: f32,
+        pub ambient: Vector3<f32>,
+        pub diffuse: Vector3<f32>,
+        pub specular: Vector3<f32>,
+        pub constant: f32,
+        pub linear: f32,
+        pub quadratic: f32,
+        pub set: bool,
+        pub shadow_index: i32,
+        pub range: f32,
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DirectionalLight {
     direction: UnitVector3<f32>,
@@ -29,6 +125,10 @@ impl DirectionalLight {
             specular,
         }
     }
+
+    pub fn direction(&self) -> UnitVector3<f32> {
+        self.direction
+    }
 }
 
 impl Light for DirectionalLight {
@@ -47,6 +147,19 @@ impl Light for DirectionalLight {
     }
 }
 
+impl From<&DirectionalLight> for DirectionalLightData {
+    fn from(light: &DirectionalLight) -> DirectionalLightData {
+        DirectionalLightData {
+            direction: light.direction.xyz(),
+            ambient: light.ambient,
+            diffuse: light.diffuse,
+            specular: light.specular,
+            set: true,
+            shadow_index: -1,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct PointLight {
     pub position: Vector3<f32>,
@@ -56,6 +169,7 @@ pub struct PointLight {
     pub constant: f32,
     pub linear: f32,
     pub quadratic: f32,
+    pub range: Option<f32>,
     pub model: Matrix4<f32>,
 }
 
@@ -79,12 +193,41 @@ impl PointLight {
             constant,
             linear,
             quadratic,
+            range: None,
         }
     }
 
+    pub fn with_range(
+        position: Vector3<f32>,
+        color: Vector3<f32>,
+        intensity: LightIntensity,
+        range: f32,
+    ) -> Result<PointLight, Error> {
+        let (constant, linear, quadratic) = range_attenuation(range)?;
+        let color = color * intensity.point_candela();
+        let mut light = PointLight::new(position, Vector3::zeros(), color, color, constant, linear, quadratic);
+        light.range = Some(range);
+        Ok(light)
+    }
+
+    pub fn with_ambient(mut self, ambient: Vector3<f32>) -> PointLight {
+        self.ambient = ambient;
+        self
+    }
+
     pub fn update_model(&mut self) {
         self.model = Translation3::from(self.position).to_homogeneous();
     }
+
+    pub fn radius(&self) -> f32 {
+        self.range.unwrap_or_else(|| {
+            attenuation_radius(self.constant, self.linear, self.quadratic, self.diffuse, self.specular)
+        })
+    }
+
+    pub fn volume_model(&self) -> Matrix4<f32> {
+        Translation3::from(self.position).to_homogeneous() * Matrix4::new_scaling(self.radius())
+    }
 }
 
 impl Light for PointLight {
@@ -114,6 +257,23 @@ impl Light for PointLight {
     }
 }
 
+impl From<&PointLight> for PointLightData {
+    fn from(light: &PointLight) -> PointLightData {
+        PointLightData {
+            position: light.position,
+            constant: light.constant,
+            linear: light.linear,
+            quadratic: light.quadratic,
+            ambient: light.ambient,
+            diffuse: light.diffuse,
+            specular: light.specular,
+            set: true,
+            shadow_index: -1,
+            range: light.range.unwrap_or(0.0),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SpotLight {
     direction: UnitVector3<f32>,
@@ -126,6 +286,7 @@ pub struct SpotLight {
     constant: f32,
     linear: f32,
     quadratic: f32,
+    range: Option<f32>,
     model: Matrix4<f32>,
 }
 
@@ -159,9 +320,33 @@ impl SpotLight {
             constant,
             linear,
             quadratic,
+            range: None,
         }
     }
 
+    pub fn with_range(
+        direction: UnitVector3<f32>,
+        position: Vector3<f32>,
+        cut_ff: f32,
+        outer_cut_off: f32,
+        color: Vector3<f32>,
+        intensity: LightIntensity,
+        range: f32,
+    ) -> Result<SpotLight, Error> {
+        let (constant, linear, quadratic) = range_attenuation(range)?;
+        let color = color * intensity.spot_candela(outer_cut_off);
+        let mut light = SpotLight::new(
+            direction, position, cut_ff, outer_cut_off, Vector3::zeros(), color, color, constant, linear, quadratic,
+        );
+        light.range = Some(range);
+        Ok(light)
+    }
+
+    pub fn with_ambient(mut self, ambient: Vector3<f32>) -> SpotLight {
+        self.ambient = ambient;
+        self
+    }
+
     pub fn set_position(&mut self, new_position: Vector3<f32>) {
         self.position = new_position;
         self.model = Translation3::from(self.position).to_homogeneous();
@@ -170,6 +355,28 @@ impl SpotLight {
     pub fn set_direction(&mut self, new_direction: UnitVector3<f32>) {
         self.direction = new_direction;
     }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn direction(&self) -> UnitVector3<f32> {
+        self.direction
+    }
+
+    pub fn outer_cut_off(&self) -> f32 {
+        self.outer_cut_off
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.range.unwrap_or_else(|| {
+            attenuation_radius(self.constant, self.linear, self.quadratic, self.diffuse, self.specular)
+        })
+    }
+
+    pub fn volume_model(&self) -> Matrix4<f32> {
+        Translation3::from(self.position).to_homogeneous() * Matrix4::new_scaling(self.radius())
+    }
 }
 
 impl Light for SpotLight {
@@ -201,3 +408,23 @@ impl Light for SpotLight {
         program.set_uniform_v3(color_name, self.specular);
     }
 }
+
+impl From<&SpotLight> for SpotLightData {
+    fn from(light: &SpotLight) -> SpotLightData {
+        SpotLightData {
+            direction: light.direction.xyz(),
+            position: light.position,
+            cut_off: light.cut_ff,
+            outer_cut_off: light.outer_cut_off,
+            ambient: light.ambient,
+            diffuse: light.diffuse,
+            specular: light.specular,
+            constant: light.constant,
+            linear: light.linear,
+            quadratic: light.quadratic,
+            set: true,
+            shadow_index: -1,
+            range: light.range.unwrap_or(0.0),
+        }
+    }
+}
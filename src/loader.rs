@@ -8,6 +8,7 @@ use russimp::node::Node;
 use russimp::scene::{PostProcess, Scene};
 use russimp::texture::{Texture, TextureType};
 use russimp::Vector3D;
+use crate::error::Error;
 use crate::ecs::components::{InstancedModel, Mesh, Model, TextureInfo};
 use crate::ecs::systems::rendering::RenderingSystem;
 
@@ -31,23 +32,23 @@ fn assimp_vec_vector_to_vec_algebra_vector2(input: &Vec<Vector3D>) -> Vec<Vector
         .collect()
 }
 
-fn get_info_textures_from_texture(texture_type: &TextureType, textures: &[Texture], scene_path: &Path) -> Result<Vec<TextureInfo>, String> {
+fn get_info_textures_from_texture(texture_type: &TextureType, textures: &[Texture], scene_path: &Path) -> Result<Vec<TextureInfo>, Error> {
     textures.iter().map(|t| {
         Ok(TextureInfo {
             id: 0,
             texture_type: texture_type.clone(),
             path: scene_path.join(t.path.clone()).to_str().ok_or("Invalid path!")?.to_string()
         })
-    }).collect::<Result<Vec<TextureInfo>, String>>()
+    }).collect::<Result<Vec<TextureInfo>, Error>>()
 }
 
-fn get_textures_from_material(material: &Material, scene_path: &Path) -> Result<Vec<TextureInfo>, String> {
+fn get_textures_from_material(material: &Material, scene_path: &Path) -> Result<Vec<TextureInfo>, Error> {
     Ok(material.textures.iter()
         .filter(|(t, _)| vec![TextureType::Diffuse, TextureType::Specular].contains(t))
         .map(|(texture_type, textures)| {
             get_info_textures_from_texture(texture_type, textures, scene_path)
         })
-        .collect::<Result<Vec<Vec<TextureInfo>>, String>>()?
+        .collect::<Result<Vec<Vec<TextureInfo>>, Error>>()?
         .into_iter()
         .flatten()
         .enumerate()
@@ -58,7 +59,7 @@ fn get_textures_from_material(material: &Material, scene_path: &Path) -> Result<
         .collect())
 }
 
-fn assimp_mesh_to_mesh(assimp_mesh: &AssimpMesh, materials: &[Material], scene_path: &Path) -> Result<Mesh, String> {
+fn assimp_mesh_to_mesh(assimp_mesh: &AssimpMesh, materials: &[Material], scene_path: &Path) -> Result<Mesh, Error> {
     let tangents = if assimp_mesh.tangents.len() == 0 {
         None
     } else {
@@ -85,15 +86,15 @@ fn assimp_mesh_to_mesh(assimp_mesh: &AssimpMesh, materials: &[Material], scene_p
     })
 }
 
-fn extract_mesh_from_scene(scene: &Scene, mesh_id: usize, scene_path: &Path) -> Result<Mesh, String> {
+fn extract_mesh_from_scene(scene: &Scene, mesh_id: usize, scene_path: &Path) -> Result<Mesh, Error> {
     assimp_mesh_to_mesh(&scene.meshes[mesh_id], &scene.materials, scene_path)
 }
 
-fn process_node(scene: &Scene, node: Rc<RefCell<Node>>, meshes: &mut Vec<Mesh>, scene_path: &Path) -> Result<(), String> {
+fn process_node(scene: &Scene, node: Rc<RefCell<Node>>, meshes: &mut Vec<Mesh>, scene_path: &Path) -> Result<(), Error> {
     meshes.extend(
         node.borrow().meshes.iter().cloned()
             .map(|mid| extract_mesh_from_scene(scene, mid as usize, scene_path))
-            .collect::<Result<Vec<Mesh>, String>>()?
+            .collect::<Result<Vec<Mesh>, Error>>()?
     );
     for child in node.borrow().children.iter() {
         process_node(scene, child.clone(), meshes, scene_path)?;
@@ -101,24 +102,24 @@ fn process_node(scene: &Scene, node: Rc<RefCell<Node>>, meshes: &mut Vec<Mesh>,
     Ok(())
 }
 
-pub fn load_instanced_model(model_path: &str, rendering_system: &mut RenderingSystem, models: Vec<Matrix4<f32>>) -> Result<InstancedModel, String> {
+pub fn load_instanced_model(model_path: &str, rendering_system: &mut RenderingSystem, models: Vec<Matrix4<f32>>) -> Result<InstancedModel, Error> {
     let meshes = load_object(model_path)?;
     InstancedModel::new(meshes, rendering_system, models)
 }
 
-pub fn load_model(model_path: &str, rendering_system: &mut RenderingSystem) -> Result<Model, String> {
+pub fn load_model(model_path: &str, rendering_system: &mut RenderingSystem) -> Result<Model, Error> {
     let meshes = load_object(model_path)?;
     Model::from_meshes(meshes, rendering_system)
 }
 
-fn load_object(model_path: &str) -> Result<Vec<Mesh>, String> {
+fn load_object(model_path: &str) -> Result<Vec<Mesh>, Error> {
     let path = Path::new(model_path)
         .parent()
         .ok_or("Invalid path!".to_string())?;
     let scene = Scene::from_file(
         model_path,
         vec![PostProcess::Triangulate, PostProcess::FlipUVs, PostProcess::CalculateTangentSpace]
-    ).map_err(|e| e.to_string())?;
+    ).map_err(|e| Error::ModelImport { path: model_path.into(), source: e })?;
     let root = scene.root.clone().ok_or("No root node".to_string())?;
     let mut meshes = vec![];
     process_node(&scene, root, &mut meshes, path)?;
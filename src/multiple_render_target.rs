@@ -1,5 +1,8 @@
+use image::{Rgba32FImage, RgbaImage};
 use itertools::Itertools;
-use log::error;
+use crate::error::Error;
+use crate::frame_buffer::{check_frame_buffer_status, default_frame_buffer};
+use crate::pixels::{DepthImage, read_depth, read_rgba32f, read_rgba8};
 use crate::render_buffer::RenderBuffer;
 use crate::texture::{Texture, TextureFormat, TextureType};
 
@@ -23,20 +26,22 @@ pub struct MultipleRenderTarget {
     _render_buffer: Option<RenderBuffer>,
     pub resource: gl::types::GLuint,
     pub textures: Vec<Texture>,
+    width: u32,
+    height: u32,
 }
 
 impl MultipleRenderTarget {
-    pub fn new(width: u32, height: u32, targets: usize) -> MultipleRenderTarget {
+    pub fn new(width: u32, height: u32, targets: usize) -> Result<MultipleRenderTarget, Error> {
         MultipleRenderTarget::new_with_format(width, height, targets, TextureFormat::UnsignedByte)
     }
 
-    pub fn new_with_format(width: u32, height: u32, targets: usize, format: TextureFormat) -> MultipleRenderTarget {
+    pub fn new_with_format(width: u32, height: u32, targets: usize, format: TextureFormat) -> Result<MultipleRenderTarget, Error> {
         MultipleRenderTarget::new_with_formats(
             width, height, &std::iter::repeat(format).take(targets).collect_vec(),
         )
     }
 
-    pub fn new_with_formats(width: u32, height: u32, formats: &[TextureFormat]) -> MultipleRenderTarget {
+    pub fn new_with_formats(width: u32, height: u32, formats: &[TextureFormat]) -> Result<MultipleRenderTarget, Error> {
         let mut frame_buffer = 0 as gl::types::GLuint;
         gl_function!(GenFramebuffers(1, &mut frame_buffer));
         gl_function!(BindFramebuffer(gl::FRAMEBUFFER, frame_buffer));
@@ -49,16 +54,15 @@ impl MultipleRenderTarget {
         RenderBuffer::unbind();
         gl_function!(FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, render_buffer.0));
 
-        let status = gl_function!(CheckFramebufferStatus(gl::FRAMEBUFFER));
-        if status != gl::FRAMEBUFFER_COMPLETE {
-            error!("Error creating frame buffer, status code {}", status);
-        }
+        let status = check_frame_buffer_status();
         MultipleRenderTarget::unbind();
-        MultipleRenderTarget {
+        status.and(Ok(MultipleRenderTarget {
             textures,
             _render_buffer: Some(render_buffer),
             resource: frame_buffer,
-        }
+            width,
+            height,
+        }))
     }
 
     pub fn set_draw_buffers(&self) {
@@ -68,12 +72,46 @@ impl MultipleRenderTarget {
         gl_function!(DrawBuffers(self.textures.len() as _, attachments.as_ptr()));
     }
 
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn status(&self) -> Result<(), Error> {
+        self.bind();
+        let status = check_frame_buffer_status();
+        MultipleRenderTarget::unbind();
+        status
+    }
+
+    fn attachment(&self, target: usize) -> Result<gl::types::GLenum, Error> {
+        if target >= self.textures.len() {
+            return Err(format!("Render target {} out of {} targets", target, self.textures.len()).into());
+        }
+        Ok(gl::COLOR_ATTACHMENT0 + target as u32)
+    }
+
+    pub fn read_pixels(&self, target: usize) -> Result<RgbaImage, Error> {
+        read_rgba8(self.resource, Some(self.attachment(target)?), self.width, self.height)
+    }
+
+    pub fn read_pixels_hdr(&self, target: usize) -> Result<Rgba32FImage, Error> {
+        read_rgba32f(self.resource, Some(self.attachment(target)?), self.width, self.height)
+    }
+
+    pub fn read_depth(&self) -> Result<DepthImage, Error> {
+        read_depth(self.resource, self.width, self.height)
+    }
+
     pub fn bind(&self) {
         gl_function!(BindFramebuffer(gl::FRAMEBUFFER, self.resource));
     }
 
     pub fn unbind() {
-        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, 0));
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, default_frame_buffer()));
     }
 }
 
@@ -1,3 +1,4 @@
+use crate::frame_buffer::default_frame_buffer;
 use crate::texture::{Texture, TextureFormat, TextureType};
 
 #[derive(Debug)]
@@ -51,8 +52,12 @@ impl PingPongFrameBuffer {
         texture.bind(gl::TEXTURE0 + texture_index);
     }
 
+    pub fn texture(&self, ping: bool) -> &Texture {
+        if ping { &self.ping_texture } else { &self.pong_texture }
+    }
+
     pub fn unbind() {
-        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, 0));
+        gl_function!(BindFramebuffer(gl::FRAMEBUFFER, default_frame_buffer()));
     }
 }
 
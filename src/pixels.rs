@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use num_traits::Zero;
+use image::{ImageBuffer, Luma, Pixel, Rgba, Rgba32FImage, RgbaImage};
+use crate::error::Error;
+use crate::frame_buffer::FrameBuffer;
+
+pub type DepthImage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreenshotFormat {
+    Png,
+    Exr,
+}
+
+impl ScreenshotFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Exr => "exr",
+        }
+    }
+}
+
+fn read<P: Pixel>(
+    frame_buffer: gl::types::GLuint,
+    read_buffer: Option<gl::types::GLenum>,
+    width: u32,
+    height: u32,
+    format: gl::types::GLenum,
+    data_type: gl::types::GLenum,
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>, Error> {
+    let mut data = vec![P::Subpixel::zero(); (width * height) as usize * P::CHANNEL_COUNT as usize];
+    let mut previous_read_buffer = 0;
+    gl_function!(BindFramebuffer(gl::READ_FRAMEBUFFER, frame_buffer));
+    if let Some(buffer) = read_buffer {
+        gl_function!(GetIntegerv(gl::READ_BUFFER, &mut previous_read_buffer));
+        gl_function!(ReadBuffer(buffer));
+    }
+    gl_function!(PixelStorei(gl::PACK_ALIGNMENT, 1));
+    gl_function!(ReadPixels(0, 0, width as _, height as _, format, data_type, data.as_mut_ptr() as *mut _));
+    if read_buffer.is_some() {
+        gl_function!(ReadBuffer(previous_read_buffer as _));
+    }
+    FrameBuffer::read_unbind();
+    let mut image = ImageBuffer::from_raw(width, height, data)
+        .ok_or(Error::from("Error building image from frame buffer"))?;
+    image::imageops::flip_vertical_in_place(&mut image);
+    Ok(image)
+}
+
+pub(crate) fn read_rgba8(
+    frame_buffer: gl::types::GLuint,
+    read_buffer: Option<gl::types::GLenum>,
+    width: u32,
+    height: u32,
+) -> Result<RgbaImage, Error> {
+    read::<Rgba<u8>>(frame_buffer, read_buffer, width, height, gl::RGBA, gl::UNSIGNED_BYTE)
+}
+
+pub(crate) fn read_rgba32f(
+    frame_buffer: gl::types::GLuint,
+    read_buffer: Option<gl::types::GLenum>,
+    width: u32,
+    height: u32,
+) -> Result<Rgba32FImage, Error> {
+    read::<Rgba<f32>>(frame_buffer, read_buffer, width, height, gl::RGBA, gl::FLOAT)
+}
+
+pub(crate) fn read_depth(frame_buffer: gl::types::GLuint, width: u32, height: u32) -> Result<DepthImage, Error> {
+    read::<Luma<f32>>(frame_buffer, None, width, height, gl::DEPTH_COMPONENT, gl::FLOAT)
+}
+
+pub fn timestamped_path<P: AsRef<Path>>(directory: P, prefix: &str, extension: &str) -> Result<PathBuf, Error> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?;
+    Ok(directory.as_ref().join(format!(
+        "{}-{}-{:03}.{}", prefix, timestamp.as_secs(), timestamp.subsec_millis(), extension,
+    )))
+}
@@ -0,0 +1,299 @@
+use std::collections::HashSet;
+use regex::Regex;
+use crate::error::Error;
+
+pub struct SourceFile {
+    pub name: String,
+    pub content: String,
+}
+
+pub struct PreprocessedSource {
+    pub code: String,
+    pub files: Vec<SourceFile>,
+}
+
+impl PreprocessedSource {
+    pub fn file(&self, index: usize) -> Option<&SourceFile> {
+        self.files.get(index)
+    }
+}
+
+struct Directives {
+    include: Regex,
+    version: Regex,
+    extension: Regex,
+    pragma_once: Regex,
+    ifndef: Regex,
+    conditional: Regex,
+    define: Regex,
+    endif: Regex,
+}
+
+impl Directives {
+    fn new() -> Directives {
+        Directives {
+            include: Regex::new(r#"^\s*#\s*include\s+"([^"]+)"\s*(//.*)?$"#).unwrap(),
+            version: Regex::new(r"^\s*#\s*version\b").unwrap(),
+            extension: Regex::new(r"^\s*#\s*extension\b").unwrap(),
+            pragma_once: Regex::new(r"^\s*#\s*pragma\s+once\s*$").unwrap(),
+            ifndef: Regex::new(r"^\s*#\s*ifndef\s+(\w+)\s*$").unwrap(),
+            conditional: Regex::new(r"^\s*#\s*if(def|ndef)?\b").unwrap(),
+            define: Regex::new(r"^\s*#\s*define\s+(\w+)").unwrap(),
+            endif: Regex::new(r"^\s*#\s*endif\b").unwrap(),
+        }
+    }
+
+    fn include_guard(&self, content: &str) -> Option<String> {
+        let mut lines = content.lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with("//"));
+        let guard = self.ifndef.captures(lines.next()?)?[1].to_string();
+        let defined = self.define.captures(lines.next()?)?[1].to_string();
+        if guard != defined || !self.endif.is_match(lines.last()?) {
+            return None;
+        }
+        Some(guard)
+    }
+}
+
+pub struct Preprocessor<'a> {
+    read: &'a mut dyn FnMut(&str) -> Result<String, Error>,
+    directives: Directives,
+    conditionals: usize,
+    extensions: Vec<String>,
+    files: Vec<SourceFile>,
+    stack: Vec<String>,
+    included: HashSet<String>,
+    once: HashSet<String>,
+}
+
+impl<'a> Preprocessor<'a> {
+    pub fn new(read: &'a mut dyn FnMut(&str) -> Result<String, Error>) -> Preprocessor<'a> {
+        Preprocessor {
+            read,
+            directives: Directives::new(),
+            conditionals: 0,
+            extensions: vec![],
+            files: vec![],
+            stack: vec![],
+            included: HashSet::new(),
+            once: HashSet::new(),
+        }
+    }
+
+    pub fn enable_extensions(mut self, extensions: &[String]) -> Preprocessor<'a> {
+        for extension in extensions {
+            let extension = format!("#extension {} : enable", extension);
+            if !self.extensions.contains(&extension) {
+                self.extensions.push(extension);
+            }
+        }
+        self
+    }
+
+    pub fn process(mut self, glsl: &str, defines: &[(String, String)]) -> Result<PreprocessedSource, Error> {
+        let content = (self.read)(glsl)?;
+        if let Some(guard) = self.directives.include_guard(&content) {
+            self.included.insert(guard);
+        }
+        self.file_index(glsl, &content);
+        let lines = content.lines().collect::<Vec<_>>();
+        let version = lines.iter()
+            .position(|l| !l.trim().is_empty() && !l.trim().starts_with("//"))
+            .filter(|i| self.directives.version.is_match(lines[*i]));
+        let mut code = String::new();
+        let first_line = match version {
+            Some(i) => {
+                for line in &lines[..=i] {
+                    code.push_str(line);
+                    code.push('\n');
+                }
+                i + 2
+            }
+            None => 1,
+        };
+        let rest = lines[first_line - 1..].join("\n");
+        let mut body = String::new();
+        self.process_file(glsl, &rest, first_line, &mut body)?;
+        for extension in self.extensions.iter() {
+            code.push_str(extension);
+            code.push('\n');
+        }
+        for (name, value) in defines {
+            code.push_str(&format!("#define {} {}\n", name, value));
+        }
+        code.push_str(&body);
+        Ok(PreprocessedSource {
+            code,
+            files: self.files,
+        })
+    }
+
+    fn file_index(&mut self, glsl: &str, content: &str) -> usize {
+        match self.files.iter().position(|f| f.name == glsl) {
+            Some(index) => index,
+            None => {
+                self.files.push(SourceFile {
+                    name: glsl.to_string(),
+                    content: content.to_string(),
+                });
+                self.files.len() - 1
+            }
+        }
+    }
+
+    fn include(&mut self, glsl: &str, code: &mut String) -> Result<(), Error> {
+        if self.once.contains(glsl) {
+            return Ok(());
+        }
+        let content = (self.read)(glsl)?;
+        let guard = self.directives.include_guard(&content);
+        if guard.as_ref().map(|g| self.included.contains(g)).unwrap_or(false) {
+            return Ok(());
+        }
+        if self.stack.iter().any(|f| f == glsl) {
+            let mut chain = self.stack.clone();
+            chain.push(glsl.to_string());
+            return Err(Error::IncludeCycle { chain });
+        }
+        if let Some(guard) = guard {
+            self.included.insert(guard);
+        }
+        self.process_file(glsl, &content, 1, code)
+    }
+
+    fn process_file(&mut self, glsl: &str, content: &str, first_line: usize, code: &mut String) -> Result<(), Error> {
+        let index = self.file_index(glsl, content);
+        let top_level = self.conditionals + self.directives.include_guard(content).is_some() as usize;
+        self.stack.push(glsl.to_string());
+        code.push_str(&format!("#line {} {}\n", first_line, index));
+        for (i, line) in content.lines().enumerate() {
+            let line_number = first_line + i;
+            if self.directives.pragma_once.is_match(line) {
+                self.once.insert(glsl.to_string());
+                code.push('\n');
+            } else if self.directives.extension.is_match(line) && self.conditionals <= top_level {
+                let extension = line.trim().to_string();
+                if !self.extensions.contains(&extension) {
+                    self.extensions.push(extension);
+                }
+                code.push('\n');
+            } else if let Some(captures) = self.directives.include.captures(line) {
+                self.include(&captures[1], code)?;
+                code.push_str(&format!("#line {} {}\n", line_number + 1, index));
+            } else if line.trim_start().starts_with("#include") {
+                return Err(format!("{}:{}: malformed include directive: {}", glsl, line_number, line.trim()).into());
+            } else {
+                if self.directives.conditional.is_match(line) {
+                    self.conditionals += 1;
+                } else if self.directives.endif.is_match(line) {
+                    self.conditionals = self.conditionals.saturating_sub(1);
+                }
+                code.push_str(line);
+                code.push('\n');
+            }
+        }
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    fn preprocess(files: &[(&str, &str)], defines: &[(&str, &str)]) -> Result<PreprocessedSource, Error> {
+        let main = files[0].0;
+        let files = files.iter()
+            .map(|(name, content)| (name.to_string(), content.to_string()))
+            .collect::<HashMap<_, _>>();
+        let mut read = |name: &str| files.get(name).cloned().ok_or_else(|| Error::from(format!("{} not found", name)));
+        let defines = defines.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>();
+        Preprocessor::new(&mut read).process(main, &defines)
+    }
+
+    #[test]
+    fn defines_are_injected_after_version() {
+        let source = preprocess(&[("a.glsl", "#version 410 core\nvoid main() {}\n")], &[("SSAO", "1")]).unwrap();
+        assert_eq!(source.code, "#version 410 core\n#define SSAO 1\n#line 2 0\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn line_directives_follow_includes() {
+        let source = preprocess(&[
+            ("a.glsl", "#version 410\nuniform float a;\n#include \"b.glsl\"\nvoid main() {}\n"),
+            ("b.glsl", "float b;\n"),
+        ], &[]).unwrap();
+        assert_eq!(
+            source.code,
+            "#version 410\n#line 2 0\nuniform float a;\n#line 1 1\nfloat b;\n#line 4 0\nvoid main() {}\n",
+        );
+        assert_eq!(source.file(1).map(|f| f.name.as_str()), Some("b.glsl"));
+    }
+
+    #[test]
+    fn extensions_are_hoisted_above_defines() {
+        let source = preprocess(&[
+            ("a.glsl", "#version 410\nin vec3 normal;\n#include \"b.glsl\"\n"),
+            ("b.glsl", "#extension GL_ARB_shader_storage_buffer_object : enable\nfloat b;\n"),
+        ], &[("MAX_LIGHTS", "8")]).unwrap();
+        assert_eq!(
+            source.code,
+            "#version 410\n#extension GL_ARB_shader_storage_buffer_object : enable\n#define MAX_LIGHTS 8\n\
+             #line 2 0\nin vec3 normal;\n#line 1 1\n\nfloat b;\n#line 4 0\n",
+        );
+    }
+
+    #[test]
+    fn conditional_extensions_are_not_hoisted() {
+        let source = preprocess(&[
+            ("a.glsl", "#version 410\nin vec3 normal;\n#include \"b.glsl\"\n"),
+            ("b.glsl", "#ifndef B\n#define B\n#extension GL_A : enable\n#ifdef C\n#extension GL_C : enable\n#endif\n#endif\n"),
+        ], &[]).unwrap();
+        assert_eq!(
+            source.code,
+            "#version 410\n#extension GL_A : enable\n#line 2 0\nin vec3 normal;\n\
+             #line 1 1\n#ifndef B\n#define B\n\n#ifdef C\n#extension GL_C : enable\n#endif\n#endif\n#line 4 0\n",
+        );
+    }
+
+    #[test]
+    fn enabled_extensions_are_not_repeated() {
+        let mut read = |_: &str| {
+            Ok("#version 410\n#extension GL_ARB_shader_storage_buffer_object : enable\nvoid main() {}\n".to_string())
+        };
+        let source = Preprocessor::new(&mut read)
+            .enable_extensions(&["GL_ARB_shader_storage_buffer_object".to_string()])
+            .process("a.glsl", &[])
+            .unwrap();
+        assert_eq!(
+            source.code,
+            "#version 410\n#extension GL_ARB_shader_storage_buffer_object : enable\n#line 2 0\n\nvoid main() {}\n",
+        );
+    }
+
+    #[test]
+    fn pragma_once_includes_a_file_once() {
+        let source = preprocess(&[
+            ("a.glsl", "#include \"b.glsl\"\n#include \"b.glsl\"\nvoid main() {}\n"),
+            ("b.glsl", "#pragma once\nfloat b;\n"),
+        ], &[]).unwrap();
+        assert_eq!(source.code.matches("float b;").count(), 1);
+    }
+
+    #[test]
+    fn include_cycles_are_reported() {
+        let result = preprocess(&[
+            ("a.glsl", "#include \"b.glsl\"\n"),
+            ("b.glsl", "#include \"a.glsl\"\n"),
+        ], &[]);
+        match result {
+            Err(Error::IncludeCycle { chain }) => assert_eq!(chain, vec!["a.glsl", "b.glsl", "a.glsl"]),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("expected an include cycle"),
+        }
+    }
+}
@@ -1,9 +1,13 @@
-use std::cell::RefCell;
+use std::cell::{Cell, Ref, RefCell};
 use std::collections::HashMap;
 use std::ffi::CString;
 use std::mem::transmute;
+use std::rc::{Rc, Weak};
+use crate::error::Error;
+use crate::reflection::ProgramReflection;
 use super::gl_function;
 use crate::shader::Shader;
+use crate::uniform::UniformType;
 use gl;
 use nalgebra::{Matrix4, Vector3};
 use std::ptr;
@@ -12,7 +16,7 @@ use log::warn;
 fn check_success(
     resource: gl::types::GLuint,
     success_type: gl::types::GLenum,
-) -> Result<(), String> {
+) -> Result<(), Error> {
     let mut status = gl::FALSE as gl::types::GLint;
     gl_function!(GetProgramiv(resource, success_type, &mut status));
 
@@ -31,33 +35,128 @@ fn check_success(
             .expect("ProgramInfoLog not valid utf8")
             .to_string();
         log::error!("{}", &s);
-        Err(s)
+        Err(Error::ProgramLink {
+            log: s,
+        })
     } else {
         Ok(())
     }
 }
 
-pub struct Program {
-    resource: gl::types::GLuint,
+fn link(shaders: &[Shader]) -> Result<gl::types::GLuint, Error> {
+    let resource = gl_function!(CreateProgram());
+    for shader in shaders.iter() {
+        gl_function!(AttachShader(resource, shader.0));
+    }
+    gl_function!(LinkProgram(resource));
+    if let Err(e) = check_success(resource, gl::LINK_STATUS) {
+        gl_function!(DeleteProgram(resource));
+        return Err(e);
+    }
+    Ok(resource)
+}
+
+type Setup = Rc<dyn Fn(&Program)>;
+
+pub(crate) struct ProgramState {
+    resource: Cell<gl::types::GLuint>,
+    setup: RefCell<Vec<Setup>>,
+    storage_blocks: RefCell<Vec<(String, usize)>>,
+    uniform_blocks: RefCell<Vec<(String, usize)>>,
     uniforms: RefCell<HashMap<String, gl::types::GLint>>,
+    reflection: RefCell<ProgramReflection>,
 }
 
-impl Program {
-    pub fn new(shaders: Vec<Shader>) -> Result<Program, String> {
-        let resource = gl_function!(CreateProgram());
-        for shader in shaders.iter() {
-            gl_function!(AttachShader(resource, shader.0));
+impl ProgramState {
+    pub(crate) fn relink(self: &Rc<Self>, shaders: Vec<Shader>) -> Result<(), Error> {
+        let resource = link(&shaders)?;
+        gl_function!(DeleteProgram(self.resource.get()));
+        self.resource.set(resource);
+        self.uniforms.borrow_mut().clear();
+        let mut reflection = ProgramReflection::query(resource);
+        for (uniform, binding_point) in self.uniform_blocks.borrow().iter() {
+            bind_uniform_block(resource, &mut reflection, uniform, *binding_point);
+        }
+        for (block, binding_point) in self.storage_blocks.borrow().iter() {
+            bind_storage_block(resource, block, *binding_point);
         }
-        gl_function!(LinkProgram(resource));
-        check_success(resource, gl::LINK_STATUS)?;
+        *self.reflection.borrow_mut() = reflection;
+        let program = Program { state: self.clone() };
+        let setup = self.setup.borrow().clone();
+        for setup in setup.iter() {
+            setup(&program);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ProgramState {
+    fn drop(&mut self) {
+        gl_function!(DeleteProgram(self.resource.get()));
+    }
+}
+
+fn bind_uniform_block(resource: gl::types::GLuint, reflection: &mut ProgramReflection, uniform: &str, binding_point: usize) {
+    if let Some(block_index) = reflection.set_block_binding(uniform, binding_point) {
+        gl_function!(UniformBlockBinding(resource, block_index as u32, binding_point as u32));
+    }
+}
+
+fn bind_storage_block(resource: gl::types::GLuint, block: &str, binding_point: usize) {
+    let c_string = CString::new(block).unwrap();
+    let block_index = gl_function!(GetProgramResourceIndex(resource, gl::SHADER_STORAGE_BLOCK, c_string.as_ptr()));
+    if block_index != gl::INVALID_INDEX {
+        gl_function!(ShaderStorageBlockBinding(resource, block_index, binding_point as u32));
+    }
+}
+
+pub struct Program {
+    state: Rc<ProgramState>,
+}
+
+impl Program {
+    pub fn new(shaders: Vec<Shader>) -> Result<Program, Error> {
+        let resource = link(&shaders)?;
         Ok(Program {
-            resource,
-            uniforms: RefCell::new(HashMap::new()),
+            state: Rc::new(ProgramState {
+                resource: Cell::new(resource),
+                setup: RefCell::new(vec![]),
+                storage_blocks: RefCell::new(vec![]),
+                uniform_blocks: RefCell::new(vec![]),
+                uniforms: RefCell::new(HashMap::new()),
+                reflection: RefCell::new(ProgramReflection::query(resource)),
+            }),
         })
     }
 
+    pub(crate) fn state(&self) -> Weak<ProgramState> {
+        Rc::downgrade(&self.state)
+    }
+
+    pub fn relink(&self, shaders: Vec<Shader>) -> Result<(), Error> {
+        self.state.relink(shaders)
+    }
+
+    pub fn setup<F: Fn(&Program) + 'static>(&self, setup: F) {
+        setup(self);
+        self.state.setup.borrow_mut().push(Rc::new(setup));
+    }
+
+    fn resource(&self) -> gl::types::GLuint {
+        self.state.resource.get()
+    }
+
+    pub fn reflection(&self) -> Ref<'_, ProgramReflection> {
+        self.state.reflection.borrow()
+    }
+
     pub fn use_program(&self) {
-        gl_function!(UseProgram(self.resource));
+        gl_function!(UseProgram(self.resource()));
+    }
+
+    pub fn set_uniform<T: UniformType + ?Sized>(&self, uniform: &str, value: &T) {
+        let location = self.find_uniform(uniform);
+        value.set(location);
     }
 
     pub fn set_uniform_f1(&self, uniform: &str, x: f32) {
@@ -86,13 +185,17 @@ impl Program {
     }
 
     pub fn bind_uniform_block(&self, uniform: &str, binding_point: usize) {
-        let c_string = CString::new(uniform).unwrap();
-        let block_index = gl_function!(GetUniformBlockIndex(self.resource, transmute(c_string.as_ptr())));
-        gl_function!(UniformBlockBinding(self.resource, block_index, binding_point as u32));
+        bind_uniform_block(self.resource(), &mut self.state.reflection.borrow_mut(), uniform, binding_point);
+        self.state.uniform_blocks.borrow_mut().push((uniform.to_string(), binding_point));
+    }
+
+    pub fn bind_storage_block(&self, block: &str, binding_point: usize) {
+        bind_storage_block(self.resource(), block, binding_point);
+        self.state.storage_blocks.borrow_mut().push((block.to_string(), binding_point));
     }
 
     fn find_uniform(&self, uniform: &str) -> gl::types::GLint {
-        let mut cache = self.uniforms.borrow_mut();
+        let mut cache = self.state.uniforms.borrow_mut();
         match cache.get(uniform) {
             Some(location) if *location == -1 => {
                 warn!("Uniform {} does not exist", uniform);
@@ -102,7 +205,7 @@ impl Program {
             None => {
                 let c_str = CString::new(uniform).unwrap();
                 let location = gl_function!(GetUniformLocation(
-                    self.resource,
+                    self.resource(),
                     transmute(c_str.as_ptr())
                 ));
                 if location == -1 {
@@ -114,9 +217,3 @@ impl Program {
         }
     }
 }
-
-impl Drop for Program {
-    fn drop(&mut self) {
-        gl_function!(DeleteProgram(self.resource));
-    }
-}
@@ -0,0 +1,46 @@
+use crate::buffer::Buffer;
+use crate::gl_function;
+use crate::vertex_array::VertexArray;
+
+pub const VERTICES: [f32; 24] = [
+    -1f32, 1f32, 0f32, 1f32,
+    -1f32, -1f32, 0f32, 0f32,
+    1f32, -1f32, 1f32, 0f32,
+    -1f32, 1f32, 0f32, 1f32,
+    1f32, -1f32, 1f32, 0f32,
+    1f32, 1f32, 1f32, 1f32,
+];
+
+pub struct Quad {
+    vertex_array: VertexArray,
+    _vertex_buffer: Buffer,
+}
+
+impl Quad {
+    pub fn new() -> Quad {
+        let vertex_array = VertexArray::new();
+        let vertex_buffer = Buffer::new(gl::ARRAY_BUFFER);
+        vertex_array.bind();
+        vertex_buffer.bind();
+        vertex_buffer.set_data(&VERTICES, gl::STATIC_DRAW);
+        VertexArray::set_vertex_attrib_with_padding::<f32>(gl::FLOAT, 0, 4, 2, 0, false);
+        VertexArray::set_vertex_attrib_with_padding::<f32>(gl::FLOAT, 1, 4, 2, 2, false);
+        VertexArray::unbind();
+        Quad {
+            vertex_array,
+            _vertex_buffer: vertex_buffer,
+        }
+    }
+
+    pub fn draw(&self) {
+        self.vertex_array.bind();
+        gl_function!(DrawArrays(gl::TRIANGLES, 0, 6));
+        VertexArray::unbind();
+    }
+}
+
+impl Default for Quad {
+    fn default() -> Quad {
+        Quad::new()
+    }
+}
@@ -0,0 +1,254 @@
+use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint};
+use crate::gl_function;
+
+#[derive(Clone, Debug)]
+pub struct UniformInfo {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub array_size: usize,
+    pub location: GLint,
+    pub block_index: Option<usize>,
+    pub offset: Option<usize>,
+    pub array_stride: Option<usize>,
+    pub matrix_stride: Option<usize>,
+}
+
+impl UniformInfo {
+    pub fn is_sampler(&self) -> bool {
+        is_sampler(self.gl_type)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UniformBlockInfo {
+    pub name: String,
+    pub index: usize,
+    pub binding: usize,
+    pub data_size: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct AttributeInfo {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub array_size: usize,
+    pub location: GLint,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProgramReflection {
+    uniforms: Vec<UniformInfo>,
+    blocks: Vec<UniformBlockInfo>,
+    attributes: Vec<AttributeInfo>,
+}
+
+fn program_parameter(program: GLuint, parameter: GLenum) -> GLint {
+    let mut value = 0;
+    gl_function!(GetProgramiv(program, parameter, &mut value));
+    value
+}
+
+fn name_from_buffer(buffer: &[u8], length: GLsizei) -> String {
+    String::from_utf8_lossy(&buffer[..length as usize]).to_string()
+}
+
+fn uniforms_parameter(program: GLuint, indices: &[GLuint], parameter: GLenum) -> Vec<GLint> {
+    let mut values = vec![0; indices.len()];
+    if !indices.is_empty() {
+        gl_function!(GetActiveUniformsiv(program, indices.len() as _, indices.as_ptr(), parameter, values.as_mut_ptr()));
+    }
+    values
+}
+
+fn query_uniforms(program: GLuint) -> Vec<UniformInfo> {
+    let count = program_parameter(program, gl::ACTIVE_UNIFORMS) as GLuint;
+    let mut buffer = vec![0u8; program_parameter(program, gl::ACTIVE_UNIFORM_MAX_LENGTH).max(1) as usize];
+    let indices = (0..count).collect::<Vec<_>>();
+    let block_indices = uniforms_parameter(program, &indices, gl::UNIFORM_BLOCK_INDEX);
+    let offsets = uniforms_parameter(program, &indices, gl::UNIFORM_OFFSET);
+    let array_strides = uniforms_parameter(program, &indices, gl::UNIFORM_ARRAY_STRIDE);
+    let matrix_strides = uniforms_parameter(program, &indices, gl::UNIFORM_MATRIX_STRIDE);
+    indices.into_iter().map(|i| {
+        let mut length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        gl_function!(GetActiveUniform(
+            program, i, buffer.len() as _, &mut length, &mut size, &mut gl_type, buffer.as_mut_ptr() as *mut GLchar
+        ));
+        let name = name_from_buffer(&buffer, length);
+        let block_index = block_indices[i as usize];
+        let in_block = |value: GLint| if block_index >= 0 { Some(value as usize) } else { None };
+        let location = if block_index >= 0 {
+            -1
+        } else {
+            let c_name = std::ffi::CString::new(name.as_str()).unwrap();
+            gl_function!(GetUniformLocation(program, c_name.as_ptr()))
+        };
+        UniformInfo {
+            name,
+            gl_type,
+            array_size: size as usize,
+            location,
+            block_index: in_block(block_index),
+            offset: in_block(offsets[i as usize]),
+            array_stride: in_block(array_strides[i as usize]),
+            matrix_stride: in_block(matrix_strides[i as usize]),
+        }
+    }).collect()
+}
+
+fn query_blocks(program: GLuint) -> Vec<UniformBlockInfo> {
+    let count = program_parameter(program, gl::ACTIVE_UNIFORM_BLOCKS) as GLuint;
+    let mut buffer = vec![0u8; program_parameter(program, gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH).max(1) as usize];
+    (0..count).map(|i| {
+        let mut length = 0;
+        gl_function!(GetActiveUniformBlockName(program, i, buffer.len() as _, &mut length, buffer.as_mut_ptr() as *mut GLchar));
+        let mut binding = 0;
+        let mut data_size = 0;
+        gl_function!(GetActiveUniformBlockiv(program, i, gl::UNIFORM_BLOCK_BINDING, &mut binding));
+        gl_function!(GetActiveUniformBlockiv(program, i, gl::UNIFORM_BLOCK_DATA_SIZE, &mut data_size));
+        UniformBlockInfo {
+            name: name_from_buffer(&buffer, length),
+            index: i as usize,
+            binding: binding as usize,
+            data_size: data_size as usize,
+        }
+    }).collect()
+}
+
+fn query_attributes(program: GLuint) -> Vec<AttributeInfo> {
+    let count = program_parameter(program, gl::ACTIVE_ATTRIBUTES) as GLuint;
+    let mut buffer = vec![0u8; program_parameter(program, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH).max(1) as usize];
+    (0..count).map(|i| {
+        let mut length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        gl_function!(GetActiveAttrib(
+            program, i, buffer.len() as _, &mut length, &mut size, &mut gl_type, buffer.as_mut_ptr() as *mut GLchar
+        ));
+        let name = name_from_buffer(&buffer, length);
+        let c_name = std::ffi::CString::new(name.as_str()).unwrap();
+        let location = gl_function!(GetAttribLocation(program, c_name.as_ptr()));
+        AttributeInfo {
+            name,
+            gl_type,
+            array_size: size as usize,
+            location,
+        }
+    }).collect()
+}
+
+impl ProgramReflection {
+    pub(crate) fn query(program: GLuint) -> ProgramReflection {
+        ProgramReflection {
+            uniforms: query_uniforms(program),
+            blocks: query_blocks(program),
+            attributes: query_attributes(program),
+        }
+    }
+
+    pub fn uniforms(&self) -> &[UniformInfo] {
+        &self.uniforms
+    }
+
+    pub fn blocks(&self) -> &[UniformBlockInfo] {
+        &self.blocks
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        if let Some(uniform) = self.uniforms.iter().find(|u| u.name == name) {
+            return Some(uniform);
+        }
+        let (base, index) = match name.strip_suffix(']').and_then(|n| n.rsplit_once('[')) {
+            Some((base, index)) => (base, index.parse::<usize>().ok()?),
+            None => (name, 0),
+        };
+        let array_name = format!("{}[0]", base);
+        self.uniforms.iter()
+            .find(|u| u.name == array_name)
+            .filter(|u| index < u.array_size)
+    }
+
+    pub fn block(&self, name: &str) -> Option<&UniformBlockInfo> {
+        self.blocks.iter().find(|b| b.name == name)
+    }
+
+    pub(crate) fn set_block_binding(&mut self, name: &str, binding: usize) -> Option<usize> {
+        let block = self.blocks.iter_mut().find(|b| b.name == name)?;
+        block.binding = binding;
+        Some(block.index)
+    }
+
+    pub fn block_uniforms(&self, name: &str) -> Vec<&UniformInfo> {
+        match self.block(name) {
+            Some(block) => self.uniforms.iter().filter(|u| u.block_index == Some(block.index)).collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+}
+
+pub fn is_sampler(gl_type: GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::SAMPLER_1D | gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE |
+        gl::SAMPLER_1D_SHADOW | gl::SAMPLER_2D_SHADOW | gl::SAMPLER_CUBE_SHADOW |
+        gl::SAMPLER_1D_ARRAY | gl::SAMPLER_2D_ARRAY | gl::SAMPLER_1D_ARRAY_SHADOW | gl::SAMPLER_2D_ARRAY_SHADOW |
+        gl::SAMPLER_CUBE_MAP_ARRAY | gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW |
+        gl::SAMPLER_2D_MULTISAMPLE | gl::SAMPLER_2D_MULTISAMPLE_ARRAY | gl::SAMPLER_BUFFER | gl::SAMPLER_2D_RECT |
+        gl::INT_SAMPLER_2D | gl::INT_SAMPLER_3D | gl::INT_SAMPLER_CUBE | gl::INT_SAMPLER_2D_ARRAY |
+        gl::INT_SAMPLER_BUFFER | gl::INT_SAMPLER_CUBE_MAP_ARRAY |
+        gl::UNSIGNED_INT_SAMPLER_2D | gl::UNSIGNED_INT_SAMPLER_3D | gl::UNSIGNED_INT_SAMPLER_CUBE |
+        gl::UNSIGNED_INT_SAMPLER_2D_ARRAY | gl::UNSIGNED_INT_SAMPLER_BUFFER | gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY
+    )
+}
+
+pub fn gl_type_name(gl_type: GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::DOUBLE => "double",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::FLOAT_MAT2x3 => "mat2x3",
+        gl::FLOAT_MAT2x4 => "mat2x4",
+        gl::FLOAT_MAT3x2 => "mat3x2",
+        gl::FLOAT_MAT3x4 => "mat3x4",
+        gl::FLOAT_MAT4x2 => "mat4x2",
+        gl::FLOAT_MAT4x3 => "mat4x3",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        gl::SAMPLER_CUBE_MAP_ARRAY => "samplerCubeArray",
+        gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW => "samplerCubeArrayShadow",
+        gl::SAMPLER_2D_MULTISAMPLE => "sampler2DMS",
+        t if is_sampler(t) => "sampler",
+        _ => "unknown",
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::Error;
 use super::gl_function;
 use gl;
 use std::ffi::CString;
@@ -6,10 +7,10 @@ use std::ptr;
 
 fn check_success(
     resource: gl::types::GLuint,
-    success_type: gl::types::GLenum,
-) -> Result<(), String> {
+    stage: gl::types::GLenum,
+) -> Result<(), Error> {
     let mut status = gl::FALSE as gl::types::GLint;
-    gl_function!(GetShaderiv(resource, success_type, &mut status));
+    gl_function!(GetShaderiv(resource, gl::COMPILE_STATUS, &mut status));
 
     if status != (gl::TRUE as gl::types::GLint) {
         let mut len = 0;
@@ -24,9 +25,13 @@ fn check_success(
         let s = std::str::from_utf8(&buf)
             .ok()
             .expect("ShaderInfoLog not valid utf8")
+            .trim_end_matches('\0')
             .to_string();
-        log::error!("{}", &s);
-        Err(s)
+        Err(Error::ShaderCompile {
+            stage,
+            log: s,
+            diagnostics: vec![],
+        })
     } else {
         Ok(())
     }
@@ -35,13 +40,13 @@ fn check_success(
 pub struct Shader(pub(crate) gl::types::GLuint);
 
 impl Shader {
-    pub fn new(shader_type: gl::types::GLenum, content: &str) -> Result<Shader, String> {
+    pub fn new(shader_type: gl::types::GLenum, content: &str) -> Result<Shader, Error> {
         let shader = gl_function!(CreateShader(shader_type));
         let c_str = CString::new(content.as_bytes()).unwrap();
         gl_function!(ShaderSource(shader, 1, &c_str.as_ptr(), ptr::null()));
         gl_function!(CompileShader(shader));
 
-        check_success(shader, gl::COMPILE_STATUS)?;
+        check_success(shader, shader_type)?;
         Ok(Shader(shader))
     }
 }
@@ -0,0 +1,186 @@
+use std::fmt::{Display, Formatter};
+use regex::Regex;
+use crate::preprocessor::PreprocessedSource;
+
+const CONTEXT_LINES: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+pub struct ShaderDiagnostic {
+    pub severity: Severity,
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub message: String,
+    pub excerpt: Vec<(usize, String)>,
+}
+
+struct LogEntry {
+    source: usize,
+    line: usize,
+    column: Option<usize>,
+    severity: Severity,
+    message: String,
+}
+
+fn severity(s: &str) -> Severity {
+    if s.eq_ignore_ascii_case("warning") {
+        Severity::Warning
+    } else {
+        Severity::Error
+    }
+}
+
+fn parse_info_log(log: &str) -> Vec<LogEntry> {
+    let mesa = Regex::new(r"^(\d+):(\d+)\((\d+)\):\s*(error|warning):\s*(.*)$").unwrap();
+    let nvidia = Regex::new(r"^(\d+)\((\d+)\)\s*:\s*(error|warning)\s*\w*:\s*(.*)$").unwrap();
+    let amd = Regex::new(r"(?i)^(error|warning):\s*(\d+):(\d+):\s*(.*)$").unwrap();
+    log.lines()
+        .map(|l| l.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter_map(|l| {
+            if let Some(c) = mesa.captures(l) {
+                Some(LogEntry {
+                    source: c[1].parse().ok()?,
+                    line: c[2].parse().ok()?,
+                    column: c[3].parse().ok(),
+                    severity: severity(&c[4]),
+                    message: c[5].to_string(),
+                })
+            } else if let Some(c) = nvidia.captures(l) {
+                Some(LogEntry {
+                    source: c[1].parse().ok()?,
+                    line: c[2].parse().ok()?,
+                    column: None,
+                    severity: severity(&c[3]),
+                    message: c[4].to_string(),
+                })
+            } else if let Some(c) = amd.captures(l) {
+                Some(LogEntry {
+                    source: c[2].parse().ok()?,
+                    line: c[3].parse().ok()?,
+                    column: None,
+                    severity: severity(&c[1]),
+                    message: c[4].to_string(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn map_info_log(log: &str, source: &PreprocessedSource) -> Vec<ShaderDiagnostic> {
+    parse_info_log(log).into_iter()
+        .map(|entry| {
+            let (file, excerpt) = match source.file(entry.source) {
+                Some(file) => {
+                    let first = entry.line.saturating_sub(CONTEXT_LINES).max(1);
+                    let excerpt = file.content.lines()
+                        .enumerate()
+                        .map(|(i, l)| (i + 1, l.to_string()))
+                        .skip(first - 1)
+                        .take(entry.line + CONTEXT_LINES + 1 - first)
+                        .collect();
+                    (file.name.clone(), excerpt)
+                }
+                None => (format!("<source {}>", entry.source), vec![]),
+            };
+            ShaderDiagnostic {
+                severity: entry.severity,
+                file,
+                line: entry.line,
+                column: entry.column,
+                message: entry.message,
+                excerpt,
+            }
+        })
+        .collect()
+}
+
+impl Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}\n", severity, self.message)?;
+        match self.column {
+            Some(column) => write!(f, "  --> {}:{}:{}\n", self.file, self.line, column)?,
+            None => write!(f, "  --> {}:{}\n", self.file, self.line)?,
+        }
+        let width = self.excerpt.last().map(|(n, _)| n.to_string().len()).unwrap_or(1);
+        for (number, line) in self.excerpt.iter() {
+            write!(f, "{:>width$} | {}\n", number, line, width = width)?;
+            if *number != self.line {
+                continue;
+            }
+            let indent = line.len() - line.trim_start().len();
+            let marker = match self.column {
+                Some(column) if column > 0 => format!("{}^", " ".repeat(column - 1)),
+                _ => format!("{}{}", &line[..indent], "^".repeat(line.trim().len().max(1))),
+            };
+            write!(f, "{:>width$} | {}\n", "", marker, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preprocessor::SourceFile;
+    use super::*;
+
+    fn entries(log: &str) -> Vec<(usize, usize, Option<usize>, Severity, String)> {
+        parse_info_log(log).into_iter()
+            .map(|e| (e.source, e.line, e.column, e.severity, e.message))
+            .collect()
+    }
+
+    #[test]
+    fn parses_mesa_logs() {
+        let log = "0:12(5): error: `fragPos' undeclared\n1:3(10): warning: `unused' declared but not used\n\0";
+        assert_eq!(entries(log), vec![
+            (0, 12, Some(5), Severity::Error, "`fragPos' undeclared".to_string()),
+            (1, 3, Some(10), Severity::Warning, "`unused' declared but not used".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn parses_nvidia_logs() {
+        let log = "0(12) : error C1008: undefined variable \"fragPos\"\n2(7) : warning C7050: \"color\" might be used before being initialized\n";
+        assert_eq!(entries(log), vec![
+            (0, 12, None, Severity::Error, "undefined variable \"fragPos\"".to_string()),
+            (2, 7, None, Severity::Warning, "\"color\" might be used before being initialized".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn parses_amd_logs() {
+        let log = "ERROR: 0:12: 'fragPos' : undeclared identifier\nWARNING: 1:4: 'color' : variable is not written\nERROR: 2 compilation errors.  No code generated.\n";
+        assert_eq!(entries(log), vec![
+            (0, 12, None, Severity::Error, "'fragPos' : undeclared identifier".to_string()),
+            (1, 4, None, Severity::Warning, "'color' : variable is not written".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn maps_entries_to_source_files() {
+        let source = PreprocessedSource {
+            code: String::new(),
+            files: vec![SourceFile {
+                name: "lights.glsl".to_string(),
+                content: "a\nb\nc\nd\ne\nf\n".to_string(),
+            }],
+        };
+        let diagnostics = map_info_log("0:4(1): error: oops", &source);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "lights.glsl");
+        assert_eq!(diagnostics[0].line, 4);
+        assert_eq!(diagnostics[0].excerpt.iter().map(|(n, _)| *n).collect::<Vec<_>>(), vec![2, 3, 4, 5, 6]);
+    }
+}
@@ -1,45 +1,239 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
+use std::time::SystemTime;
+use crate::error::Error;
+use crate::preprocessor::{PreprocessedSource, Preprocessor};
+use crate::program::{Program, ProgramState};
 use crate::shader::Shader;
+use crate::shader_diagnostic::map_info_log;
 use gl;
 use include_dir::Dir;
-use regex::Regex;
+use log::{error, info};
 
+const SHADERS_DIR_VARIABLE: &'static str = "LEARNOPENGL_SHADERS_DIR";
+
+#[derive(Clone, Copy, Debug)]
 pub enum ShaderType {
     Fragment = gl::FRAGMENT_SHADER as isize,
     Geometry = gl::GEOMETRY_SHADER as isize,
     Vertex = gl::VERTEX_SHADER as isize,
 }
 
+#[derive(Clone)]
+enum ShaderSource {
+    Bundle(&'static Dir<'static>),
+    Filesystem(PathBuf),
+}
+
+struct WatchedProgram {
+    program: Weak<ProgramState>,
+    stages: Vec<(ShaderType, String)>,
+    extensions: Vec<String>,
+    defines: Vec<(String, String)>,
+    dependencies: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[derive(Clone)]
 pub struct ShaderLoader {
-    shaders: &'static Dir<'static>,
+    source: ShaderSource,
+    extensions: Vec<String>,
+    defines: Vec<(String, String)>,
+    watched: Rc<RefCell<Vec<WatchedProgram>>>,
 }
 
 impl ShaderLoader {
     pub fn new(shaders: &'static Dir) -> ShaderLoader {
         ShaderLoader {
-            shaders
+            source: ShaderSource::Bundle(shaders),
+            extensions: vec![],
+            defines: vec![],
+            watched: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    pub fn from_directory<P: Into<PathBuf>>(directory: P) -> ShaderLoader {
+        ShaderLoader {
+            source: ShaderSource::Filesystem(directory.into()),
+            extensions: vec![],
+            defines: vec![],
+            watched: Rc::new(RefCell::new(vec![])),
         }
     }
 
-    pub fn load(&self, shader_type: ShaderType, glsl: &'static str) -> Result<Shader, String> {
-        Shader::new(shader_type as _, &self.load_file(glsl)?)
+    pub fn from_env(shaders: &'static Dir) -> ShaderLoader {
+        match std::env::var(SHADERS_DIR_VARIABLE) {
+            Ok(directory) => ShaderLoader::from_directory(directory),
+            Err(_) => ShaderLoader::new(shaders),
+        }
     }
 
-    fn load_file(&self, glsl: &str) -> Result<String, String> {
-        let mut content = self.shaders
-            .get_file(glsl)
-            .ok_or(format!("Shader {} not found", glsl))
-            .map(|f| f.contents_utf8())?
-            .map(|s| s.to_string())
-            .ok_or("Empty file")
-            .map_err(|s| s.to_string())?;
-        let include_str = "#include".to_string();
-        let includes_regex = Regex::new(
-            r#"include "(.+)""#
-        ).map_err(|e| e.to_string())?;
+    pub fn is_watching(&self) -> bool {
+        matches!(self.source, ShaderSource::Filesystem(_))
+    }
+
+    pub fn define<V: ToString>(mut self, name: &str, value: V) -> ShaderLoader {
+        let value = value.to_string();
+        match self.defines.iter_mut().find(|(n, _)| n == name) {
+            Some(define) => define.1 = value,
+            None => self.defines.push((name.to_string(), value)),
+        }
+        self
+    }
 
-        for cap in includes_regex.captures_iter(&(content.clone())) {
-            content = content.replace(&(include_str.clone() + r#" ""# + &cap[1] + r#"""#), &self.load_file(&cap[1])?);
+    pub fn extension(mut self, name: &str) -> ShaderLoader {
+        if !self.extensions.iter().any(|e| e == name) {
+            self.extensions.push(name.to_string());
         }
-        Ok(content)
+        self
+    }
+
+    pub fn defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    pub fn load(&self, shader_type: ShaderType, glsl: &str) -> Result<Shader, Error> {
+        self.compile(shader_type, glsl, &self.extensions, &self.defines, &mut vec![])
     }
-}
\ No newline at end of file
+
+    fn compile(
+        &self,
+        shader_type: ShaderType,
+        glsl: &str,
+        extensions: &[String],
+        defines: &[(String, String)],
+        dependencies: &mut Vec<PathBuf>,
+    ) -> Result<Shader, Error> {
+        let source = self.preprocess(glsl, extensions, defines, dependencies)?;
+        Shader::new(shader_type as _, &source.code).map_err(|e| match e {
+            Error::ShaderCompile { stage, log, .. } => {
+                let diagnostics = map_info_log(&log, &source).into_iter()
+                    .map(|mut d| {
+                        d.file = self.display_path(&d.file);
+                        d
+                    })
+                    .collect();
+                let e = Error::ShaderCompile { stage, log, diagnostics };
+                error!("{}", e);
+                e
+            }
+            e => e,
+        })
+    }
+
+    fn load_shaders(
+        &self,
+        stages: &[(ShaderType, String)],
+        extensions: &[String],
+        defines: &[(String, String)],
+        dependencies: &mut Vec<PathBuf>,
+    ) -> Result<Vec<Shader>, Error> {
+        stages.iter()
+            .map(|(shader_type, glsl)| self.compile(*shader_type, glsl, extensions, defines, dependencies))
+            .collect()
+    }
+
+    pub fn program(&self, stages: Vec<(ShaderType, &str)>) -> Result<Program, Error> {
+        let stages = stages.into_iter()
+            .map(|(shader_type, glsl)| (shader_type, glsl.to_string()))
+            .collect::<Vec<_>>();
+        let mut dependencies = vec![];
+        let program = Program::new(self.load_shaders(&stages, &self.extensions, &self.defines, &mut dependencies)?)?;
+        if self.is_watching() {
+            self.watched.borrow_mut().push(WatchedProgram {
+                program: program.state(),
+                stages,
+                extensions: self.extensions.clone(),
+                defines: self.defines.clone(),
+                dependencies: dependencies.into_iter().map(|d| {
+                    let time = modified(&d);
+                    (d, time)
+                }).collect(),
+            });
+        }
+        Ok(program)
+    }
+
+    pub fn reload_changed(&self) -> usize {
+        let mut reloaded = 0;
+        let mut watched = self.watched.borrow_mut();
+        watched.retain(|w| w.program.strong_count() > 0);
+        for watched_program in watched.iter_mut() {
+            let changed = watched_program.dependencies.iter().any(|(path, time)| modified(path) != *time);
+            if !changed {
+                continue;
+            }
+            let program = match watched_program.program.upgrade() {
+                Some(program) => program,
+                None => continue,
+            };
+            let mut dependencies = vec![];
+            let result = self.load_shaders(
+                &watched_program.stages,
+                &watched_program.extensions,
+                &watched_program.defines,
+                &mut dependencies,
+            )
+                .and_then(|shaders| program.relink(shaders));
+            if dependencies.is_empty() {
+                for (path, time) in watched_program.dependencies.iter_mut() {
+                    *time = modified(path);
+                }
+            } else {
+                watched_program.dependencies = dependencies.into_iter().map(|d| {
+                    let time = modified(&d);
+                    (d, time)
+                }).collect();
+            }
+            let names = watched_program.stages.iter().map(|(_, glsl)| glsl.as_str()).collect::<Vec<_>>().join(", ");
+            match result {
+                Ok(()) => {
+                    info!("Reloaded program {}", names);
+                    reloaded += 1;
+                }
+                Err(e) => error!("Keeping previous version of program {}: {}", names, e),
+            }
+        }
+        reloaded
+    }
+
+    fn display_path(&self, glsl: &str) -> String {
+        match &self.source {
+            ShaderSource::Bundle(_) => glsl.to_string(),
+            ShaderSource::Filesystem(directory) => directory.join(glsl).display().to_string(),
+        }
+    }
+
+    fn read_file(&self, glsl: &str, dependencies: &mut Vec<PathBuf>) -> Result<String, Error> {
+        match &self.source {
+            ShaderSource::Bundle(shaders) => shaders
+                .get_file(glsl)
+                .ok_or(Error::MissingAsset { path: glsl.into() })
+                .map(|f| f.contents_utf8())?
+                .map(|s| s.to_string())
+                .ok_or(format!("Shader {} is not valid UTF-8", glsl).into()),
+            ShaderSource::Filesystem(directory) => {
+                let path = directory.join(glsl);
+                if !dependencies.contains(&path) {
+                    dependencies.push(path.clone());
+                }
+                std::fs::read_to_string(&path).map_err(|e| Error::io(path, e))
+            }
+        }
+    }
+
+    pub fn preprocess(
+        &self,
+        glsl: &str,
+        extensions: &[String],
+        defines: &[(String, String)],
+        dependencies: &mut Vec<PathBuf>,
+    ) -> Result<PreprocessedSource, Error> {
+        let mut read = |file: &str| self.read_file(file, dependencies);
+        Preprocessor::new(&mut read).enable_extensions(extensions).process(glsl, defines)
+    }
+}
@@ -0,0 +1,300 @@
+use nalgebra::{Matrix2, Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+pub struct Std140Writer {
+    data: Vec<u8>,
+}
+
+impl Std140Writer {
+    pub fn new() -> Std140Writer {
+        Std140Writer { data: vec![] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn align(&mut self, alignment: usize) {
+        let padding = (alignment - self.data.len() % alignment) % alignment;
+        self.data.resize(self.data.len() + padding, 0);
+    }
+
+    pub fn write_bytes(&mut self, alignment: usize, bytes: &[u8]) {
+        self.align(alignment);
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl Default for Std140Writer {
+    fn default() -> Std140Writer {
+        Std140Writer::new()
+    }
+}
+
+pub const fn round_up_to_vec4(alignment: usize) -> usize {
+    alignment.div_ceil(16) * 16
+}
+
+pub const fn struct_alignment(alignments: &[usize]) -> usize {
+    let mut alignment = 16;
+    let mut i = 0;
+    while i < alignments.len() {
+        if alignments[i] > alignment {
+            alignment = alignments[i];
+        }
+        i += 1;
+    }
+    round_up_to_vec4(alignment)
+}
+
+pub trait Std140 {
+    const ALIGNMENT: usize;
+
+    fn write_std140(&self, writer: &mut Std140Writer);
+
+    fn std140_bytes(&self) -> Vec<u8> {
+        let mut writer = Std140Writer::new();
+        self.write_std140(&mut writer);
+        writer.align(Self::ALIGNMENT);
+        writer.into_bytes()
+    }
+}
+
+macro_rules! std140_scalar {
+    ($type:ty) => {
+        impl Std140 for $type {
+            const ALIGNMENT: usize = 4;
+
+            fn write_std140(&self, writer: &mut Std140Writer) {
+                writer.write_bytes(4, &self.to_ne_bytes());
+            }
+        }
+    };
+}
+
+macro_rules! std140_vector {
+    ($type:ty, $alignment:expr) => {
+        impl Std140 for $type {
+            const ALIGNMENT: usize = $alignment;
+
+            fn write_std140(&self, writer: &mut Std140Writer) {
+                writer.align($alignment);
+                for component in self.iter() {
+                    component.write_std140(writer);
+                }
+            }
+        }
+    };
+}
+
+macro_rules! std140_matrix {
+    ($type:ty) => {
+        impl Std140 for $type {
+            const ALIGNMENT: usize = 16;
+
+            fn write_std140(&self, writer: &mut Std140Writer) {
+                for column in self.column_iter() {
+                    writer.align(16);
+                    for component in column.iter() {
+                        component.write_std140(writer);
+                    }
+                }
+                writer.align(16);
+            }
+        }
+    };
+}
+
+std140_scalar!(f32);
+std140_scalar!(i32);
+std140_scalar!(u32);
+std140_vector!(Vector2<f32>, 8);
+std140_vector!(Vector3<f32>, 16);
+std140_vector!(Vector4<f32>, 16);
+std140_vector!(Vector2<i32>, 8);
+std140_vector!(Vector3<i32>, 16);
+std140_vector!(Vector4<i32>, 16);
+std140_vector!(Vector2<u32>, 8);
+std140_vector!(Vector3<u32>, 16);
+std140_vector!(Vector4<u32>, 16);
+std140_matrix!(Matrix2<f32>);
+std140_matrix!(Matrix3<f32>);
+std140_matrix!(Matrix4<f32>);
+
+impl Std140 for bool {
+    const ALIGNMENT: usize = 4;
+
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        (*self as u32).write_std140(writer);
+    }
+}
+
+impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGNMENT: usize = round_up_to_vec4(T::ALIGNMENT);
+
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        for element in self.iter() {
+            writer.align(Self::ALIGNMENT);
+            element.write_std140(writer);
+            writer.align(Self::ALIGNMENT);
+        }
+    }
+}
+
+impl<T: Std140> Std140 for Vec<T> {
+    const ALIGNMENT: usize = round_up_to_vec4(T::ALIGNMENT);
+
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        for element in self.iter() {
+            writer.align(Self::ALIGNMENT);
+            element.write_std140(writer);
+            writer.align(Self::ALIGNMENT);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! std140 {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $type:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $type),*
+        }
+
+        impl $crate::std140::Std140 for $name {
+            const ALIGNMENT: usize = $crate::std140::struct_alignment(
+                &[$(<$type as $crate::std140::Std140>::ALIGNMENT),*]
+            );
+
+            fn write_std140(&self, writer: &mut $crate::std140::Std140Writer) {
+                writer.align(Self::ALIGNMENT);
+                $($crate::std140::Std140::write_std140(&self.$field, writer);)*
+                writer.align(Self::ALIGNMENT);
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+    use crate::ecs::systems::rendering::Lights;
+    use crate::light::{DirectionalLightData, PointLightData, SpotLightData};
+    use super::*;
+
+    fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn i32_at(bytes: &[u8], offset: usize) -> i32 {
+        i32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn point_light() -> PointLightData {
+        PointLightData {
+            position: Vector3::new(1.0, 2.0, 3.0),
+            constant: 4.0,
+            linear: 5.0,
+            quadratic: 6.0,
+            ambient: Vector3::new(7.0, 8.0, 9.0),
+            diffuse: Vector3::new(10.0, 11.0, 12.0),
+            specular: Vector3::new(13.0, 14.0, 15.0),
+            set: true,
+            shadow_index: 16,
+            range: 17.0,
+        }
+    }
+
+    #[test]
+    fn scalars_pack_after_vec3() {
+        std140! {
+            struct Packed {
+                a: Vector3<f32>,
+                b: f32,
+                c: Vector2<f32>,
+            }
+        }
+        let bytes = Packed { a: Vector3::new(1.0, 2.0, 3.0), b: 4.0, c: Vector2::new(5.0, 6.0) }.std140_bytes();
+        assert_eq!(f32_at(&bytes, 12), 4.0);
+        assert_eq!(f32_at(&bytes, 16), 5.0);
+        assert_eq!(bytes.len(), 32);
+    }
+
+    #[test]
+    fn point_light_layout() {
+        let bytes = point_light().std140_bytes();
+        let offsets = [(0, 1.0), (12, 4.0), (16, 5.0), (20, 6.0), (32, 7.0), (48, 10.0), (64, 13.0)];
+        for (offset, value) in offsets {
+            assert_eq!(f32_at(&bytes, offset), value, "offset {}", offset);
+        }
+        assert_eq!(i32_at(&bytes, 76), 1);
+        assert_eq!(i32_at(&bytes, 80), 16);
+        assert_eq!(f32_at(&bytes, 84), 17.0);
+        assert_eq!(bytes.len(), 96);
+    }
+
+    #[test]
+    fn spot_light_layout() {
+        let bytes = SpotLightData {
+            direction: Vector3::new(1.0, 2.0, 3.0),
+            position: Vector3::new(4.0, 5.0, 6.0),
+            cut_off: 7.0,
+            outer_cut_off: 8.0,
+            ambient: Vector3::new(9.0, 10.0, 11.0),
+            diffuse: Vector3::new(12.0, 13.0, 14.0),
+            specular: Vector3::new(15.0, 16.0, 17.0),
+            constant: 18.0,
+            linear: 19.0,
+            quadratic: 20.0,
+            set: true,
+            shadow_index: 21,
+            range: 22.0,
+        }.std140_bytes();
+        let offsets = [
+            (0, 1.0), (16, 4.0), (28, 7.0), (32, 8.0), (48, 9.0), (64, 12.0), (80, 15.0),
+            (92, 18.0), (96, 19.0), (100, 20.0),
+        ];
+        for (offset, value) in offsets {
+            assert_eq!(f32_at(&bytes, offset), value, "offset {}", offset);
+        }
+        assert_eq!(i32_at(&bytes, 104), 1);
+        assert_eq!(i32_at(&bytes, 108), 21);
+        assert_eq!(f32_at(&bytes, 112), 22.0);
+        assert_eq!(bytes.len(), 128);
+    }
+
+    #[test]
+    fn scalar_arrays_use_vec4_stride() {
+        let bytes = [1f32, 2f32, 3f32].std140_bytes();
+        assert_eq!(f32_at(&bytes, 16), 2.0);
+        assert_eq!(f32_at(&bytes, 32), 3.0);
+        assert_eq!(bytes.len(), 48);
+    }
+
+    #[test]
+    fn light_array_strides() {
+        let directional_size = DirectionalLightData::default().std140_bytes().len();
+        let point_size = point_light().std140_bytes().len();
+        let bytes = Lights {
+            directional_lights: vec![DirectionalLightData::default(); 2],
+            point_lights: vec![PointLightData::default(), point_light()],
+            spot_lights: vec![SpotLightData::default(); 2],
+        }.std140_bytes();
+        assert_eq!(directional_size, 80);
+        let second_point_light = 2 * directional_size + point_size;
+        assert_eq!(f32_at(&bytes, second_point_light + 12), 4.0);
+        assert_eq!(bytes.len(), 2 * directional_size + 2 * point_size + 2 * 128);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::buffer::Buffer;
+use crate::error::Error;
 use crate::gl_function;
 use gl;
 use std::mem::transmute;
@@ -12,11 +14,15 @@ pub enum TextureType {
     Texture3D = gl::TEXTURE_3D as isize,
     CubeMap = gl::TEXTURE_CUBE_MAP as isize,
     Texture2DMultisample = gl::TEXTURE_2D_MULTISAMPLE as isize,
+    TextureBuffer = gl::TEXTURE_BUFFER as isize,
+    Texture2DArray = gl::TEXTURE_2D_ARRAY as isize,
+    CubeMapArray = gl::TEXTURE_CUBE_MAP_ARRAY as isize,
 }
 
 #[derive(Clone, Copy)]
 pub enum TextureFormat {
     FloatingPoint,
+    HighPrecisionFloatingPoint,
     UnsignedByte,
     UnsignedByteWithAlpha,
     Grey,
@@ -76,6 +82,20 @@ impl Texture {
         ));
     }
 
+    pub fn alloc_float_cube_map_face(&self, face: u32, level: usize, width: usize, height: usize) {
+        gl_function!(TexImage2D(
+            gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+            level as _,
+            gl::RGBA16F as _,
+            width as _,
+            height as _,
+            0,
+            gl::RGBA as _,
+            gl::FLOAT,
+            ptr::null(),
+        ));
+    }
+
     pub fn set_cube_map_face(&self, face: u32, width: usize, height: usize, data: &[u8]) {
         gl_function!(TexImage2D(
             gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
@@ -90,7 +110,7 @@ impl Texture {
         ));
     }
 
-    pub fn set_image_2d_with_type(&self, width: u32, height: u32, data: &[u8], color_type: ColorType) -> Result<(), String> {
+    pub fn set_image_2d_with_type(&self, width: u32, height: u32, data: &[u8], color_type: ColorType) -> Result<(), Error> {
         let gl_type = match color_type {
             ColorType::Rgb8 => Ok(gl::RGB),
             ColorType::Rgba8 => Ok(gl::RGBA),
@@ -197,6 +217,9 @@ impl Texture {
                 gl::FLOAT,
                 ptr::null(),
             )),
+            (TextureType::Texture2D, TextureFormat::HighPrecisionFloatingPoint) => gl_function!(TexImage2D(
+                self.1, 0, gl::RGBA32F as _, width as _, height as _, 0, gl::RGBA as _, gl::FLOAT, ptr::null(),
+            )),
             (TextureType::Texture2D, TextureFormat::Grey) => gl_function!(TexImage2D(
                 self.1, 0, gl::RED as _, width as _, height as _, 0, gl::RED as _, gl::FLOAT, ptr::null(),
             )),
@@ -221,6 +244,29 @@ impl Texture {
         }
     }
 
+    pub fn allocate_depth_layers(&self, width: u32, height: u32, layers: usize) -> Result<(), Error> {
+        match self.2 {
+            TextureType::Texture2DArray | TextureType::CubeMapArray => gl_function!(TexImage3D(
+                self.1,
+                0,
+                gl::DEPTH_COMPONENT24 as _,
+                width as _,
+                height as _,
+                layers as _,
+                0,
+                gl::DEPTH_COMPONENT as _,
+                gl::FLOAT,
+                ptr::null(),
+            )),
+            t => return Err(format!("Depth layers need an array texture, got {:?}", t).into()),
+        };
+        Ok(())
+    }
+
+    pub fn attach_buffer(&self, buffer: &Buffer, internal_format: gl::types::GLenum) {
+        gl_function!(TexBuffer(self.1, internal_format, buffer.0));
+    }
+
     pub fn set_parameter(&self, parameter: gl::types::GLenum, value: gl::types::GLenum) {
         gl_function!(TexParameteri(self.1, parameter, value as i32));
     }
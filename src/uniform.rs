@@ -0,0 +1,120 @@
+use gl::types::{GLenum, GLint};
+use nalgebra::{Matrix2, Matrix3, Matrix4, Matrix4x3, Vector2, Vector3, Vector4};
+use crate::gl_function;
+use crate::reflection::is_sampler;
+
+pub trait UniformElement: Sized {
+    fn accepts(gl_type: GLenum) -> bool;
+    fn set_slice(location: GLint, values: &[Self]);
+}
+
+pub trait UniformType {
+    fn accepts(gl_type: GLenum) -> bool;
+    fn set(&self, location: GLint);
+}
+
+macro_rules! uniform_vector {
+    ($type:ty, $gl_type:expr, $function:ident, $component:ty) => {
+        impl UniformElement for $type {
+            fn accepts(gl_type: GLenum) -> bool {
+                gl_type == $gl_type
+            }
+
+            fn set_slice(location: GLint, values: &[Self]) {
+                gl_function!($function(location, values.len() as _, values.as_ptr() as *const $component));
+            }
+        }
+    };
+}
+
+macro_rules! uniform_matrix {
+    ($type:ty, $gl_type:expr, $function:ident) => {
+        impl UniformElement for $type {
+            fn accepts(gl_type: GLenum) -> bool {
+                gl_type == $gl_type
+            }
+
+            fn set_slice(location: GLint, values: &[Self]) {
+                gl_function!($function(location, values.len() as _, gl::FALSE, values.as_ptr() as *const f32));
+            }
+        }
+    };
+}
+
+uniform_vector!(f32, gl::FLOAT, Uniform1fv, f32);
+uniform_vector!(Vector2<f32>, gl::FLOAT_VEC2, Uniform2fv, f32);
+uniform_vector!(Vector3<f32>, gl::FLOAT_VEC3, Uniform3fv, f32);
+uniform_vector!(Vector4<f32>, gl::FLOAT_VEC4, Uniform4fv, f32);
+uniform_vector!(Vector2<i32>, gl::INT_VEC2, Uniform2iv, i32);
+uniform_vector!(Vector3<i32>, gl::INT_VEC3, Uniform3iv, i32);
+uniform_vector!(Vector4<i32>, gl::INT_VEC4, Uniform4iv, i32);
+uniform_vector!(u32, gl::UNSIGNED_INT, Uniform1uiv, u32);
+uniform_vector!(Vector2<u32>, gl::UNSIGNED_INT_VEC2, Uniform2uiv, u32);
+uniform_vector!(Vector3<u32>, gl::UNSIGNED_INT_VEC3, Uniform3uiv, u32);
+uniform_vector!(Vector4<u32>, gl::UNSIGNED_INT_VEC4, Uniform4uiv, u32);
+uniform_matrix!(Matrix2<f32>, gl::FLOAT_MAT2, UniformMatrix2fv);
+uniform_matrix!(Matrix3<f32>, gl::FLOAT_MAT3, UniformMatrix3fv);
+uniform_matrix!(Matrix4<f32>, gl::FLOAT_MAT4, UniformMatrix4fv);
+// GLSL names matrices by columns first, so mat3x4 is nalgebra's 4 rows by 3 columns.
+uniform_matrix!(Matrix4x3<f32>, gl::FLOAT_MAT3x4, UniformMatrix3x4fv);
+
+impl UniformElement for i32 {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::INT || gl_type == gl::BOOL || is_sampler(gl_type)
+    }
+
+    fn set_slice(location: GLint, values: &[Self]) {
+        gl_function!(Uniform1iv(location, values.len() as _, values.as_ptr()));
+    }
+}
+
+impl UniformElement for bool {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::BOOL
+    }
+
+    fn set_slice(location: GLint, values: &[Self]) {
+        let values = values.iter().map(|v| *v as i32).collect::<Vec<_>>();
+        i32::set_slice(location, &values);
+    }
+}
+
+impl<T: UniformElement> UniformType for T {
+    fn accepts(gl_type: GLenum) -> bool {
+        T::accepts(gl_type)
+    }
+
+    fn set(&self, location: GLint) {
+        T::set_slice(location, std::slice::from_ref(self));
+    }
+}
+
+impl<T: UniformElement> UniformType for [T] {
+    fn accepts(gl_type: GLenum) -> bool {
+        T::accepts(gl_type)
+    }
+
+    fn set(&self, location: GLint) {
+        T::set_slice(location, self);
+    }
+}
+
+impl<T: UniformElement> UniformType for Vec<T> {
+    fn accepts(gl_type: GLenum) -> bool {
+        T::accepts(gl_type)
+    }
+
+    fn set(&self, location: GLint) {
+        T::set_slice(location, self);
+    }
+}
+
+impl<T: UniformElement, const N: usize> UniformType for [T; N] {
+    fn accepts(gl_type: GLenum) -> bool {
+        T::accepts(gl_type)
+    }
+
+    fn set(&self, location: GLint) {
+        T::set_slice(location, self);
+    }
+}
@@ -0,0 +1,77 @@
+use std::marker::PhantomData;
+use crate::buffer::Buffer;
+use crate::error::Error;
+use crate::program::Program;
+use crate::std140::Std140;
+
+pub struct UniformBlock<T: Std140> {
+    buffer: Buffer,
+    binding_point: usize,
+    size: usize,
+    _type: PhantomData<T>,
+}
+
+impl<T: Std140> UniformBlock<T> {
+    pub fn new(binding_point: usize, value: &T) -> UniformBlock<T> {
+        UniformBlock::with_target(gl::UNIFORM_BUFFER, binding_point, value)
+    }
+
+    pub fn storage(binding_point: usize, value: &T) -> UniformBlock<T> {
+        UniformBlock::with_target(gl::SHADER_STORAGE_BUFFER, binding_point, value)
+    }
+
+    fn with_target(target: gl::types::GLenum, binding_point: usize, value: &T) -> UniformBlock<T> {
+        let data = value.std140_bytes();
+        let buffer = Buffer::new(target);
+        buffer.bind();
+        buffer.set_data(&data, gl::DYNAMIC_DRAW);
+        buffer.unbind();
+        buffer.link_to_binding_point(binding_point, 0, data.len());
+        UniformBlock {
+            buffer,
+            binding_point,
+            size: data.len(),
+            _type: PhantomData,
+        }
+    }
+
+    pub fn binding_point(&self) -> usize {
+        self.binding_point
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn set(&self, value: &T) -> Result<(), Error> {
+        self.set_bytes(0, &value.std140_bytes())
+    }
+
+    pub fn set_bytes(&self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        if offset + data.len() > self.size {
+            return Err(format!(
+                "Uniform block at binding point {} holds {} bytes but was given {} at offset {}",
+                self.binding_point, self.size, data.len(), offset,
+            ).into());
+        }
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.buffer.bind();
+        self.buffer.set_sub_data(offset, data.len(), data);
+        self.buffer.unbind();
+        Ok(())
+    }
+
+    pub fn bind_to(&self, program: &Program, block: &str) -> Result<(), Error> {
+        program.bind_uniform_block(block, self.binding_point);
+        match program.reflection().block(block) {
+            Some(info) if info.data_size > self.size => Err(Error::UniformBlockLayout {
+                name: block.to_string(),
+                declared: info.data_size,
+                given: self.size,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,37 +1,84 @@
 use gl;
+use image::{Rgba32FImage, RgbaImage};
 use sdl2::{EventPump, Sdl, TimerSubsystem, VideoSubsystem};
-use sdl2::event::EventPollIterator;
+use sdl2::event::Event;
 use sdl2::mouse::MouseUtil;
 use sdl2::video::{GLContext, GLProfile, Window as SDL2Window};
+use crate::error::Error;
+use crate::frame_buffer::default_frame_buffer;
+use crate::gl_error::gl_debug_requested;
+use crate::pixels::{DepthImage, read_depth, read_rgba32f, read_rgba8};
+#[cfg(feature = "headless")]
+use crate::headless::{HeadlessContext, HeadlessOptions};
 
-pub struct Window {
+struct SdlSurface {
     events: Option<EventPump>,
     _gl_context: GLContext,
     sdl_context: Sdl,
     timer: TimerSubsystem,
     _video: VideoSubsystem,
     window: SDL2Window,
+}
+
+enum Surface {
+    Sdl(SdlSurface),
+    #[cfg(feature = "headless")]
+    Headless(HeadlessContext),
+}
+
+pub struct Window {
+    surface: Surface,
+    gl_debug: bool,
     now: usize,
     last: usize,
+    width: usize,
+    height: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WindowOptions {
+    pub samples: Option<u8>,
+    pub gl_debug: bool,
 }
 
 impl Window {
-    pub fn new_with_anti_alias(name: &str, width: usize, height: usize, size: u8) -> Result<Window, String> {
+    pub fn new_with_anti_alias(name: &str, width: usize, height: usize, size: u8) -> Result<Window, Error> {
+        Window::new_with_options(name, width, height, WindowOptions { samples: Some(size), ..WindowOptions::default() })
+    }
+
+    pub fn new(name: &str, width: usize, height: usize) -> Result<Window, Error> {
+        Window::new_with_options(name, width, height, WindowOptions::default())
+    }
+
+    pub fn new_with_options(name: &str, width: usize, height: usize, options: WindowOptions) -> Result<Window, Error> {
+        let gl_debug = options.gl_debug || gl_debug_requested();
+        #[cfg(feature = "headless")]
+        if let Some(headless) = HeadlessOptions::from_env() {
+            return Window::new_headless(width, height, HeadlessOptions { gl_debug, ..headless });
+        }
         let sdl_context = sdl2::init()?;
         let video = sdl_context.video()?;
         let attrs = video.gl_attr();
 
         attrs.set_stencil_size(8);
         attrs.set_context_major_version(4);
-        #[cfg(target_os = "macos")]
-            attrs.set_context_minor_version(1);
-        #[cfg(target_os = "linux")]
+        if cfg!(target_os = "linux") && options.samples.is_some() {
             attrs.set_context_minor_version(6);
+        } else {
+            attrs.set_context_minor_version(1);
+        }
         attrs.set_context_profile(GLProfile::Core);
+        let mut context_flags = attrs.set_context_flags();
         #[cfg(target_os = "macos")]
-            attrs.set_context_flags().forward_compatible().set();
-        attrs.set_multisample_buffers(1);
-        attrs.set_multisample_samples(size);
+            context_flags.forward_compatible();
+        if gl_debug {
+            context_flags.debug();
+        }
+        context_flags.set();
+        if let Some(samples) = options.samples {
+            attrs.set_multisample_buffers(1);
+            attrs.set_multisample_samples(samples);
+        }
         sdl_context.mouse().capture(true);
         sdl_context.mouse().set_relative_mouse_mode(true);
 
@@ -41,91 +88,173 @@ impl Window {
             .opengl()
             .build()
             .map_err(|e| e.to_string())?;
-        let gl_context = window.gl_create_context().unwrap();
+        let gl_context = window.gl_create_context()?;
         gl::load_with(|s| video.gl_get_proc_address(s) as *const std::os::raw::c_void);
-        gl_function!(Enable(gl::MULTISAMPLE));
-        let sdl_timer = sdl_context.timer().unwrap();
+        if options.samples.is_some() {
+            gl_function!(Enable(gl::MULTISAMPLE));
+        }
+        let sdl_timer = sdl_context.timer()?;
         Ok(Window {
-            sdl_context,
-            _video: video,
-            window,
-            events: None,
+            surface: Surface::Sdl(SdlSurface {
+                sdl_context,
+                _video: video,
+                window,
+                events: None,
+                timer: sdl_timer,
+                _gl_context: gl_context,
+            }),
+            gl_debug,
             last: 0,
             now: 0,
-            timer: sdl_timer,
-            _gl_context: gl_context,
+            width,
+            height,
         })
     }
 
-    pub fn new(name: &str, width: usize, height: usize) -> Result<Window, String> {
-        let sdl_context = sdl2::init()?;
-        let video = sdl_context.video()?;
-        let attrs = video.gl_attr();
-
-        attrs.set_stencil_size(8);
-        attrs.set_context_major_version(4);
-        attrs.set_context_minor_version(1);
-        attrs.set_context_profile(GLProfile::Core);
-        #[cfg(target_os = "macos")]
-            attrs.set_context_flags().forward_compatible().set();
-        sdl_context.mouse().capture(true);
-        sdl_context.mouse().set_relative_mouse_mode(true);
-
-        let window = video
-            .window(name, width as _, height as _)
-            .position_centered()
-            .opengl()
-            .build()
-            .map_err(|e| e.to_string())?;
-        let gl_context = window.gl_create_context().unwrap();
-        gl::load_with(|s| video.gl_get_proc_address(s) as *const std::os::raw::c_void);
-        let sdl_timer = sdl_context.timer().unwrap();
+    #[cfg(feature = "headless")]
+    pub fn new_headless(width: usize, height: usize, options: HeadlessOptions) -> Result<Window, Error> {
         Ok(Window {
-            sdl_context,
-            _video: video,
-            window,
-            events: None,
+            surface: Surface::Headless(HeadlessContext::new(width, height, options)?),
+            gl_debug: options.gl_debug,
             last: 0,
             now: 0,
-            timer: sdl_timer,
-            _gl_context: gl_context,
+            width,
+            height,
         })
     }
 
+    #[cfg(feature = "headless")]
+    pub fn headless(&self) -> Option<&HeadlessContext> {
+        match &self.surface {
+            Surface::Headless(headless) => Some(headless),
+            _ => None,
+        }
+    }
+
+    pub fn gl_debug(&self) -> bool {
+        self.gl_debug
+    }
+
+    pub fn is_headless(&self) -> bool {
+        match &self.surface {
+            Surface::Sdl(_) => false,
+            #[cfg(feature = "headless")]
+            Surface::Headless(_) => true,
+        }
+    }
+
+    pub fn should_close(&self) -> bool {
+        match &self.surface {
+            Surface::Sdl(_) => false,
+            #[cfg(feature = "headless")]
+            Surface::Headless(headless) => headless.finished(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub(crate) fn read_buffer(&self) -> gl::types::GLenum {
+        match &self.surface {
+            Surface::Sdl(_) => gl::BACK,
+            #[cfg(feature = "headless")]
+            Surface::Headless(_) => gl::COLOR_ATTACHMENT0,
+        }
+    }
+
+    pub fn read_pixels(&self) -> Result<RgbaImage, Error> {
+        read_rgba8(default_frame_buffer(), Some(self.read_buffer()), self.width as _, self.height as _)
+    }
+
+    pub fn read_pixels_hdr(&self) -> Result<Rgba32FImage, Error> {
+        read_rgba32f(default_frame_buffer(), Some(self.read_buffer()), self.width as _, self.height as _)
+    }
+
+    pub fn read_depth(&self) -> Result<DepthImage, Error> {
+        read_depth(default_frame_buffer(), self.width as _, self.height as _)
+    }
+
     pub fn start_timer(&mut self) {
-        self.now = self.timer.performance_counter() as _;
+        match &self.surface {
+            Surface::Sdl(sdl) => self.now = sdl.timer.performance_counter() as _,
+            #[cfg(feature = "headless")]
+            Surface::Headless(_) => {}
+        }
     }
 
     pub fn delta_time(&mut self) -> f32 {
-        self.last = self.now;
-        self.now = self.timer.performance_counter() as _;
-        ((self.now - self.last) / 1000) as f32
+        match &self.surface {
+            Surface::Sdl(sdl) => {
+                self.last = self.now;
+                self.now = sdl.timer.performance_counter() as _;
+                ((self.now - self.last) / 1000) as f32
+            }
+            #[cfg(feature = "headless")]
+            Surface::Headless(headless) => headless.delta_time(),
+        }
     }
 
     pub fn ticks(&self) -> u32 {
-        self.timer.ticks()
+        match &self.surface {
+            Surface::Sdl(sdl) => sdl.timer.ticks(),
+            #[cfg(feature = "headless")]
+            Surface::Headless(headless) => headless.ticks(),
+        }
     }
 
-    pub fn swap_buffers(&self) {
-        self.window.gl_swap_window()
+    pub fn swap_buffers(&mut self) {
+        match &mut self.surface {
+            Surface::Sdl(sdl) => sdl.window.gl_swap_window(),
+            #[cfg(feature = "headless")]
+            Surface::Headless(headless) => headless.swap_buffers(),
+        }
     }
 
     pub fn delay(&mut self, ms: usize) {
-        self.timer.delay(ms as _);
+        match &mut self.surface {
+            Surface::Sdl(sdl) => sdl.timer.delay(ms as _),
+            #[cfg(feature = "headless")]
+            Surface::Headless(_) => {}
+        }
     }
 
-    pub fn get_pumper(&mut self) -> EventPump {
-        self.sdl_context.event_pump().unwrap()
+    pub fn get_pumper(&mut self) -> Result<EventPump, Error> {
+        match &self.surface {
+            Surface::Sdl(sdl) => Ok(sdl.sdl_context.event_pump()?),
+            #[cfg(feature = "headless")]
+            Surface::Headless(_) => Err("Headless windows have no event pump".into()),
+        }
     }
 
-    pub fn events(&mut self) -> EventPollIterator {
-        if self.events.is_none() {
-            self.events = Some(self.get_pumper())
+    pub fn events(&mut self) -> Box<dyn Iterator<Item=Event> + '_> {
+        match &mut self.surface {
+            Surface::Sdl(sdl) => {
+                if sdl.events.is_none() {
+                    sdl.events = Some(sdl.sdl_context.event_pump().unwrap())
+                }
+                Box::new(sdl.events.as_mut().unwrap().poll_iter())
+            }
+            #[cfg(feature = "headless")]
+            Surface::Headless(headless) => {
+                if headless.finished() {
+                    Box::new(std::iter::once(Event::Quit { timestamp: headless.ticks() }))
+                } else {
+                    Box::new(std::iter::empty())
+                }
+            }
         }
-        self.events.as_mut().unwrap().poll_iter()
     }
 
-    pub fn mouse(&self) -> MouseUtil {
-        self.sdl_context.mouse()
+    pub fn mouse(&self) -> Result<MouseUtil, Error> {
+        match &self.surface {
+            Surface::Sdl(sdl) => Ok(sdl.sdl_context.mouse()),
+            #[cfg(feature = "headless")]
+            Surface::Headless(_) => Err("Headless windows have no mouse".into()),
+        }
     }
 }
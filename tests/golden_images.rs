@@ -0,0 +1,223 @@
+#![cfg(feature = "headless")]
+
+use hecs::World;
+use include_dir::{Dir, include_dir};
+use itertools::Itertools;
+use nalgebra::{Rotation3, UnitVector3, Vector3};
+use russimp::texture::TextureType;
+use learnopengl::camera::Camera;
+use learnopengl::cube::cube_mesh;
+use learnopengl::ecs::components::{ExtraUniform, TextureInfo, Transform, UniformValue};
+use learnopengl::ecs::systems::rendering::RenderingPath;
+use learnopengl::ecs::systems::rendering::ssao::SsaoSettings;
+use learnopengl::ecs::systems::system::System;
+use learnopengl::error::Error;
+use learnopengl::frame_buffer::FrameBuffer;
+use learnopengl::game::Game;
+use learnopengl::gl_error::{GlErrorPolicy, set_gl_error_policy};
+use learnopengl::gl_function;
+use learnopengl::golden_image::{GoldenImage, render_game};
+use learnopengl::headless::HeadlessOptions;
+use learnopengl::light::{DirectionalLight, PointLight};
+use learnopengl::plane::build_plane;
+use learnopengl::program::Program;
+use learnopengl::quad::Quad;
+use learnopengl::shader_loader::{ShaderLoader, ShaderType};
+use learnopengl::sphere::sphere_mesh;
+
+const WIDTH: usize = 800;
+const HEIGHT: usize = 600;
+const FRAMES: usize = 3;
+
+fn golden(name: &str) -> GoldenImage {
+    GoldenImage::new(format!("{}/tests/golden/{}.png", env!("CARGO_MANIFEST_DIR"), name))
+        .with_tolerance(4)
+        .with_max_mismatched_pixels(WIDTH * HEIGHT / 1000)
+}
+
+fn fix_camera(game: &Game, position: Vector3<f32>) {
+    *game.camera().borrow_mut() = Camera::new(position, Vector3::new(0f32, 0f32, -1f32), Vector3::y_axis());
+}
+
+static SHADERS_DIR: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/src/bin/shaders");
+
+struct OffscreenGreyscaleSystem {
+    frame_buffer: FrameBuffer,
+    program: Program,
+    quad: Quad,
+}
+
+impl OffscreenGreyscaleSystem {
+    fn new() -> Result<OffscreenGreyscaleSystem, Error> {
+        let program = ShaderLoader::new(&SHADERS_DIR).program(vec![
+            (ShaderType::Vertex, "15.1-postprocessing_vertex.glsl"),
+            (ShaderType::Fragment, "post_greyscale_fragment.glsl"),
+        ])?;
+        program.use_program();
+        program.set_uniform_i1("texture1", 0);
+        Ok(OffscreenGreyscaleSystem {
+            frame_buffer: FrameBuffer::new(WIDTH as _, HEIGHT as _)?,
+            program,
+            quad: Quad::new(),
+        })
+    }
+}
+
+impl System for OffscreenGreyscaleSystem {
+    fn name(&self) -> &str {
+        "Offscreen greyscale"
+    }
+
+    fn start(&self, _world: &mut World) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn early_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        self.frame_buffer.bind();
+        Ok(())
+    }
+
+    fn update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn late_update(&self, _world: &mut World, _delta_time: f32) -> Result<(), Error> {
+        FrameBuffer::unbind();
+        gl_function!(Disable(gl::DEPTH_TEST));
+        gl_function!(Clear(gl::COLOR_BUFFER_BIT));
+        self.program.use_program();
+        self.frame_buffer.texture.bind(gl::TEXTURE0);
+        self.quad.draw();
+        gl_function!(Enable(gl::DEPTH_TEST));
+        Ok(())
+    }
+}
+
+fn depth_testing_scene() -> Result<Game, Error> {
+    set_gl_error_policy(GlErrorPolicy::Panic);
+    let mut game = Game::new_headless(
+        WIDTH,
+        HEIGHT,
+        HeadlessOptions::new(FRAMES),
+        Vector3::new(0f32, 0f32, 0f32),
+        "17.1-uniform_buffer_objects_vertex.glsl",
+        "12.1-modelloading.glsl",
+        "17.1-uniform_buffer_objects_vertex.glsl",
+        "09.1-lightfragment.glsl",
+    )?;
+    fix_camera(&game, Vector3::new(0f32, 0.5f32, 4f32));
+    let directional_light = DirectionalLight::new(
+        UnitVector3::new_normalize(Vector3::new(-0.2f32, -1f32, -0.3f32)),
+        Vector3::new(0.2f32, 0.2f32, 0.2f32),
+        Vector3::new(0.5f32, 0.5f32, 0.5f32),
+        Vector3::new(1f32, 1f32, 1f32),
+    );
+    game.spawn_light(directional_light, &cube_mesh(vec![]))?;
+    let cube = cube_mesh(vec![
+        TextureInfo {
+            id: 0,
+            texture_type: TextureType::Diffuse,
+            path: format!("{}/resource/marble.jpg", env!("CARGO_MANIFEST_DIR")),
+        }
+    ]);
+    let floor = build_plane(-0.5f32, 5f32, 2f32, vec![
+        TextureInfo {
+            id: 0,
+            texture_type: TextureType::Diffuse,
+            path: format!("{}/resource/metal.png", env!("CARGO_MANIFEST_DIR")),
+        }
+    ]);
+    for position in vec![Vector3::new(-1f32, 0f32, -1f32), Vector3::new(2f32, 0f32, 0f32)] {
+        game.spawn_mesh(&cube, Transform {
+            position,
+            rotation: Rotation3::identity(),
+            scale: Vector3::new(1f32, 1f32, 1f32),
+        })?;
+    }
+    game.spawn_mesh(&floor, Transform::identity())?;
+    Ok(game)
+}
+
+#[test]
+fn depth_testing() -> Result<(), Error> {
+    let mut game = depth_testing_scene()?;
+    let image = render_game(&mut game, vec![])?;
+    golden("depth_testing").check(&image)
+}
+
+#[test]
+fn depth_testing_with_offscreen_system() -> Result<(), Error> {
+    let mut game = depth_testing_scene()?;
+    let system = OffscreenGreyscaleSystem::new()?;
+    let image = render_game(&mut game, vec![Box::new(system)])?;
+    golden("depth_testing_offscreen_greyscale").check(&image)
+}
+
+#[test]
+fn deferred_ssao() -> Result<(), Error> {
+    let mut game = depth_testing_scene()?;
+    game.set_rendering_path(RenderingPath::Deferred);
+    game.enable_ssao(SsaoSettings::default())?;
+    let image = render_game(&mut game, vec![])?;
+    golden("deferred_ssao").check(&image)
+}
+
+#[test]
+fn pbr_spheres() -> Result<(), Error> {
+    set_gl_error_policy(GlErrorPolicy::Panic);
+    let mut game = Game::new_headless(
+        WIDTH,
+        HEIGHT,
+        HeadlessOptions::new(FRAMES),
+        Vector3::new(0f32, 0f32, 0f32),
+        "28.1-pbr_vertex.glsl",
+        "28.1-pbr.glsl",
+        "17.1-uniform_buffer_objects_vertex.glsl",
+        "25.1-bloom_light_fragment.glsl",
+    )?;
+    fix_camera(&game, Vector3::new(0f32, 0f32, 8f32));
+    let mut light_cube = cube_mesh(vec![]);
+    light_cube.vertices = light_cube.vertices.iter().map(|v| v * 0.25).collect_vec();
+    for position in vec![
+        Vector3::new(-10f32, 10f32, 10f32),
+        Vector3::new(10f32, 10f32, 10f32),
+    ] {
+        game.spawn_light(PointLight::new(
+            position,
+            Vector3::zeros(),
+            Vector3::zeros(),
+            Vector3::new(300f32, 300f32, 300f32),
+            1f32,
+            0.7f32,
+            1.8f32,
+        ), &light_cube)?;
+    }
+    let sphere = sphere_mesh(vec![]);
+    for (i, roughness) in vec![0.1f32, 0.5f32, 0.9f32].into_iter().enumerate() {
+        let e = game.spawn_mesh(&sphere, Transform {
+            position: Vector3::new((i as f32 - 1f32) * 2.5f32, 0f32, 0f32),
+            rotation: Rotation3::identity(),
+            scale: Vector3::new(1f32, 1f32, 1f32),
+        })?;
+        game.add_to(e, vec![
+            ExtraUniform {
+                name: "metallic",
+                value: UniformValue::Float(0.5f32),
+            },
+            ExtraUniform {
+                name: "roughness",
+                value: UniformValue::Float(roughness),
+            },
+            ExtraUniform {
+                name: "ao",
+                value: UniformValue::Float(1f32),
+            },
+            ExtraUniform {
+                name: "albedo",
+                value: UniformValue::Vector3(Vector3::new(0.5f32, 0f32, 0f32)),
+            },
+        ])?;
+    }
+    let image = render_game(&mut game, vec![])?;
+    golden("pbr_spheres").check(&image)
+}